@@ -0,0 +1,278 @@
+use crate::domain::account::{AccountLimits, Amount, DisputeStats, LockCause};
+use crate::domain::currency::Currency;
+use crate::domain::transaction::TransactionType;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::BTreeMap;
+
+/// Which transaction types can be disputed.
+///
+/// - `Deposits`: Only deposits can be disputed (default).
+/// - `Withdrawals`: Only withdrawals can be disputed.
+/// - `Both`: Deposits and withdrawals can be disputed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DisputeEligibility {
+    #[default]
+    Deposits,
+    Withdrawals,
+    Both,
+}
+
+impl DisputeEligibility {
+    /// Returns `true` if transactions of the given type can be disputed.
+    pub fn allows(&self, r#type: TransactionType) -> bool {
+        matches!(
+            (self, r#type),
+            (Self::Deposits | Self::Both, TransactionType::Deposit)
+                | (Self::Withdrawals | Self::Both, TransactionType::Withdrawal)
+        )
+    }
+
+    /// Returns `true` if withdrawals can be disputed, so their records must be kept.
+    pub fn includes_withdrawals(&self) -> bool {
+        self.allows(TransactionType::Withdrawal)
+    }
+}
+
+/// What to do with a dispute when the client no longer has the funds to cover the hold.
+///
+/// - `Reject`: The dispute is rejected (default).
+/// - `AllowNegative`: The whole amount is held, letting the available balance go negative.
+/// - `HoldAvailable`: What is available is held, and the shortfall is recorded as owed by
+///   the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DisputeFundsPolicy {
+    #[default]
+    Reject,
+    AllowNegative,
+    HoldAvailable,
+}
+
+/// Which dispute operations still apply to a locked account, whose deposits, withdrawals and
+/// transfers are always refused.
+///
+/// - `Block`: None of them (default).
+/// - `Settle`: Resolves and chargebacks, so the disputes open when the account was locked can
+///   be settled.
+/// - `SettleAndDispute`: Resolves, chargebacks and new disputes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LockedDisputePolicy {
+    #[default]
+    Block,
+    Settle,
+    SettleAndDispute,
+}
+
+impl LockedDisputePolicy {
+    /// Returns `true` if a transaction of the given type applies to a locked account.
+    pub fn allows(&self, r#type: TransactionType) -> bool {
+        matches!(
+            (self, r#type),
+            (
+                Self::Settle | Self::SettleAndDispute,
+                TransactionType::Resolve | TransactionType::Chargeback
+            ) | (Self::SettleAndDispute, TransactionType::Dispute)
+        )
+    }
+}
+
+/// Dispute thresholds above which a client's account is locked (no threshold by default).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AutoLockPolicy {
+    /// Lock accounts with more than this many transactions under an open dispute.
+    pub max_open_disputes: Option<u32>,
+    /// Lock accounts having disputed more than this amount in a currency.
+    pub max_disputed_value: Option<Decimal>,
+}
+
+impl AutoLockPolicy {
+    /// Returns why an account with these dispute counters must be locked, if it must, after a
+    /// dispute in `currency`.
+    pub fn check(&self, stats: &DisputeStats, currency: Currency) -> Option<LockCause> {
+        if self.max_open_disputes.is_some_and(|max| stats.open > max) {
+            Some(LockCause::OpenDisputes)
+        } else if self
+            .max_disputed_value
+            .is_some_and(|max| stats.disputed_value(currency).0 > max)
+        {
+            Some(LockCause::DisputedValue)
+        } else {
+            None
+        }
+    }
+}
+
+/// What to do with amounts that have more decimal places than allowed.
+///
+/// - `Reject`: The transaction is rejected (default).
+/// - `Bankers`: Round half to even.
+/// - `HalfUp`: Round half away from zero.
+/// - `Truncate`: Drop the extra decimal places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum PrecisionMode {
+    #[default]
+    Reject,
+    Bankers,
+    HalfUp,
+    Truncate,
+}
+
+/// The maximum number of decimal places of input amounts, and how to enforce it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrecisionPolicy {
+    /// The maximum number of decimal places.
+    pub scale: u32,
+    /// How amounts with more decimal places are handled.
+    pub mode: PrecisionMode,
+}
+
+impl PrecisionPolicy {
+    /// Creates a policy allowing `scale` decimal places.
+    pub fn new(scale: u32, mode: PrecisionMode) -> Self {
+        Self { scale, mode }
+    }
+
+    /// Applies the policy to an amount.
+    ///
+    /// Trailing zeros do not count as decimal places. Returns `None` if the amount is
+    /// rejected, or if rounding it leaves nothing to transfer.
+    pub fn apply(&self, amount: Amount) -> Option<Amount> {
+        let value = amount.value();
+        if value.normalize().scale() <= self.scale {
+            return Some(amount);
+        }
+        let strategy = match self.mode {
+            PrecisionMode::Reject => return None,
+            PrecisionMode::Bankers => RoundingStrategy::MidpointNearestEven,
+            PrecisionMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            PrecisionMode::Truncate => RoundingStrategy::ToZero,
+        };
+        Amount::new(value.round_dp_with_strategy(self.scale, strategy)).ok()
+    }
+}
+
+/// Policies that tune how the `PaymentEngine` applies transactions.
+#[derive(Debug, Clone, Default)]
+pub struct EngineConfig {
+    /// Which transaction types can be disputed.
+    pub dispute_eligibility: DisputeEligibility,
+    /// What to do with disputes the available funds cannot cover.
+    pub dispute_funds: DisputeFundsPolicy,
+    /// The dispute thresholds locking an account.
+    pub auto_lock: AutoLockPolicy,
+    /// Which dispute operations still apply to locked accounts.
+    pub locked_disputes: LockedDisputePolicy,
+    /// Record the time at which accounts are locked in their lock reason.
+    pub lock_timestamps: bool,
+    /// Refuse to persist accounts violating their invariants, failing the transaction instead.
+    pub strict_invariants: bool,
+    /// The precision enforced on input amounts (any precision is accepted when unset).
+    pub precision: Option<PrecisionPolicy>,
+    /// Credit limits given to the accounts of these clients when they are opened.
+    pub limits: BTreeMap<u16, AccountLimits>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::account::Balance;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_dispute_eligibility() {
+        assert!(DisputeEligibility::Deposits.allows(TransactionType::Deposit));
+        assert!(!DisputeEligibility::Deposits.allows(TransactionType::Withdrawal));
+        assert!(!DisputeEligibility::Withdrawals.allows(TransactionType::Deposit));
+        assert!(DisputeEligibility::Withdrawals.allows(TransactionType::Withdrawal));
+        assert!(DisputeEligibility::Both.allows(TransactionType::Deposit));
+        assert!(DisputeEligibility::Both.allows(TransactionType::Withdrawal));
+        assert!(!DisputeEligibility::Both.allows(TransactionType::Dispute));
+    }
+
+    #[test]
+    fn test_precision_policy() {
+        let amount = |value| Amount::new(value).unwrap();
+        let policy = |mode| PrecisionPolicy::new(4, mode);
+
+        // Trailing zeros are not extra precision
+        assert_eq!(
+            policy(PrecisionMode::Reject).apply(amount(dec!(1.50000))),
+            Some(amount(dec!(1.50000)))
+        );
+        assert_eq!(
+            policy(PrecisionMode::Reject).apply(amount(dec!(1.00005))),
+            None
+        );
+        assert_eq!(
+            policy(PrecisionMode::Bankers).apply(amount(dec!(1.00005))),
+            Some(amount(dec!(1.0000)))
+        );
+        assert_eq!(
+            policy(PrecisionMode::Bankers).apply(amount(dec!(1.00015))),
+            Some(amount(dec!(1.0002)))
+        );
+        assert_eq!(
+            policy(PrecisionMode::HalfUp).apply(amount(dec!(1.00005))),
+            Some(amount(dec!(1.0001)))
+        );
+        assert_eq!(
+            policy(PrecisionMode::Truncate).apply(amount(dec!(1.00009))),
+            Some(amount(dec!(1.0000)))
+        );
+        // Rounding down to zero leaves no amount
+        assert_eq!(
+            policy(PrecisionMode::Truncate).apply(amount(dec!(0.00009))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_locked_dispute_policy() {
+        let block = LockedDisputePolicy::Block;
+        assert!(!block.allows(TransactionType::Resolve));
+        assert!(!block.allows(TransactionType::Chargeback));
+
+        let settle = LockedDisputePolicy::Settle;
+        assert!(settle.allows(TransactionType::Resolve));
+        assert!(settle.allows(TransactionType::Chargeback));
+        assert!(!settle.allows(TransactionType::Dispute));
+
+        let dispute = LockedDisputePolicy::SettleAndDispute;
+        assert!(dispute.allows(TransactionType::Dispute));
+        assert!(!dispute.allows(TransactionType::Deposit));
+        assert!(!dispute.allows(TransactionType::Withdrawal));
+    }
+
+    #[test]
+    fn test_auto_lock_policy() {
+        let currency = Currency::default();
+        let mut stats = DisputeStats::default();
+        stats
+            .record_dispute(currency, Balance::new(dec!(100)), true)
+            .unwrap();
+        stats
+            .record_dispute(currency, Balance::new(dec!(50)), true)
+            .unwrap();
+
+        assert_eq!(AutoLockPolicy::default().check(&stats, currency), None);
+        let by_count = AutoLockPolicy {
+            max_open_disputes: Some(1),
+            ..AutoLockPolicy::default()
+        };
+        assert_eq!(
+            by_count.check(&stats, currency),
+            Some(LockCause::OpenDisputes)
+        );
+        let by_value = AutoLockPolicy {
+            max_disputed_value: Some(dec!(150)),
+            ..AutoLockPolicy::default()
+        };
+        assert_eq!(by_value.check(&stats, currency), None);
+        stats
+            .record_dispute(currency, Balance::new(dec!(0.01)), false)
+            .unwrap();
+        assert_eq!(
+            by_value.check(&stats, currency),
+            Some(LockCause::DisputedValue)
+        );
+    }
+}
@@ -1,7 +1,26 @@
-use crate::domain::account::ClientAccount;
-use crate::domain::ports::{AccountStoreBox, TransactionStoreBox};
-use crate::domain::transaction::{DisputeStatus, Transaction, TransactionType};
-use crate::error::Result;
+use crate::application::config::{DisputeFundsPolicy, EngineConfig};
+use crate::domain::account::{AccountStatus, Balance, ClientAccount, LockCause, LockReason};
+use crate::domain::checkpoint::{Checkpoint, Fingerprint, InputPosition};
+use crate::domain::currency::Currency;
+use crate::domain::dispute::{Dispute, DisputeRecord, DisputeStatus};
+use crate::domain::event::{self, DomainEvent, EventRecord};
+use crate::domain::fee::{FeeAccount, FeeSchedule};
+use crate::domain::ids::IdBitmap;
+use crate::domain::invariant::{self, Invariant, InvariantViolation};
+use crate::domain::ledger::{self, JournalEntry, LedgerAccount, LedgerMismatch};
+use crate::domain::outcome::{RejectionReason, TransactionOutcome};
+use crate::domain::ports::{
+    AccountStoreBox, CheckpointStoreBox, EventStoreBox, FeeStoreBox, JournalStoreBox, RiskStoreBox,
+    SnapshotStoreBox, TransactionStoreBox, UnitOfWork, UnitOfWorkStoreBox,
+};
+use crate::domain::risk::{Activity, RiskDecision, RiskFlag, RiskRuleBox, RiskState};
+use crate::domain::snapshot::Snapshot;
+use crate::domain::transaction::{Transaction, TransactionType};
+use crate::error::{PaymentError, Result};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// The main entry point for the transaction processing application.
 ///
@@ -11,6 +30,199 @@ use crate::error::Result;
 pub struct PaymentEngine {
     account_store: AccountStoreBox,
     transaction_store: TransactionStoreBox,
+    /// Commits the account, transaction, fee, journal and event writes together; they are
+    /// written one after the other without it.
+    unit_of_work: Option<UnitOfWorkStoreBox>,
+    journal_store: Option<JournalStoreBox>,
+    event_store: Option<EventStoreBox>,
+    snapshot_store: Option<SnapshotStoreBox>,
+    checkpoint_store: Option<CheckpointStoreBox>,
+    /// Number of transactions between two snapshots (0 disables periodic snapshots).
+    snapshot_interval: u64,
+    /// Number of input transactions processed so far, including those of a restored snapshot.
+    position: AtomicU64,
+    /// The writes committed since the last snapshot, which the next one only has to hold, or
+    /// `None` while the next snapshot must be a full one.
+    snapshot_changes: Mutex<Option<SnapshotChanges>>,
+    /// Invariant violations let through outside of strict mode.
+    violations: Mutex<Vec<InvariantViolation>>,
+    risk_rules: Vec<RiskRuleBox>,
+    risk_store: Option<RiskStoreBox>,
+    /// Applied transactions flagged for review by the risk rules.
+    risk_flags: Mutex<Vec<RiskFlag>>,
+    fee_schedule: FeeSchedule,
+    fee_store: Option<FeeStoreBox>,
+    config: EngineConfig,
+}
+
+/// Side effects of applying a transaction, persisted by `process_transaction` together with
+/// the client's account.
+#[derive(Default)]
+struct Effects {
+    /// Another client's account modified by the transaction (transfers).
+    counterparty: Option<ClientAccount>,
+    /// Transaction records to persist with the accounts.
+    transactions: Vec<Transaction>,
+    /// IDs of transactions that had no effect, recorded for duplicate detection only.
+    transaction_ids: Vec<u32>,
+    /// Dispute records of the transactions disputed, resolved or charged back.
+    disputes: Vec<DisputeRecord>,
+    /// Journal entries recording the balance movements.
+    journal: Vec<JournalEntry>,
+    /// Domain events of the affected accounts, in the order they happened.
+    events: Vec<EventRecord>,
+    /// Fees collected from the client, credited to the fee account.
+    fees: Vec<(Currency, Balance)>,
+}
+
+/// The accounts, transaction records, dispute records and transaction IDs committed since the
+/// last snapshot.
+#[derive(Default)]
+struct SnapshotChanges {
+    accounts: BTreeMap<u16, ClientAccount>,
+    transactions: Vec<Transaction>,
+    disputes: BTreeMap<u32, DisputeRecord>,
+    transaction_ids: IdBitmap,
+}
+
+impl SnapshotChanges {
+    /// Collects the changes of a unit of work.
+    fn of(work: &UnitOfWork) -> Self {
+        Self {
+            accounts: work
+                .accounts
+                .iter()
+                .map(|account| (account.client, account.clone()))
+                .collect(),
+            transactions: work.transactions.clone(),
+            disputes: work
+                .disputes
+                .iter()
+                .map(|record| (record.tx, record.clone()))
+                .collect(),
+            transaction_ids: work
+                .transactions
+                .iter()
+                .map(|tx| tx.tx)
+                .chain(work.transaction_ids.iter().copied())
+                .collect(),
+        }
+    }
+
+    /// Adds later changes, which replace the earlier ones of the same account or transaction.
+    fn merge(&mut self, later: SnapshotChanges) {
+        self.accounts.extend(later.accounts);
+        self.transactions.extend(later.transactions);
+        self.disputes.extend(later.disputes);
+        self.transaction_ids.extend(later.transaction_ids.iter());
+    }
+
+    /// Returns the incremental snapshot of the changes.
+    fn into_snapshot(self, position: u64, fees: FeeAccount) -> Snapshot {
+        Snapshot {
+            position,
+            incremental: true,
+            accounts: self.accounts.into_values().collect(),
+            transactions: self.transactions,
+            disputes: self.disputes.into_values().collect(),
+            transaction_ids: self.transaction_ids,
+            fees,
+        }
+    }
+}
+
+impl Effects {
+    /// Records a movement of funds between two ledger accounts.
+    fn post(
+        &mut self,
+        tx: u32,
+        currency: Currency,
+        debit: LedgerAccount,
+        credit: LedgerAccount,
+        amount: Balance,
+    ) {
+        self.journal
+            .push(JournalEntry::new(tx, currency, debit, credit, amount));
+    }
+
+    /// Records a transaction to persist with the accounts.
+    fn store(&mut self, tx: Transaction) {
+        self.transactions.push(tx);
+    }
+
+    /// Records the ID of a transaction that had no effect, so a retry is a duplicate.
+    fn store_id(&mut self, tx: u32) {
+        self.transaction_ids.push(tx);
+    }
+
+    /// Records the disputes of a transaction to persist with the accounts.
+    fn dispute(&mut self, record: DisputeRecord) {
+        self.disputes.push(record);
+    }
+
+    /// Records an event of a client account.
+    fn record(&mut self, client: u16, tx: u32, event: DomainEvent) {
+        self.events.push(EventRecord::new(client, tx, event));
+    }
+
+    /// Records a fee debited from a client's available balance.
+    fn charge(&mut self, client: u16, tx: u32, currency: Currency, fee: Balance) {
+        if fee == Balance::ZERO {
+            return;
+        }
+        self.record(
+            client,
+            tx,
+            DomainEvent::FeeCharged {
+                currency,
+                amount: fee,
+            },
+        );
+        self.post(
+            tx,
+            currency,
+            LedgerAccount::Available(client),
+            LedgerAccount::Fees,
+            fee,
+        );
+        self.fees.push((currency, fee));
+    }
+
+    /// Records the current dispute counters of a client account.
+    fn stats(&mut self, account: &ClientAccount, tx: u32) {
+        self.record(
+            account.client,
+            tx,
+            DomainEvent::DisputeStatsChanged {
+                stats: account.disputes.clone(),
+            },
+        );
+    }
+
+    /// Records the locking of a client account.
+    fn lock(&mut self, account: &ClientAccount, tx: u32) {
+        self.record(
+            account.client,
+            tx,
+            DomainEvent::AccountLocked {
+                reason: account.lock_reason,
+            },
+        );
+    }
+
+    /// Records the opening of a new client account, along with its configured limits.
+    fn open(&mut self, account: &ClientAccount, tx: u32) {
+        self.record(account.client, tx, DomainEvent::AccountOpened);
+        if !account.limits.is_default() {
+            self.record(
+                account.client,
+                tx,
+                DomainEvent::LimitsChanged {
+                    limits: account.limits,
+                },
+            );
+        }
+    }
 }
 
 impl PaymentEngine {
@@ -21,99 +233,1214 @@ impl PaymentEngine {
     /// * `account_store` - The store for client accounts.
     /// * `transaction_store` - The store for transaction history.
     pub fn new(account_store: AccountStoreBox, transaction_store: TransactionStoreBox) -> Self {
+        Self::with_config(account_store, transaction_store, EngineConfig::default())
+    }
+
+    /// Creates a new `PaymentEngine` instance with custom processing policies.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_store` - The store for client accounts.
+    /// * `transaction_store` - The store for transaction history.
+    /// * `config` - The policies applied while processing transactions.
+    pub fn with_config(
+        account_store: AccountStoreBox,
+        transaction_store: TransactionStoreBox,
+        config: EngineConfig,
+    ) -> Self {
         Self {
             account_store,
             transaction_store,
+            unit_of_work: None,
+            journal_store: None,
+            event_store: None,
+            snapshot_store: None,
+            checkpoint_store: None,
+            snapshot_interval: 0,
+            position: AtomicU64::new(0),
+            snapshot_changes: Mutex::new(None),
+            violations: Mutex::new(Vec::new()),
+            risk_rules: Vec::new(),
+            risk_store: None,
+            risk_flags: Mutex::new(Vec::new()),
+            fee_schedule: FeeSchedule::default(),
+            fee_store: None,
+            config,
         }
     }
 
+    /// Commits the account, transaction, fee, journal and event writes of each transaction
+    /// through `unit_of_work`, which must be backed by the same data as the other stores.
+    pub fn with_unit_of_work(mut self, unit_of_work: UnitOfWorkStoreBox) -> Self {
+        self.unit_of_work = Some(unit_of_work);
+        self
+    }
+
+    /// Enables the double-entry journal, posting every balance movement to `journal_store`.
+    pub fn with_journal(mut self, journal_store: JournalStoreBox) -> Self {
+        self.journal_store = Some(journal_store);
+        self
+    }
+
+    /// Enables the event-sourced mode.
+    ///
+    /// Every applied transaction appends its domain events to `event_store`, and the accounts
+    /// persisted in the account store are derived by folding these events over the stored
+    /// state, rather than being the result of mutating it directly. The account store then
+    /// only acts as a projection of the log, which [`Self::replay`] can rebuild.
+    pub fn with_event_log(mut self, event_store: EventStoreBox) -> Self {
+        self.event_store = Some(event_store);
+        self
+    }
+
+    /// Enables snapshots, saving one to `snapshot_store` every `interval` transactions.
+    ///
+    /// An `interval` of 0 disables periodic snapshots, leaving only explicit calls to
+    /// [`Self::save_snapshot`].
+    pub fn with_snapshots(mut self, snapshot_store: SnapshotStoreBox, interval: u64) -> Self {
+        self.snapshot_store = Some(snapshot_store);
+        self.snapshot_interval = interval;
+        self
+    }
+
+    /// Enables input checkpoints, kept in `checkpoint_store`.
+    ///
+    /// The checkpoints given to [`Self::process_checkpointed`] are committed with the writes of
+    /// their transaction, so `checkpoint_store` must be backed by the same data as the unit of
+    /// work store, if any.
+    pub fn with_checkpoints(mut self, checkpoint_store: CheckpointStoreBox) -> Self {
+        self.checkpoint_store = Some(checkpoint_store);
+        self
+    }
+
+    /// Enables the risk rules, evaluated in order before a transaction is applied.
+    ///
+    /// The first rule rejecting a transaction stops it, while flagged transactions are applied
+    /// and reported by [`Self::risk_flags`]. The recent activity of each client, which the
+    /// rules are evaluated against, is kept in `risk_store`.
+    pub fn with_risk_rules(mut self, rules: Vec<RiskRuleBox>, risk_store: RiskStoreBox) -> Self {
+        self.risk_rules = rules;
+        self.risk_store = Some(risk_store);
+        self
+    }
+
+    /// Enables the fee schedule, crediting the collected fees to the account in `fee_store`.
+    ///
+    /// Withdrawals and transfers are only applied if the client can pay both the amount and
+    /// the fee, which are debited together. Chargebacks charge their fee on what is left of the
+    /// available balance, down to the client's floor, since they cannot be refused.
+    pub fn with_fees(mut self, schedule: FeeSchedule, fee_store: FeeStoreBox) -> Self {
+        self.fee_schedule = schedule;
+        self.fee_store = Some(fee_store);
+        self
+    }
+
     /// Submits a transaction for processing.
     ///
     /// This method processes the transaction and persists the results directly.
     /// It ensures sequential consistency by awaiting storage operations.
-    pub async fn process_transaction(&self, tx: Transaction) -> Result<()> {
-        let mut account = self
-            .account_store
-            .get(tx.client)
-            .await?
-            .unwrap_or_else(|| ClientAccount::new(tx.client));
+    ///
+    /// Returns whether the transaction was applied or, if it had no effect, why it was rejected.
+    /// Errors are reserved for storage failures and, in strict mode, for accounts violating
+    /// their invariants, which are then not persisted.
+    pub async fn process_transaction(&self, tx: Transaction) -> Result<TransactionOutcome> {
+        self.process_at(tx, None).await
+    }
+
+    /// Submits a transaction read from the input fingerprinted `fingerprint`, ending at `end`.
+    ///
+    /// The checkpoint right after the transaction is committed with its writes, or alone if
+    /// the transaction wrote nothing, so a crash never separates them: resuming from the latest
+    /// checkpoint applies every transaction exactly once. It is not committed if processing
+    /// fails. Fails if checkpoints are not enabled (see [`Self::with_checkpoints`]).
+    pub async fn process_checkpointed(
+        &self,
+        tx: Transaction,
+        fingerprint: Fingerprint,
+        end: InputPosition,
+    ) -> Result<TransactionOutcome> {
+        self.checkpoint_store()?;
+        let checkpoint = Checkpoint {
+            fingerprint,
+            input: end,
+            position: self.position() + 1,
+        };
+        self.process_at(tx, Some(checkpoint)).await
+    }
+
+    async fn process_at(
+        &self,
+        tx: Transaction,
+        mut checkpoint: Option<Checkpoint>,
+    ) -> Result<TransactionOutcome> {
+        let mut outcome = self.process(tx, &mut checkpoint).await;
+        // A transaction rejected before any write still moves the checkpoint
+        if let (Some(checkpoint), Ok(_)) = (checkpoint, &outcome)
+            && let Err(e) = self.save_checkpoint(checkpoint).await
+        {
+            outcome = Err(e);
+        }
+
+        let position = self.position.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.snapshot_interval > 0 && position.is_multiple_of(self.snapshot_interval) {
+            self.save_snapshot().await?;
+        }
+        outcome
+    }
+
+    async fn process(
+        &self,
+        mut tx: Transaction,
+        checkpoint: &mut Option<Checkpoint>,
+    ) -> Result<TransactionOutcome> {
+        // Enforce the precision policy before the amount is used or recorded
+        if let (Some(policy), Some(amount)) = (self.config.precision, tx.amount) {
+            match policy.apply(amount) {
+                Some(amount) => tx.amount = Some(amount),
+                None => {
+                    return Ok(TransactionOutcome::rejected(
+                        RejectionReason::ExcessPrecision,
+                    ));
+                }
+            }
+        }
+
+        let stored = self.account_store.get(tx.client).await?;
+        let opened = stored.is_none();
+        let mut account = stored.unwrap_or_else(|| self.new_account(tx.client));
+
+        // Skip if the account status does not accept this transaction type, unless the policy
+        // lets disputes go on after a lock
+        let accepted = account.status.accepts(tx.r#type)
+            || (account.status == AccountStatus::Locked
+                && self.config.locked_disputes.allows(tx.r#type));
+        if !accepted {
+            return Ok(TransactionOutcome::rejected(status_rejection(
+                account.status,
+            )));
+        }
+
+        // Evaluate the risk rules before the account is touched
+        let position = self.position.load(Ordering::SeqCst);
+        let (risk_state, flagged) = match &self.risk_store {
+            Some(risk_store) => {
+                let state = risk_store
+                    .get(tx.client)
+                    .await?
+                    .unwrap_or_else(|| RiskState::new(tx.client));
+                let mut flagged = Vec::new();
+                for rule in &self.risk_rules {
+                    match rule.evaluate(&tx, &state, position) {
+                        RiskDecision::Allow => {}
+                        RiskDecision::Reject { .. } => {
+                            return Ok(TransactionOutcome::rejected(RejectionReason::RiskRejected));
+                        }
+                        RiskDecision::Flag { rule } => flagged.push(rule),
+                    }
+                }
+                (Some(state), flagged)
+            }
+            None => (None, Vec::new()),
+        };
+        let (client, tx_id, r#type, amount) = (tx.client, tx.tx, tx.r#type, tx.amount);
+
+        let mut effects = Effects::default();
+        if opened {
+            effects.open(&account, tx.tx);
+        }
+        let outcome = self.apply(&mut account, &mut effects, tx).await?;
+
+        // In event-sourced mode, the accounts are derived from the events, which are logged
+        // along with the accounts
+        let log_events = self.event_store.is_some() && !effects.events.is_empty();
+        if log_events {
+            account = self.project(account.client, &effects.events).await?;
+            if let Some(counterparty) = effects.counterparty.as_mut() {
+                *counterparty = self.project(counterparty.client, &effects.events).await?;
+            }
+        }
 
-        // Skip if account is locked
-        if account.status == crate::domain::account::AccountStatus::Locked {
-            return Ok(());
+        // Validate every account before it is persisted
+        let mut violations = self.check_invariants(&account);
+        if let Some(counterparty) = &effects.counterparty {
+            violations.extend(self.check_invariants(counterparty));
         }
+        if !violations.is_empty() {
+            if self.config.strict_invariants {
+                return Err(PaymentError::InvariantViolation(violations));
+            }
+            self.violations
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .extend(violations);
+        }
+
+        // The fees collected are credited to the fee account, persisted with the accounts
+        let fees = match &self.fee_store {
+            Some(fee_store) if !effects.fees.is_empty() => {
+                let mut fee_account = fee_store.get().await?;
+                for (currency, fee) in effects.fees {
+                    fee_account.credit(currency, fee)?;
+                }
+                Some(fee_account)
+            }
+            _ => None,
+        };
+
+        // Remember the funds movements of the client for the next evaluations
+        let risk = risk_state
+            .filter(|_| outcome.is_applied())
+            .zip(amount.filter(|_| {
+                matches!(
+                    r#type,
+                    TransactionType::Deposit
+                        | TransactionType::Withdrawal
+                        | TransactionType::Transfer
+                )
+            }))
+            .map(|(mut state, amount)| {
+                let window = self.risk_rules.iter().map(|rule| rule.window()).max();
+                state.prune(position, window.unwrap_or(0));
+                state.activity.push(Activity {
+                    position,
+                    r#type,
+                    amount: amount.into(),
+                });
+                state
+            });
+
+        // The accounts (both sides of a transfer) are persisted with the transaction records
+        let mut accounts = vec![account];
+        accounts.extend(effects.counterparty);
+        let work = UnitOfWork {
+            accounts,
+            transactions: effects.transactions,
+            transaction_ids: effects.transaction_ids,
+            disputes: effects.disputes,
+            fees,
+            risk,
+            journal: if self.journal_store.is_some() {
+                effects.journal
+            } else {
+                Vec::new()
+            },
+            events: if log_events {
+                effects.events
+            } else {
+                Vec::new()
+            },
+            checkpoint: checkpoint.take(),
+        };
+        let changes = self.tracks_changes().then(|| SnapshotChanges::of(&work));
+        self.commit(work).await?;
+        if let Some(changes) = changes {
+            self.record_changes(changes);
+        }
+
+        if outcome.is_applied() {
+            self.risk_flags
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .extend(flagged.into_iter().map(|rule| RiskFlag {
+                    client,
+                    tx: tx_id,
+                    rule,
+                }));
+        }
+        Ok(outcome)
+    }
 
+    /// Applies a transaction to the given account.
+    ///
+    /// Everything else the transaction produces (transaction records, another client's account
+    /// for transfers, journal entries) is collected in `effects`, so the caller can persist it
+    /// together.
+    async fn apply(
+        &self,
+        account: &mut ClientAccount,
+        effects: &mut Effects,
+        tx: Transaction,
+    ) -> Result<TransactionOutcome> {
+        let client = account.client;
         match tx.r#type {
+            TransactionType::Freeze | TransactionType::Unlock | TransactionType::Close => {
+                let result = match tx.r#type {
+                    TransactionType::Freeze => account.freeze(),
+                    TransactionType::Unlock => account.unlock(),
+                    _ => account.close(),
+                };
+                if result.is_err() {
+                    return Ok(TransactionOutcome::rejected(
+                        RejectionReason::InvalidStatusTransition,
+                    ));
+                }
+                let event = match tx.r#type {
+                    TransactionType::Freeze => DomainEvent::AccountFrozen,
+                    TransactionType::Unlock => DomainEvent::AccountUnlocked,
+                    _ => DomainEvent::AccountClosed,
+                };
+                effects.record(client, tx.tx, event);
+                Ok(TransactionOutcome::Applied)
+            }
+            TransactionType::SetOverdraft | TransactionType::SetReserve => {
+                // Without an amount, the limit is removed
+                let value = tx.amount.map_or(Balance::ZERO, Balance::from);
+                if tx.r#type == TransactionType::SetOverdraft {
+                    account.limits.overdraft = value;
+                } else {
+                    account.limits.reserve = value;
+                }
+                effects.record(
+                    client,
+                    tx.tx,
+                    DomainEvent::LimitsChanged {
+                        limits: account.limits,
+                    },
+                );
+                Ok(TransactionOutcome::Applied)
+            }
             TransactionType::Deposit => {
-                if let Some(amount) = tx.amount {
-                    // Ignore duplicate transaction IDs
-                    if !self.transaction_store.exists(tx.tx).await? {
-                        account.deposit(amount.into());
-                        self.transaction_store.store(tx).await?;
-                    }
+                let Some(amount) = tx.amount else {
+                    return Ok(TransactionOutcome::rejected(RejectionReason::MissingAmount));
+                };
+                // Ignore duplicate transaction IDs
+                if self.transaction_store.exists(tx.tx).await? {
+                    return Ok(TransactionOutcome::rejected(
+                        RejectionReason::DuplicateTransaction,
+                    ));
+                }
+                if let Err(e) = account.deposit(tx.currency, amount.into()) {
+                    return Ok(TransactionOutcome::rejected(balance_rejection(&e)));
                 }
+                effects.record(
+                    client,
+                    tx.tx,
+                    DomainEvent::Deposited {
+                        currency: tx.currency,
+                        amount: amount.into(),
+                    },
+                );
+                effects.post(
+                    tx.tx,
+                    tx.currency,
+                    LedgerAccount::Cash,
+                    LedgerAccount::Available(client),
+                    amount.into(),
+                );
+                effects.store(tx);
+                Ok(TransactionOutcome::Applied)
             }
             TransactionType::Withdrawal => {
-                if let Some(amount) = tx.amount {
-                    // Ignore duplicate transaction IDs
-                    if !self.transaction_store.exists(tx.tx).await? {
-                        let _ = account.withdraw(amount.into());
-                        self.transaction_store.store(tx).await?;
-                    }
+                let Some(amount) = tx.amount else {
+                    return Ok(TransactionOutcome::rejected(RejectionReason::MissingAmount));
+                };
+                // Ignore duplicate transaction IDs
+                if self.transaction_store.exists(tx.tx).await? {
+                    return Ok(TransactionOutcome::rejected(
+                        RejectionReason::DuplicateTransaction,
+                    ));
+                }
+                // The fee is debited together with the amount, so neither is taken alone.
+                let result = self
+                    .fee_schedule
+                    .fee(tx.r#type, amount.into())
+                    .and_then(|fee| {
+                        let debit = Balance::from(amount).checked_add(fee)?;
+                        account.withdraw(tx.currency, debit).map(|()| fee)
+                    });
+                if let Ok(fee) = result {
+                    effects.record(
+                        client,
+                        tx.tx,
+                        DomainEvent::Withdrawn {
+                            currency: tx.currency,
+                            amount: amount.into(),
+                        },
+                    );
+                    effects.post(
+                        tx.tx,
+                        tx.currency,
+                        LedgerAccount::Available(client),
+                        LedgerAccount::Cash,
+                        amount.into(),
+                    );
+                    effects.charge(client, tx.tx, tx.currency, fee);
+                    effects.store(tx);
+                } else {
+                    // Only the ID of a bounced withdrawal is recorded: a retry is a duplicate,
+                    // but there are no funds to dispute
+                    effects.store_id(tx.tx);
+                }
+                match result {
+                    Ok(_) => Ok(TransactionOutcome::Applied),
+                    Err(e) => Ok(TransactionOutcome::rejected(balance_rejection(&e))),
+                }
+            }
+            TransactionType::Transfer => {
+                let Some(amount) = tx.amount else {
+                    return Ok(TransactionOutcome::rejected(RejectionReason::MissingAmount));
+                };
+                let Some(destination_id) = tx.destination.filter(|&d| d != tx.client) else {
+                    return Ok(TransactionOutcome::rejected(
+                        RejectionReason::InvalidDestination,
+                    ));
+                };
+                // Ignore duplicate transaction IDs
+                if self.transaction_store.exists(tx.tx).await? {
+                    return Ok(TransactionOutcome::rejected(
+                        RejectionReason::DuplicateTransaction,
+                    ));
+                }
+                let stored_destination = self.account_store.get(destination_id).await?;
+                let destination_opened = stored_destination.is_none();
+                let mut destination =
+                    stored_destination.unwrap_or_else(|| self.new_account(destination_id));
+                if !destination.status.accepts_incoming() {
+                    return Ok(TransactionOutcome::rejected(status_rejection(
+                        destination.status,
+                    )));
+                }
+                // Credit the destination first: the source account is persisted even if the
+                // transfer is rejected, so it must only be debited once nothing else can fail
+                if let Err(e) = destination.deposit(tx.currency, amount.into()) {
+                    return Ok(TransactionOutcome::rejected(balance_rejection(&e)));
+                }
+                let result = self
+                    .fee_schedule
+                    .fee(tx.r#type, amount.into())
+                    .and_then(|fee| {
+                        let debit = Balance::from(amount).checked_add(fee)?;
+                        account.withdraw(tx.currency, debit).map(|()| fee)
+                    });
+                let fee = match result {
+                    Ok(fee) => fee,
+                    Err(e) => return Ok(TransactionOutcome::rejected(balance_rejection(&e))),
+                };
+                effects.record(
+                    client,
+                    tx.tx,
+                    DomainEvent::Withdrawn {
+                        currency: tx.currency,
+                        amount: amount.into(),
+                    },
+                );
+                if destination_opened {
+                    effects.open(&destination, tx.tx);
                 }
+                effects.record(
+                    destination_id,
+                    tx.tx,
+                    DomainEvent::Deposited {
+                        currency: tx.currency,
+                        amount: amount.into(),
+                    },
+                );
+                effects.post(
+                    tx.tx,
+                    tx.currency,
+                    LedgerAccount::Available(client),
+                    LedgerAccount::Available(destination_id),
+                    amount.into(),
+                );
+                effects.charge(client, tx.tx, tx.currency, fee);
+                effects.store(tx);
+                effects.counterparty = Some(destination);
+                Ok(TransactionOutcome::Applied)
             }
             TransactionType::Dispute => {
-                if let Some(mut original_tx) = self.transaction_store.get(tx.tx).await?
-                    && original_tx.r#type == TransactionType::Deposit
-                    && original_tx.client == tx.client
-                    && original_tx.dispute_status == DisputeStatus::None
-                    && let Some(amount) = original_tx.amount
-                    && account.hold(amount.into()).is_ok()
+                let original_tx = match self.referenced_transaction(&tx).await? {
+                    Ok(original_tx) => original_tx,
+                    Err(reason) => return Ok(TransactionOutcome::rejected(reason)),
+                };
+                if !self.config.dispute_eligibility.allows(original_tx.r#type) {
+                    return Ok(TransactionOutcome::rejected(RejectionReason::NotDisputable));
+                }
+                let Some(original_amount) = original_tx.amount else {
+                    return Ok(TransactionOutcome::rejected(RejectionReason::MissingAmount));
+                };
+                let mut record = self
+                    .transaction_store
+                    .get_disputes(original_tx.tx)
+                    .await?
+                    .unwrap_or_else(|| DisputeRecord::new(original_tx.tx));
+                let undisputed = match record
+                    .total_disputed()
+                    .and_then(|disputed| Balance::from(original_amount).checked_sub(disputed))
+                {
+                    Ok(undisputed) => undisputed,
+                    Err(e) => return Ok(TransactionOutcome::rejected(balance_rejection(&e))),
+                };
+                if undisputed <= Balance::ZERO {
+                    return Ok(TransactionOutcome::rejected(
+                        RejectionReason::InvalidDisputeState,
+                    ));
+                }
+                // Without an amount, the dispute covers the whole undisputed remainder
+                let amount = tx.amount.map_or(undisputed, Balance::from);
+                if amount > undisputed {
+                    return Ok(TransactionOutcome::rejected(
+                        RejectionReason::InvalidDisputeAmount,
+                    ));
+                }
+                // A disputed withdrawal credits the withdrawn funds back as held
+                let withdrawal = original_tx.r#type == TransactionType::Withdrawal;
+                let currency = original_tx.currency;
+                let mut stats = account.disputes.clone();
+                let opened = !record.has_open();
+                if let Err(e) = stats.record_dispute(currency, amount, opened) {
+                    return Ok(TransactionOutcome::rejected(balance_rejection(&e)));
+                }
+                let headroom = account.headroom(currency);
+                let split = match self.config.dispute_funds {
+                    DisputeFundsPolicy::HoldAvailable if !withdrawal && amount > headroom => amount
+                        .checked_sub(headroom)
+                        .map(|shortfall| (headroom, shortfall)),
+                    _ => Ok((amount, Balance::ZERO)),
+                };
+                let (held, shortfall) = match split {
+                    Ok(split) => split,
+                    Err(e) => return Ok(TransactionOutcome::rejected(balance_rejection(&e))),
+                };
+                let overdrawn = !withdrawal
+                    && amount > headroom
+                    && self.config.dispute_funds == DisputeFundsPolicy::AllowNegative;
+                // Both steps are applied to a copy so a failed second step
+                // leaves the account untouched
+                let mut staged = account.clone();
+                let (result, source) = if withdrawal {
+                    (
+                        staged.hold_withdrawal(currency, held),
+                        LedgerAccount::ChargebackLosses,
+                    )
+                } else if overdrawn {
+                    (
+                        staged.hold_overdrawn(currency, held),
+                        LedgerAccount::Available(client),
+                    )
+                } else {
+                    (
+                        staged.hold(currency, held),
+                        LedgerAccount::Available(client),
+                    )
+                };
+                if let Err(e) = result.and_then(|()| staged.add_receivable(currency, shortfall)) {
+                    return Ok(TransactionOutcome::rejected(balance_rejection(&e)));
+                }
+                *account = staged;
+                if overdrawn {
+                    effects.record(
+                        client,
+                        tx.tx,
+                        DomainEvent::FundsHeldOverdrawn {
+                            currency,
+                            amount: held,
+                        },
+                    );
+                } else if held > Balance::ZERO {
+                    effects.record(
+                        client,
+                        tx.tx,
+                        DomainEvent::FundsHeld {
+                            currency,
+                            amount: held,
+                            withdrawal,
+                        },
+                    );
+                }
+                if held > Balance::ZERO {
+                    effects.post(tx.tx, currency, source, LedgerAccount::Held(client), held);
+                }
+                if shortfall > Balance::ZERO {
+                    effects.record(
+                        client,
+                        tx.tx,
+                        DomainEvent::ReceivableRecorded {
+                            currency,
+                            amount: shortfall,
+                        },
+                    );
+                }
+                // Count the dispute, and lock the account once it crosses a threshold
+                account.disputes = stats;
+                effects.stats(account, tx.tx);
+                if account.status == AccountStatus::Active
+                    && let Some(cause) = self.config.auto_lock.check(&account.disputes, currency)
                 {
-                    original_tx.dispute_status = DisputeStatus::Disputed;
-                    self.transaction_store.store(original_tx).await?;
+                    account.lock(self.lock_reason(&tx, cause));
+                    effects.lock(account, tx.tx);
                 }
+                record.disputes.push(Dispute::open(amount, shortfall));
+                effects.dispute(record);
+                Ok(TransactionOutcome::Applied)
             }
             TransactionType::Resolve => {
-                if let Some(mut original_tx) = self.transaction_store.get(tx.tx).await?
-                    && original_tx.client == tx.client
-                    && original_tx.dispute_status == DisputeStatus::Disputed
-                    && let Some(amount) = original_tx.amount
-                    && account.resolve(amount.into()).is_ok()
-                {
-                    original_tx.dispute_status = DisputeStatus::Resolved;
-                    self.transaction_store.store(original_tx).await?;
+                let (original_tx, mut record, targets) = match self.open_disputes(&tx).await? {
+                    Ok(found) => found,
+                    Err(reason) => return Ok(TransactionOutcome::rejected(reason)),
+                };
+                // The held funds are released, and the owed funds are cancelled
+                let currency = original_tx.currency;
+                let (_, released, cleared) = match record.sum(&targets) {
+                    Ok(sums) => sums,
+                    Err(e) => return Ok(TransactionOutcome::rejected(balance_rejection(&e))),
+                };
+                let mut staged = account.clone();
+                let (result, destination) = match original_tx.r#type {
+                    TransactionType::Withdrawal => (
+                        staged.resolve_withdrawal(currency, released),
+                        LedgerAccount::ChargebackLosses,
+                    ),
+                    _ => (
+                        staged.resolve(currency, released),
+                        LedgerAccount::Available(client),
+                    ),
+                };
+                if let Err(e) = result.and_then(|()| staged.clear_receivable(currency, cleared)) {
+                    return Ok(TransactionOutcome::rejected(balance_rejection(&e)));
+                }
+                *account = staged;
+                if released > Balance::ZERO {
+                    effects.record(
+                        client,
+                        tx.tx,
+                        DomainEvent::HoldReleased {
+                            currency,
+                            amount: released,
+                            withdrawal: original_tx.r#type == TransactionType::Withdrawal,
+                        },
+                    );
+                    effects.post(
+                        tx.tx,
+                        currency,
+                        LedgerAccount::Held(client),
+                        destination,
+                        released,
+                    );
+                }
+                if cleared > Balance::ZERO {
+                    effects.record(
+                        client,
+                        tx.tx,
+                        DomainEvent::ReceivableCleared {
+                            currency,
+                            amount: cleared,
+                        },
+                    );
                 }
+                record.settle(&targets, DisputeStatus::Resolved);
+                if !record.has_open() {
+                    account.disputes.record_closed();
+                    effects.stats(account, tx.tx);
+                }
+                effects.dispute(record);
+                Ok(TransactionOutcome::Applied)
             }
             TransactionType::Chargeback => {
-                if let Some(mut original_tx) = self.transaction_store.get(tx.tx).await?
-                    && original_tx.client == tx.client
-                    && original_tx.dispute_status == DisputeStatus::Disputed
-                    && let Some(amount) = original_tx.amount
-                    && account.chargeback(amount.into()).is_ok()
-                {
-                    original_tx.dispute_status = DisputeStatus::Chargebacked;
-                    self.transaction_store.store(original_tx).await?;
+                let (original_tx, mut record, targets) = match self.open_disputes(&tx).await? {
+                    Ok(found) => found,
+                    Err(reason) => return Ok(TransactionOutcome::rejected(reason)),
+                };
+                // A reversed deposit leaves the system, a reversed withdrawal returns to the client.
+                // Funds the client could not cover stay owed by the client.
+                // The fee on the amount reversed is computed before the account is touched.
+                let result = record.sum(&targets).and_then(|(amount, charged, _)| {
+                    Ok((charged, self.fee_schedule.fee(tx.r#type, amount)?))
+                });
+                let (charged, fee) = match result {
+                    Ok(split) => split,
+                    Err(e) => return Ok(TransactionOutcome::rejected(balance_rejection(&e))),
+                };
+                let locked = account.status == AccountStatus::Locked;
+                let (result, destination) = match original_tx.r#type {
+                    TransactionType::Withdrawal => (
+                        account.chargeback_withdrawal(original_tx.currency, charged),
+                        LedgerAccount::Available(client),
+                    ),
+                    _ => (
+                        account.chargeback(original_tx.currency, charged),
+                        LedgerAccount::Cash,
+                    ),
+                };
+                if let Err(e) = result {
+                    return Ok(TransactionOutcome::rejected(balance_rejection(&e)));
+                }
+                if charged > Balance::ZERO {
+                    effects.record(
+                        client,
+                        tx.tx,
+                        DomainEvent::ChargedBack {
+                            currency: original_tx.currency,
+                            amount: charged,
+                            withdrawal: original_tx.r#type == TransactionType::Withdrawal,
+                        },
+                    );
+                    effects.post(
+                        tx.tx,
+                        original_tx.currency,
+                        LedgerAccount::Held(client),
+                        destination,
+                        charged,
+                    );
+                }
+                // The fee takes what the client can pay, without going below its floor
+                let headroom = account.headroom(original_tx.currency);
+                let fee = if fee > headroom { headroom } else { fee };
+                if fee > Balance::ZERO {
+                    account.withdraw(original_tx.currency, fee)?;
                 }
+                effects.charge(client, tx.tx, original_tx.currency, fee);
+                record.settle(&targets, DisputeStatus::Chargebacked);
+                if !record.has_open() {
+                    account.disputes.record_closed();
+                    effects.stats(account, tx.tx);
+                }
+                // An account that is already locked keeps its original lock reason
+                if !locked {
+                    account.lock(self.lock_reason(&tx, LockCause::Chargeback));
+                    effects.lock(account, tx.tx);
+                }
+                effects.dispute(record);
+                Ok(TransactionOutcome::Applied)
+            }
+        }
+    }
+
+    /// Persists the writes of a unit of work, atomically if a unit of work store is set.
+    async fn commit(&self, work: UnitOfWork) -> Result<()> {
+        if let Some(unit_of_work) = &self.unit_of_work {
+            return unit_of_work.commit(work).await;
+        }
+        if let Some(event_store) = &self.event_store
+            && !work.events.is_empty()
+        {
+            event_store.append(work.events).await?;
+        }
+        for tx in work.transactions {
+            self.transaction_store.store(tx).await?;
+        }
+        for tx_id in work.transaction_ids {
+            self.transaction_store.store_id(tx_id).await?;
+        }
+        for record in work.disputes {
+            self.transaction_store.store_disputes(record).await?;
+        }
+        self.account_store.store_all(work.accounts).await?;
+        if let (Some(fee_store), Some(fees)) = (&self.fee_store, work.fees) {
+            fee_store.store(fees).await?;
+        }
+        if let (Some(risk_store), Some(state)) = (&self.risk_store, work.risk) {
+            risk_store.store(state).await?;
+        }
+        if let Some(journal_store) = &self.journal_store
+            && !work.journal.is_empty()
+        {
+            journal_store.append(work.journal).await?;
+        }
+        if let Some(checkpoint) = work.checkpoint {
+            self.checkpoint_store()?.save(checkpoint).await?;
+        }
+        Ok(())
+    }
+
+    /// Describes the locking of an account by `tx`, at the current position.
+    fn lock_reason(&self, tx: &Transaction, cause: LockCause) -> LockReason {
+        let timestamp = if self.config.lock_timestamps {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|elapsed| elapsed.as_secs())
+        } else {
+            None
+        };
+        LockReason {
+            tx: tx.tx,
+            r#type: tx.r#type,
+            cause,
+            position: self.position(),
+            timestamp,
+        }
+    }
+
+    /// Creates the account of a new client, with the limits configured for it.
+    fn new_account(&self, client: u16) -> ClientAccount {
+        let mut account = ClientAccount::new(client);
+        if let Some(limits) = self.config.limits.get(&client) {
+            account.limits = *limits;
+        }
+        account
+    }
+
+    /// Folds new events of a client over its stored state.
+    async fn project(&self, client: u16, events: &[EventRecord]) -> Result<ClientAccount> {
+        let mut account = self
+            .account_store
+            .get(client)
+            .await?
+            .unwrap_or_else(|| ClientAccount::new(client));
+        for record in events.iter().filter(|record| record.client == client) {
+            record.event.apply(&mut account)?;
+        }
+        Ok(account)
+    }
+
+    /// Looks up the transaction referenced by a dispute, resolve or chargeback.
+    ///
+    /// Returns the rejection reason if the transaction is unknown, belongs to another client, or
+    /// is in a different currency than the referencing transaction.
+    async fn referenced_transaction(
+        &self,
+        tx: &Transaction,
+    ) -> Result<std::result::Result<Transaction, RejectionReason>> {
+        match self.transaction_store.get(tx.tx).await? {
+            None => Ok(Err(RejectionReason::UnknownTransaction)),
+            Some(original_tx) if original_tx.client != tx.client => {
+                Ok(Err(RejectionReason::ClientMismatch))
             }
+            Some(original_tx) if original_tx.currency != tx.currency => {
+                Ok(Err(RejectionReason::CurrencyMismatch))
+            }
+            Some(original_tx) => Ok(Ok(original_tx)),
+        }
+    }
+
+    /// Looks up the open disputes a resolve or chargeback settles, along with the transaction
+    /// they were raised on and its dispute record.
+    ///
+    /// Returns the rejection reason if the transaction cannot be referenced, has no open
+    /// dispute, or has no open dispute of the amount of the operation.
+    async fn open_disputes(
+        &self,
+        tx: &Transaction,
+    ) -> Result<std::result::Result<(Transaction, DisputeRecord, Vec<usize>), RejectionReason>>
+    {
+        let original_tx = match self.referenced_transaction(tx).await? {
+            Ok(original_tx) => original_tx,
+            Err(reason) => return Ok(Err(reason)),
+        };
+        let Some(record) = self
+            .transaction_store
+            .get_disputes(original_tx.tx)
+            .await?
+            .filter(DisputeRecord::has_open)
+        else {
+            return Ok(Err(RejectionReason::InvalidDisputeState));
+        };
+        // Without an amount, the operation covers all open disputes
+        let targets = record.targets(tx.amount.map(Balance::from));
+        if targets.is_empty() {
+            return Ok(Err(RejectionReason::InvalidDisputeAmount));
+        }
+        Ok(Ok((original_tx, record, targets)))
+    }
+
+    /// Returns all journal entries, in posting order.
+    ///
+    /// Fails if the journal is not enabled (see [`Self::with_journal`]).
+    pub async fn journal_entries(&self) -> Result<Vec<JournalEntry>> {
+        self.journal_store()?.get_all().await
+    }
+
+    /// Runs a trial balance of the journal against the current account balances.
+    ///
+    /// Returns the mismatches found, which is empty when the books balance. Fails if the
+    /// journal is not enabled (see [`Self::with_journal`]), or if a client sub-ledger
+    /// overflows.
+    pub async fn trial_balance(&self) -> Result<Vec<LedgerMismatch>> {
+        let entries = self.journal_store()?.get_all().await?;
+        let accounts = self.account_store.get_all().await?;
+        ledger::trial_balance(&entries, &accounts)
+    }
+
+    fn journal_store(&self) -> Result<&JournalStoreBox> {
+        self.journal_store
+            .as_ref()
+            .ok_or_else(|| PaymentError::ValidationError("The journal is not enabled".to_string()))
+    }
+
+    /// Returns the events of a client, in log order, to audit how its balances came about.
+    ///
+    /// Fails if the event-sourced mode is not enabled (see [`Self::with_event_log`]).
+    pub async fn account_history(&self, client_id: u16) -> Result<Vec<EventRecord>> {
+        self.event_store()?.get_for_client(client_id).await
+    }
+
+    /// Rebuilds the account store from the event log.
+    ///
+    /// Every account found in the log is overwritten with the fold of its events, which
+    /// recovers from corrupted or lost account state. Returns the number of accounts rebuilt.
+    /// Fails if the event-sourced mode is not enabled (see [`Self::with_event_log`]).
+    pub async fn replay(&self) -> Result<usize> {
+        let records = self.event_store()?.get_all().await?;
+        let accounts = event::replay(&records)?;
+        let count = accounts.len();
+        self.account_store.store_all(accounts).await?;
+        // The rebuilt accounts are only covered by a full snapshot
+        *self.lock_snapshot_changes() = None;
+        Ok(count)
+    }
+
+    fn event_store(&self) -> Result<&EventStoreBox> {
+        self.event_store.as_ref().ok_or_else(|| {
+            PaymentError::ValidationError("The event-sourced mode is not enabled".to_string())
+        })
+    }
+
+    /// Returns the applied transactions flagged for review by the risk rules, in input order.
+    pub fn risk_flags(&self) -> Vec<RiskFlag> {
+        self.risk_flags
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the invariant violations let through so far (outside of strict mode).
+    pub fn violations(&self) -> Vec<InvariantViolation> {
+        self.violations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Scans all accounts of the store and returns those violating their invariants.
+    pub async fn verify(&self) -> Result<Vec<InvariantViolation>> {
+        let accounts = self.account_store.get_all().await?;
+        Ok(accounts
+            .iter()
+            .flat_map(|account| self.check_invariants(account))
+            .collect())
+    }
+
+    /// Checks the invariants of an account, as relaxed by the configured policies.
+    fn check_invariants(&self, account: &ClientAccount) -> Vec<InvariantViolation> {
+        let mut violations = invariant::check(account);
+        // Charging back an overdrawn hold takes the total below zero by design
+        if self.config.dispute_funds == DisputeFundsPolicy::AllowNegative {
+            violations.retain(|violation| violation.invariant != Invariant::NonNegativeTotal);
+        }
+        violations
+    }
+
+    /// Returns the number of input transactions processed so far, including those covered by
+    /// a restored snapshot.
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::SeqCst)
+    }
+
+    /// Takes a consistent full snapshot of all accounts and transactions at the current
+    /// position.
+    pub async fn snapshot(&self) -> Result<Snapshot> {
+        Ok(Snapshot {
+            position: self.position(),
+            incremental: false,
+            accounts: self.account_store.get_all().await?,
+            transactions: self.transaction_store.get_all().await?,
+            disputes: self.transaction_store.get_all_disputes().await?,
+            transaction_ids: self.transaction_store.get_all_ids().await?,
+            fees: self.fee_account().await?,
+        })
+    }
+
+    /// Returns the fees collected so far, per currency (empty if fees are not enabled).
+    pub async fn fee_account(&self) -> Result<FeeAccount> {
+        match &self.fee_store {
+            Some(fee_store) => fee_store.get().await,
+            None => Ok(FeeAccount::default()),
         }
+    }
 
-        self.account_store.store(account).await?;
+    /// Takes a snapshot and saves it.
+    ///
+    /// Once a snapshot was taken or restored, with periodic snapshots enabled, the next ones
+    /// are incremental: they only hold the accounts, transaction records, dispute records and
+    /// transaction IDs committed since, rather than the whole state. Fails if snapshots are not
+    /// enabled (see [`Self::with_snapshots`]).
+    pub async fn save_snapshot(&self) -> Result<()> {
+        let changes = self.lock_snapshot_changes().take();
+        let snapshot = match changes {
+            Some(changes) => changes.into_snapshot(self.position(), self.fee_account().await?),
+            None => self.snapshot().await?,
+        };
+        self.snapshot_store()?.save(&snapshot).await?;
+        self.track_changes();
         Ok(())
     }
 
+    /// Loads the latest snapshot into the stores.
+    ///
+    /// Meant for stores that lost their state, such as the in-memory stores after a restart.
+    /// Returns the position of the snapshot, or `None` if none was taken; only the input
+    /// transactions after that position must then be processed. Fails if snapshots are not
+    /// enabled, or if the journal, the event log or the risk rules are: snapshots do not hold
+    /// them, so the restored accounts would disagree with their history.
+    pub async fn restore(&self) -> Result<Option<u64>> {
+        let snapshot_store = self.snapshot_store()?;
+        if self.journal_store.is_some() || self.event_store.is_some() || self.risk_store.is_some() {
+            return Err(PaymentError::ValidationError(
+                "Snapshots cannot be restored with the journal, event log or risk rules enabled"
+                    .to_string(),
+            ));
+        }
+        let Some(snapshot) = snapshot_store.latest().await? else {
+            return Ok(None);
+        };
+        self.commit(UnitOfWork {
+            accounts: snapshot.accounts,
+            transactions: snapshot.transactions,
+            transaction_ids: snapshot.transaction_ids.iter().collect(),
+            disputes: snapshot.disputes,
+            fees: self.fee_store.is_some().then_some(snapshot.fees),
+            ..UnitOfWork::default()
+        })
+        .await?;
+        self.position.store(snapshot.position, Ordering::SeqCst);
+        self.track_changes();
+        Ok(Some(snapshot.position))
+    }
+
+    /// Resumes from the position the stores were left at, keeping their state.
+    ///
+    /// Meant for persistent stores, which are already up to date. With checkpoints enabled,
+    /// the latest checkpoint, committed with the writes of each transaction, tells exactly
+    /// which input transactions were applied, even after the latest snapshot. Without one,
+    /// the position of the latest snapshot is used: transactions applied between the
+    /// snapshot and the restart are then processed again. Returns the position, or `None` if
+    /// neither was saved. Fails if snapshots are not enabled.
+    pub async fn resume(&self) -> Result<Option<u64>> {
+        let snapshot = self.snapshot_store()?.latest().await?;
+        let checkpoint = match &self.checkpoint_store {
+            Some(checkpoint_store) => checkpoint_store.latest().await?,
+            None => None,
+        };
+        let position = match (checkpoint, &snapshot) {
+            (Some(checkpoint), _) => checkpoint.position,
+            (None, Some(snapshot)) => snapshot.position,
+            (None, None) => return Ok(None),
+        };
+        self.position.store(position, Ordering::SeqCst);
+        // Writes after the latest snapshot are only covered by a full snapshot
+        if snapshot.is_some_and(|snapshot| snapshot.position == position) {
+            self.track_changes();
+        }
+        Ok(Some(position))
+    }
+
+    /// Starts collecting the committed writes for the next incremental snapshot, if periodic
+    /// snapshots are enabled.
+    fn track_changes(&self) {
+        if self.snapshot_store.is_some() && self.snapshot_interval > 0 {
+            *self.lock_snapshot_changes() = Some(SnapshotChanges::default());
+        }
+    }
+
+    fn tracks_changes(&self) -> bool {
+        self.lock_snapshot_changes().is_some()
+    }
+
+    /// Adds committed writes to the changes collected for the next incremental snapshot.
+    fn record_changes(&self, changes: SnapshotChanges) {
+        if let Some(collected) = self.lock_snapshot_changes().as_mut() {
+            collected.merge(changes);
+        }
+    }
+
+    fn lock_snapshot_changes(&self) -> std::sync::MutexGuard<'_, Option<SnapshotChanges>> {
+        self.snapshot_changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Saves a checkpoint, such as the end of the input once every record was processed.
+    ///
+    /// Fails if checkpoints are not enabled (see [`Self::with_checkpoints`]).
+    pub async fn save_checkpoint(&self, checkpoint: Checkpoint) -> Result<()> {
+        self.checkpoint_store()?;
+        self.commit(UnitOfWork {
+            checkpoint: Some(checkpoint),
+            ..UnitOfWork::default()
+        })
+        .await
+    }
+
+    /// Resumes the input fingerprinted `fingerprint` from the latest checkpoint, keeping the
+    /// state of the stores.
+    ///
+    /// Meant for persistent stores, which the checkpoints are committed to. Returns the
+    /// checkpoint, whose input position the input must be read from, or `None` if none was
+    /// saved. Fails if the checkpoint was taken on another input, or if checkpoints are not
+    /// enabled.
+    pub async fn resume_input(&self, fingerprint: &Fingerprint) -> Result<Option<Checkpoint>> {
+        let Some(checkpoint) = self.checkpoint_store()?.latest().await? else {
+            return Ok(None);
+        };
+        if checkpoint.fingerprint != *fingerprint {
+            return Err(PaymentError::ValidationError(format!(
+                "The input ({}) is not the one of the checkpoint ({})",
+                fingerprint, checkpoint.fingerprint
+            )));
+        }
+        self.position.store(checkpoint.position, Ordering::SeqCst);
+        Ok(Some(checkpoint))
+    }
+
+    fn checkpoint_store(&self) -> Result<&CheckpointStoreBox> {
+        self.checkpoint_store
+            .as_ref()
+            .ok_or_else(|| PaymentError::ValidationError("Checkpoints are not enabled".to_string()))
+    }
+
+    fn snapshot_store(&self) -> Result<&SnapshotStoreBox> {
+        self.snapshot_store
+            .as_ref()
+            .ok_or_else(|| PaymentError::ValidationError("Snapshots are not enabled".to_string()))
+    }
+
     /// Consumes the engine and returns the final state of all accounts.
     pub async fn into_results(self) -> Result<Vec<ClientAccount>> {
         self.account_store.get_all().await
     }
 }
 
+/// Maps a failed balance operation to the matching rejection reason.
+fn balance_rejection(error: &PaymentError) -> RejectionReason {
+    match error {
+        PaymentError::Overflow(_) => RejectionReason::ArithmeticOverflow,
+        _ => RejectionReason::InsufficientFunds,
+    }
+}
+
+/// Maps an account status that refused a transaction to the matching rejection reason.
+fn status_rejection(status: AccountStatus) -> RejectionReason {
+    match status {
+        AccountStatus::Frozen => RejectionReason::AccountFrozen,
+        AccountStatus::Closed => RejectionReason::AccountClosed,
+        AccountStatus::Active | AccountStatus::Locked => RejectionReason::AccountLocked,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::account::Balance;
-    use crate::infrastructure::in_memory::{InMemoryAccountStore, InMemoryTransactionStore};
+    use crate::application::config::{
+        AutoLockPolicy, LockedDisputePolicy, PrecisionMode, PrecisionPolicy,
+    };
+    use crate::domain::account::{AccountLimits, CurrencyBalance};
+    use crate::domain::currency::Currency;
+    use crate::domain::fee::FeeRule;
+    use crate::domain::ports::{
+        AccountStore, CheckpointStore, EventStore, FeeStore, JournalStore, RiskStore,
+        TransactionStore, UnitOfWorkStore,
+    };
+    use crate::domain::risk::{
+        DepositThenWithdrawal, MaxWithdrawalAmount, RiskAction, WithdrawalVelocity,
+    };
+    use crate::infrastructure::in_memory::{
+        InMemoryAccountStore, InMemoryEventStore, InMemoryFeeStore, InMemoryJournalStore,
+        InMemoryRiskStore, InMemoryTransactionStore, InMemoryUnitOfWork,
+    };
+    use async_trait::async_trait;
+    use rust_decimal::Decimal;
     use rust_decimal_macros::dec;
+    use std::collections::BTreeMap;
 
     #[tokio::test]
     async fn test_duplicate_transaction_ids() {
@@ -127,14 +1454,16 @@ mod tests {
             client: 1,
             tx: 1,
             amount: Some(dec!(100.0).try_into().unwrap()),
-            dispute_status: DisputeStatus::None,
+            currency: Currency::default(),
+            destination: None,
         };
         let deposit2 = Transaction {
             r#type: TransactionType::Deposit,
             client: 1,
             tx: 1, // Duplicate ID
             amount: Some(dec!(50.0).try_into().unwrap()),
-            dispute_status: DisputeStatus::None,
+            currency: Currency::default(),
+            destination: None,
         };
 
         engine.process_transaction(deposit1).await.unwrap();
@@ -143,7 +1472,10 @@ mod tests {
         let results = engine.into_results().await.unwrap();
         let final_account = results.iter().find(|a| a.client == 1).unwrap();
         // Should be 100.0, not 150.0
-        assert_eq!(final_account.available, Balance(dec!(100.0)));
+        assert_eq!(
+            final_account.balance(Currency::default()).available,
+            Balance(dec!(100.0))
+        );
     }
 
     #[tokio::test]
@@ -158,14 +1490,18 @@ mod tests {
             client: 1,
             tx: 1,
             amount: Some(dec!(100.0).try_into().unwrap()),
-            dispute_status: DisputeStatus::None,
+            currency: Currency::default(),
+            destination: None,
         };
 
         engine.process_transaction(deposit).await.unwrap();
 
         let results = engine.into_results().await.unwrap();
         let final_account = results.iter().find(|a| a.client == 1).unwrap();
-        assert_eq!(final_account.available, Balance(dec!(100.0)));
+        assert_eq!(
+            final_account.balance(Currency::default()).available,
+            Balance(dec!(100.0))
+        );
     }
 
     #[tokio::test]
@@ -182,7 +1518,8 @@ mod tests {
                 client: i as u16,
                 tx: i,
                 amount: Some(dec!(1.0).try_into().unwrap()),
-                dispute_status: DisputeStatus::None,
+                currency: Currency::default(),
+                destination: None,
             };
             engine.process_transaction(tx).await.unwrap();
         }
@@ -192,7 +1529,10 @@ mod tests {
         assert_eq!(results.len(), 100);
 
         for account in results {
-            assert_eq!(account.available, Balance(dec!(1.0)));
+            assert_eq!(
+                account.balance(Currency::default()).available,
+                Balance(dec!(1.0))
+            );
         }
     }
 
@@ -209,7 +1549,8 @@ mod tests {
             client: 1,
             tx: 1,
             amount: Some(dec!(100.0).try_into().unwrap()),
-            dispute_status: DisputeStatus::None,
+            currency: Currency::default(),
+            destination: None,
         };
         engine.process_transaction(deposit).await.unwrap();
 
@@ -219,7 +1560,8 @@ mod tests {
             client: 1,
             tx: 1,
             amount: None,
-            dispute_status: DisputeStatus::None,
+            currency: Currency::default(),
+            destination: None,
         };
         engine.process_transaction(dispute.clone()).await.unwrap();
 
@@ -229,7 +1571,8 @@ mod tests {
             client: 1,
             tx: 1,
             amount: None,
-            dispute_status: DisputeStatus::None,
+            currency: Currency::default(),
+            destination: None,
         };
         engine.process_transaction(resolve).await.unwrap();
 
@@ -241,7 +1584,1385 @@ mod tests {
 
         // Account should be fully available (100.0), nothing held.
         // If re-dispute succeeded, 100.0 would be held.
-        assert_eq!(account.available, Balance(dec!(100.0)));
-        assert_eq!(account.held, Balance(dec!(0.0)));
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance(dec!(100.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).held,
+            Balance(dec!(0.0))
+        );
+    }
+
+    fn tx(r#type: TransactionType, client: u16, tx: u32, amount: Option<&str>) -> Transaction {
+        Transaction {
+            r#type,
+            client,
+            tx,
+            amount: amount.map(|a| {
+                a.parse::<rust_decimal::Decimal>()
+                    .unwrap()
+                    .try_into()
+                    .unwrap()
+            }),
+            currency: Currency::default(),
+            destination: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outcomes_report_rejection_reasons() {
+        let engine = PaymentEngine::new(
+            Box::new(InMemoryAccountStore::new()),
+            Box::new(InMemoryTransactionStore::new()),
+        );
+        let rejected = TransactionOutcome::rejected;
+
+        let cases = [
+            (
+                tx(TransactionType::Deposit, 1, 1, Some("10")),
+                TransactionOutcome::Applied,
+            ),
+            (
+                tx(TransactionType::Deposit, 1, 1, Some("10")),
+                rejected(RejectionReason::DuplicateTransaction),
+            ),
+            (
+                tx(TransactionType::Deposit, 1, 2, None),
+                rejected(RejectionReason::MissingAmount),
+            ),
+            (
+                tx(TransactionType::Withdrawal, 1, 3, Some("50")),
+                rejected(RejectionReason::InsufficientFunds),
+            ),
+            (
+                tx(TransactionType::Withdrawal, 1, 3, Some("5")),
+                rejected(RejectionReason::DuplicateTransaction),
+            ),
+            (
+                tx(TransactionType::Dispute, 1, 99, None),
+                rejected(RejectionReason::UnknownTransaction),
+            ),
+            (
+                tx(TransactionType::Dispute, 2, 1, None),
+                rejected(RejectionReason::ClientMismatch),
+            ),
+            (
+                tx(TransactionType::Resolve, 1, 1, None),
+                rejected(RejectionReason::InvalidDisputeState),
+            ),
+            (
+                tx(TransactionType::Dispute, 1, 1, None),
+                TransactionOutcome::Applied,
+            ),
+            (
+                tx(TransactionType::Dispute, 1, 1, None),
+                rejected(RejectionReason::InvalidDisputeState),
+            ),
+            (
+                tx(TransactionType::Chargeback, 1, 1, None),
+                TransactionOutcome::Applied,
+            ),
+            (
+                tx(TransactionType::Deposit, 1, 4, Some("10")),
+                rejected(RejectionReason::AccountLocked),
+            ),
+        ];
+
+        for (i, (tx, expected)) in cases.into_iter().enumerate() {
+            let outcome = engine.process_transaction(tx).await.unwrap();
+            assert_eq!(outcome, expected, "case {}", i);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispute_insufficient_funds_outcome() {
+        let engine = PaymentEngine::new(
+            Box::new(InMemoryAccountStore::new()),
+            Box::new(InMemoryTransactionStore::new()),
+        );
+
+        engine
+            .process_transaction(tx(TransactionType::Deposit, 1, 1, Some("100")))
+            .await
+            .unwrap();
+        engine
+            .process_transaction(tx(TransactionType::Withdrawal, 1, 2, Some("60")))
+            .await
+            .unwrap();
+        let outcome = engine
+            .process_transaction(tx(TransactionType::Dispute, 1, 1, None))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            TransactionOutcome::rejected(RejectionReason::InsufficientFunds)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_settles_each_partial_dispute() {
+        let transaction_store = InMemoryTransactionStore::new();
+        let engine = PaymentEngine::new(
+            Box::new(InMemoryAccountStore::new()),
+            Box::new(transaction_store.clone()),
+        );
+
+        for transaction in [
+            tx(TransactionType::Deposit, 1, 1, Some("100")),
+            tx(TransactionType::Dispute, 1, 1, Some("30")),
+            tx(TransactionType::Dispute, 1, 1, Some("50")),
+            tx(TransactionType::Dispute, 1, 1, Some("20")),
+            // Each operation targets the open dispute of its amount, whatever its rank
+            tx(TransactionType::Resolve, 1, 1, Some("20")),
+            tx(TransactionType::Resolve, 1, 1, Some("30")),
+            tx(TransactionType::Chargeback, 1, 1, Some("50")),
+        ] {
+            let outcome = engine.process_transaction(transaction).await.unwrap();
+            assert_eq!(outcome, TransactionOutcome::Applied);
+        }
+
+        let record = transaction_store.get_disputes(1).await.unwrap().unwrap();
+        assert_eq!(
+            record
+                .disputes
+                .iter()
+                .map(|dispute| dispute.status)
+                .collect::<Vec<_>>(),
+            vec![
+                DisputeStatus::Resolved,
+                DisputeStatus::Chargebacked,
+                DisputeStatus::Resolved
+            ]
+        );
+        let results = engine.into_results().await.unwrap();
+        let balance = results[0].balance(Currency::default());
+        assert_eq!(balance.available, Balance(dec!(50)));
+        assert_eq!(balance.held, Balance::ZERO);
+        assert_eq!(results[0].disputes.open, 0);
+        assert_eq!(results[0].status, AccountStatus::Locked);
+    }
+
+    #[tokio::test]
+    async fn test_partial_disputes() {
+        let engine = PaymentEngine::new(
+            Box::new(InMemoryAccountStore::new()),
+            Box::new(InMemoryTransactionStore::new()),
+        );
+        let rejected = TransactionOutcome::rejected;
+
+        let cases = [
+            (
+                tx(TransactionType::Deposit, 1, 1, Some("100")),
+                TransactionOutcome::Applied,
+            ),
+            (
+                tx(TransactionType::Dispute, 1, 1, Some("30")),
+                TransactionOutcome::Applied,
+            ),
+            (
+                tx(TransactionType::Dispute, 1, 1, Some("50")),
+                TransactionOutcome::Applied,
+            ),
+            // Only 20 remain undisputed
+            (
+                tx(TransactionType::Dispute, 1, 1, Some("30")),
+                rejected(RejectionReason::InvalidDisputeAmount),
+            ),
+            (
+                tx(TransactionType::Resolve, 1, 1, Some("30")),
+                TransactionOutcome::Applied,
+            ),
+            // No open dispute of 60
+            (
+                tx(TransactionType::Chargeback, 1, 1, Some("60")),
+                rejected(RejectionReason::InvalidDisputeAmount),
+            ),
+            (
+                tx(TransactionType::Chargeback, 1, 1, None),
+                TransactionOutcome::Applied,
+            ),
+        ];
+
+        for (i, (tx, expected)) in cases.into_iter().enumerate() {
+            let outcome = engine.process_transaction(tx).await.unwrap();
+            assert_eq!(outcome, expected, "case {}", i);
+        }
+
+        let results = engine.into_results().await.unwrap();
+        let account = results.iter().find(|a| a.client == 1).unwrap();
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance(dec!(50))
+        );
+        assert_eq!(account.balance(Currency::default()).held, Balance(dec!(0)));
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance(dec!(50))
+        );
+        assert_eq!(account.status, AccountStatus::Locked);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_is_all_or_nothing() {
+        let engine = PaymentEngine::new(
+            Box::new(InMemoryAccountStore::new()),
+            Box::new(InMemoryTransactionStore::new()),
+        );
+        let transfer = |id: u32, destination: Option<u16>, amount: &str| Transaction {
+            destination,
+            ..tx(TransactionType::Transfer, 1, id, Some(amount))
+        };
+        let rejected = TransactionOutcome::rejected;
+
+        let cases = [
+            (
+                tx(TransactionType::Deposit, 1, 1, Some("100")),
+                TransactionOutcome::Applied,
+            ),
+            (transfer(2, Some(2), "30"), TransactionOutcome::Applied),
+            (
+                transfer(2, Some(2), "30"),
+                rejected(RejectionReason::DuplicateTransaction),
+            ),
+            (
+                transfer(3, Some(2), "80"),
+                rejected(RejectionReason::InsufficientFunds),
+            ),
+            (
+                transfer(4, None, "10"),
+                rejected(RejectionReason::InvalidDestination),
+            ),
+            (
+                transfer(5, Some(1), "10"),
+                rejected(RejectionReason::InvalidDestination),
+            ),
+            // Lock client 3, transfers to it must fail as a whole
+            (
+                tx(TransactionType::Deposit, 3, 6, Some("10")),
+                TransactionOutcome::Applied,
+            ),
+            (
+                tx(TransactionType::Dispute, 3, 6, None),
+                TransactionOutcome::Applied,
+            ),
+            (
+                tx(TransactionType::Chargeback, 3, 6, None),
+                TransactionOutcome::Applied,
+            ),
+            (
+                transfer(7, Some(3), "10"),
+                rejected(RejectionReason::AccountLocked),
+            ),
+        ];
+
+        for (i, (tx, expected)) in cases.into_iter().enumerate() {
+            let outcome = engine.process_transaction(tx).await.unwrap();
+            assert_eq!(outcome, expected, "case {}", i);
+        }
+
+        let results = engine.into_results().await.unwrap();
+        let source = results.iter().find(|a| a.client == 1).unwrap();
+        let destination = results.iter().find(|a| a.client == 2).unwrap();
+        let locked = results.iter().find(|a| a.client == 3).unwrap();
+        assert_eq!(source.balance(Currency::default()).total, Balance(dec!(70)));
+        assert_eq!(
+            destination.balance(Currency::default()).total,
+            Balance(dec!(30))
+        );
+        assert_eq!(locked.balance(Currency::default()).total, Balance(dec!(0)));
+    }
+
+    #[tokio::test]
+    async fn test_journal_trial_balance() {
+        let journal_store = InMemoryJournalStore::new();
+        let engine = PaymentEngine::with_config(
+            Box::new(InMemoryAccountStore::new()),
+            Box::new(InMemoryTransactionStore::with_withdrawals()),
+            EngineConfig {
+                dispute_eligibility: crate::application::config::DisputeEligibility::Both,
+                ..EngineConfig::default()
+            },
+        )
+        .with_journal(Box::new(journal_store.clone()));
+        let transfer = Transaction {
+            destination: Some(2),
+            ..tx(TransactionType::Transfer, 1, 3, Some("20"))
+        };
+
+        let transactions = vec![
+            tx(TransactionType::Deposit, 1, 1, Some("100")),
+            tx(TransactionType::Withdrawal, 1, 2, Some("10")),
+            transfer,
+            // Rejected, must not post anything
+            tx(TransactionType::Withdrawal, 1, 4, Some("500")),
+            tx(TransactionType::Dispute, 1, 1, Some("10")),
+            tx(TransactionType::Dispute, 1, 1, Some("20")),
+            tx(TransactionType::Resolve, 1, 1, Some("10")),
+            tx(TransactionType::Chargeback, 1, 1, None),
+            tx(TransactionType::Deposit, 2, 5, Some("5")),
+            tx(TransactionType::Withdrawal, 2, 6, Some("5")),
+            tx(TransactionType::Dispute, 2, 6, None),
+            tx(TransactionType::Chargeback, 2, 6, None),
+        ];
+        for tx in transactions {
+            engine.process_transaction(tx).await.unwrap();
+        }
+
+        let entries = engine.journal_entries().await.unwrap();
+        assert_eq!(entries.len(), 11);
+        assert_eq!(
+            entries[0],
+            JournalEntry {
+                seq: 0,
+                ..JournalEntry::new(
+                    1,
+                    Currency::default(),
+                    LedgerAccount::Cash,
+                    LedgerAccount::Available(1),
+                    Balance(dec!(100)),
+                )
+            }
+        );
+        assert!(entries.iter().all(|e| e.tx != 4));
+        assert_eq!(journal_store.get_for_client(2).await.unwrap().len(), 5);
+
+        assert!(engine.trial_balance().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_journal_disabled() {
+        let engine = PaymentEngine::new(
+            Box::new(InMemoryAccountStore::new()),
+            Box::new(InMemoryTransactionStore::new()),
+        );
+        assert!(engine.trial_balance().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_event_sourced_replay() {
+        let account_store = InMemoryAccountStore::new();
+        let engine = PaymentEngine::new(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+        )
+        .with_event_log(Box::new(InMemoryEventStore::new()));
+        let transfer = Transaction {
+            destination: Some(2),
+            ..tx(TransactionType::Transfer, 1, 3, Some("20"))
+        };
+
+        let transactions = vec![
+            tx(TransactionType::Deposit, 1, 1, Some("100")),
+            tx(TransactionType::Withdrawal, 1, 2, Some("10")),
+            transfer,
+            tx(TransactionType::Withdrawal, 3, 4, Some("1")),
+            tx(TransactionType::Deposit, 2, 5, Some("50")),
+            tx(TransactionType::Dispute, 2, 5, Some("10")),
+            tx(TransactionType::Dispute, 2, 5, Some("20")),
+            tx(TransactionType::Resolve, 2, 5, Some("10")),
+            tx(TransactionType::Chargeback, 2, 5, None),
+            tx(TransactionType::Freeze, 1, 6, None),
+        ];
+        for tx in transactions {
+            engine.process_transaction(tx).await.unwrap();
+        }
+        let mut expected = account_store.get_all().await.unwrap();
+        expected.sort_by_key(|account| account.client);
+
+        let history = engine.account_history(2).await.unwrap();
+        assert_eq!(history[0].event, DomainEvent::AccountOpened);
+        assert_eq!(
+            history.last().unwrap().event,
+            DomainEvent::AccountLocked {
+                reason: Some(LockReason {
+                    tx: 5,
+                    r#type: TransactionType::Chargeback,
+                    cause: LockCause::Chargeback,
+                    position: 8,
+                    timestamp: None,
+                }),
+            }
+        );
+
+        // Corrupt the projections, then rebuild them from the log
+        account_store
+            .store_all(vec![ClientAccount::new(1), ClientAccount::new(2)])
+            .await
+            .unwrap();
+        assert_eq!(engine.replay().await.unwrap(), 3);
+
+        let mut rebuilt = account_store.get_all().await.unwrap();
+        rebuilt.sort_by_key(|account| account.client);
+        assert_eq!(rebuilt, expected);
+        assert_eq!(rebuilt[0].status, AccountStatus::Frozen);
+        assert_eq!(
+            rebuilt[0].balance(Currency::default()).total,
+            Balance(dec!(70))
+        );
+        assert_eq!(
+            rebuilt[1].balance(Currency::default()).total,
+            Balance(dec!(50))
+        );
+        assert_eq!(rebuilt[1].status, AccountStatus::Locked);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore() {
+        use crate::infrastructure::file::FileSnapshotStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_path = dir.path().join("snapshot.json");
+        let transactions = vec![
+            tx(TransactionType::Deposit, 1, 1, Some("100")),
+            tx(TransactionType::Deposit, 2, 2, Some("50")),
+            tx(TransactionType::Dispute, 1, 1, Some("40")),
+            tx(TransactionType::Withdrawal, 2, 3, Some("10")),
+            tx(TransactionType::Resolve, 1, 1, None),
+            tx(TransactionType::Dispute, 2, 2, None),
+            tx(TransactionType::Dispute, 1, 1, Some("60")),
+            tx(TransactionType::Chargeback, 1, 1, None),
+        ];
+        let engine = |interval| {
+            PaymentEngine::new(
+                Box::new(InMemoryAccountStore::new()),
+                Box::new(InMemoryTransactionStore::new()),
+            )
+            .with_snapshots(Box::new(FileSnapshotStore::new(&snapshot_path)), interval)
+        };
+
+        // Uninterrupted run, snapshots after transactions 3 and 6
+        let first = engine(3);
+        for tx in transactions.clone() {
+            first.process_transaction(tx).await.unwrap();
+        }
+        let mut expected = first.into_results().await.unwrap();
+        expected.sort_by_key(|account| account.client);
+        // The second snapshot only holds the changes since the first
+        let snapshots = std::fs::read_to_string(&snapshot_path).unwrap();
+        assert_eq!(snapshots.lines().count(), 2);
+
+        // Restart from the latest snapshot, processing only the remaining transactions
+        let second = engine(3);
+        assert_eq!(second.restore().await.unwrap(), Some(6));
+        assert_eq!(second.position(), 6);
+        let remaining = [
+            tx(TransactionType::Dispute, 1, 1, Some("60")),
+            tx(TransactionType::Chargeback, 1, 1, None),
+        ];
+        for tx in remaining {
+            assert!(second.process_transaction(tx).await.unwrap().is_applied());
+        }
+        // The restored history still rejects duplicates and settled disputes
+        assert_eq!(
+            second
+                .process_transaction(tx(TransactionType::Deposit, 1, 1, Some("5")))
+                .await
+                .unwrap(),
+            TransactionOutcome::rejected(RejectionReason::AccountLocked)
+        );
+        assert_eq!(
+            second
+                .process_transaction(tx(TransactionType::Deposit, 2, 2, Some("5")))
+                .await
+                .unwrap(),
+            TransactionOutcome::rejected(RejectionReason::DuplicateTransaction)
+        );
+
+        let mut restored = second.into_results().await.unwrap();
+        restored.sort_by_key(|account| account.client);
+        assert_eq!(restored, expected);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore_keeps_seen_ids() {
+        use crate::infrastructure::file::FileSnapshotStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_path = dir.path().join("snapshot.json");
+        let engine = || {
+            PaymentEngine::new(
+                Box::new(InMemoryAccountStore::new()),
+                Box::new(InMemoryTransactionStore::new()),
+            )
+            .with_snapshots(Box::new(FileSnapshotStore::new(&snapshot_path)), 2)
+        };
+
+        // A full snapshot after the withdrawal, whose record is not kept, and an incremental
+        // one after the bounced withdrawal, of which only the ID is recorded
+        let first = engine();
+        for transaction in [
+            tx(TransactionType::Deposit, 1, 1, Some("10")),
+            tx(TransactionType::Withdrawal, 1, 2, Some("5")),
+            tx(TransactionType::Withdrawal, 1, 3, Some("100")),
+            tx(TransactionType::Deposit, 1, 4, Some("1")),
+        ] {
+            first.process_transaction(transaction).await.unwrap();
+        }
+
+        let second = engine();
+        assert_eq!(second.restore().await.unwrap(), Some(4));
+        for transaction in [
+            tx(TransactionType::Withdrawal, 1, 2, Some("5")),
+            tx(TransactionType::Withdrawal, 1, 3, Some("1")),
+        ] {
+            assert_eq!(
+                second.process_transaction(transaction).await.unwrap(),
+                TransactionOutcome::rejected(RejectionReason::DuplicateTransaction)
+            );
+        }
+        let accounts = second.into_results().await.unwrap();
+        assert_eq!(
+            accounts[0].balance(Currency::default()).total,
+            Balance::new(dec!(6))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_refuses_state_missing_from_snapshots() {
+        use crate::infrastructure::file::FileSnapshotStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_path = dir.path().join("snapshot.json");
+        let engine = || {
+            PaymentEngine::new(
+                Box::new(InMemoryAccountStore::new()),
+                Box::new(InMemoryTransactionStore::new()),
+            )
+            .with_snapshots(Box::new(FileSnapshotStore::new(&snapshot_path)), 1)
+        };
+        engine()
+            .process_transaction(tx(TransactionType::Deposit, 1, 1, Some("10")))
+            .await
+            .unwrap();
+
+        let with_journal = engine().with_journal(Box::new(InMemoryJournalStore::new()));
+        assert!(with_journal.restore().await.is_err());
+        let with_events = engine().with_event_log(Box::new(InMemoryEventStore::new()));
+        assert!(with_events.restore().await.is_err());
+        let with_risk = engine().with_risk_rules(Vec::new(), Box::new(InMemoryRiskStore::new()));
+        assert!(with_risk.restore().await.is_err());
+        assert_eq!(engine().restore().await.unwrap(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_invariant_checks() {
+        let account_store = InMemoryAccountStore::new();
+        let mut corrupted = ClientAccount::new(1);
+        corrupted.balance_mut(Currency::default()).held = Balance(dec!(-5));
+        account_store.store(corrupted.clone()).await.unwrap();
+
+        // By default, violations are reported but the account is persisted
+        let engine = PaymentEngine::new(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+        );
+        let outcome = engine
+            .process_transaction(tx(TransactionType::Deposit, 1, 1, Some("10")))
+            .await
+            .unwrap();
+        assert!(outcome.is_applied());
+        assert_eq!(engine.violations().len(), 2);
+        assert_eq!(engine.verify().await.unwrap().len(), 2);
+
+        // In strict mode, the violating account is not persisted
+        account_store.store(corrupted.clone()).await.unwrap();
+        let strict = PaymentEngine::with_config(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+            EngineConfig {
+                strict_invariants: true,
+                ..EngineConfig::default()
+            },
+        );
+        let result = strict
+            .process_transaction(tx(TransactionType::Deposit, 1, 2, Some("10")))
+            .await;
+        assert!(matches!(result, Err(PaymentError::InvariantViolation(v)) if v.len() == 2));
+        assert_eq!(account_store.get(1).await.unwrap(), Some(corrupted));
+        assert!(strict.violations().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_precision_policy() {
+        let account_store = InMemoryAccountStore::new();
+        let config = |mode| EngineConfig {
+            precision: Some(PrecisionPolicy::new(4, mode)),
+            ..EngineConfig::default()
+        };
+        let engine = PaymentEngine::with_config(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+            config(PrecisionMode::Reject),
+        );
+        let outcome = engine
+            .process_transaction(tx(TransactionType::Deposit, 1, 1, Some("1.00005")))
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome,
+            TransactionOutcome::rejected(RejectionReason::ExcessPrecision)
+        );
+        assert_eq!(account_store.get(1).await.unwrap(), None);
+
+        // The rounded amount is the one applied and later disputed
+        let engine = PaymentEngine::with_config(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+            config(PrecisionMode::HalfUp),
+        );
+        for transaction in [
+            tx(TransactionType::Deposit, 1, 1, Some("1.00005")),
+            tx(TransactionType::Deposit, 1, 2, Some("2")),
+            tx(TransactionType::Dispute, 1, 1, None),
+        ] {
+            assert!(
+                engine
+                    .process_transaction(transaction)
+                    .await
+                    .unwrap()
+                    .is_applied()
+            );
+        }
+        let balance = account_store
+            .get(1)
+            .await
+            .unwrap()
+            .unwrap()
+            .balance(Currency::default());
+        assert_eq!(balance.held, Balance(dec!(1.0001)));
+        assert_eq!(balance.total, Balance(dec!(3.0001)));
+    }
+
+    #[tokio::test]
+    async fn test_overflow_is_rejected() {
+        let account_store = InMemoryAccountStore::new();
+        let engine = PaymentEngine::new(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+        );
+        let max = Decimal::MAX.to_string();
+        let mut transfer = tx(TransactionType::Transfer, 1, 4, Some(&max));
+        transfer.destination = Some(2);
+        let outcomes = [
+            tx(TransactionType::Deposit, 1, 1, Some(&max)),
+            tx(TransactionType::Deposit, 1, 2, Some("1")),
+            tx(TransactionType::Deposit, 2, 3, Some("1")),
+            transfer,
+        ];
+        let mut results = Vec::new();
+        for transaction in outcomes {
+            results.push(engine.process_transaction(transaction).await.unwrap());
+        }
+
+        let overflow = TransactionOutcome::rejected(RejectionReason::ArithmeticOverflow);
+        assert_eq!(
+            results,
+            vec![
+                TransactionOutcome::Applied,
+                overflow,
+                TransactionOutcome::Applied,
+                overflow
+            ]
+        );
+        // The rejected transfer did not debit the source
+        let source = account_store.get(1).await.unwrap().unwrap();
+        assert_eq!(
+            source.balance(Currency::default()).total,
+            Balance(Decimal::MAX)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_risk_rules() {
+        let account_store = InMemoryAccountStore::new();
+        let risk_store = InMemoryRiskStore::new();
+        let rules: Vec<RiskRuleBox> = vec![
+            Box::new(MaxWithdrawalAmount {
+                limit: dec!(100),
+                action: RiskAction::Reject,
+            }),
+            Box::new(DepositThenWithdrawal {
+                window: 2,
+                action: RiskAction::Flag,
+            }),
+            Box::new(WithdrawalVelocity {
+                max: 1,
+                window: 10,
+                action: RiskAction::Reject,
+            }),
+        ];
+        let engine = PaymentEngine::new(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+        )
+        .with_risk_rules(rules, Box::new(risk_store.clone()));
+
+        let mut outcomes = Vec::new();
+        for transaction in [
+            tx(TransactionType::Deposit, 1, 1, Some("500")),
+            tx(TransactionType::Withdrawal, 1, 2, Some("200")),
+            tx(TransactionType::Withdrawal, 1, 3, Some("50")),
+            tx(TransactionType::Withdrawal, 1, 4, Some("10")),
+        ] {
+            outcomes.push(engine.process_transaction(transaction).await.unwrap());
+        }
+
+        let risk_rejected = TransactionOutcome::rejected(RejectionReason::RiskRejected);
+        assert_eq!(
+            outcomes,
+            vec![
+                TransactionOutcome::Applied,
+                risk_rejected,
+                TransactionOutcome::Applied,
+                risk_rejected
+            ]
+        );
+        assert_eq!(
+            engine.risk_flags(),
+            vec![RiskFlag {
+                client: 1,
+                tx: 3,
+                rule: "deposit_then_withdrawal"
+            }]
+        );
+        let account = account_store.get(1).await.unwrap().unwrap();
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance(dec!(450))
+        );
+        let state = RiskStore::get(&risk_store, 1).await.unwrap().unwrap();
+        assert_eq!(state.activity.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_account_limits() {
+        let account_store = InMemoryAccountStore::new();
+        let event_store = InMemoryEventStore::new();
+        let config = EngineConfig {
+            limits: BTreeMap::from([(
+                1,
+                AccountLimits {
+                    overdraft: Balance(dec!(50)),
+                    reserve: Balance::ZERO,
+                },
+            )]),
+            ..EngineConfig::default()
+        };
+        let engine = PaymentEngine::with_config(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+            config,
+        )
+        .with_event_log(Box::new(event_store.clone()));
+
+        let mut outcomes = Vec::new();
+        for transaction in [
+            // Client 1 gets its overdraft from the configuration
+            tx(TransactionType::Deposit, 1, 1, Some("10")),
+            tx(TransactionType::Withdrawal, 1, 2, Some("60")),
+            tx(TransactionType::Withdrawal, 1, 3, Some("1")),
+            // Client 2 gets a reserve through an admin transaction
+            tx(TransactionType::Deposit, 2, 4, Some("10")),
+            tx(TransactionType::SetReserve, 2, 5, Some("4")),
+            tx(TransactionType::Withdrawal, 2, 6, Some("7")),
+            tx(TransactionType::Withdrawal, 2, 7, Some("6")),
+        ] {
+            outcomes.push(engine.process_transaction(transaction).await.unwrap());
+        }
+
+        let insufficient = TransactionOutcome::rejected(RejectionReason::InsufficientFunds);
+        assert_eq!(
+            outcomes,
+            vec![
+                TransactionOutcome::Applied,
+                TransactionOutcome::Applied,
+                insufficient,
+                TransactionOutcome::Applied,
+                TransactionOutcome::Applied,
+                insufficient,
+                TransactionOutcome::Applied,
+            ]
+        );
+        let client1 = account_store.get(1).await.unwrap().unwrap();
+        assert_eq!(
+            client1.balance(Currency::default()).total,
+            Balance(dec!(-50))
+        );
+        assert!(engine.violations().is_empty());
+        let client2 = account_store.get(2).await.unwrap().unwrap();
+        assert_eq!(client2.limits.reserve, Balance(dec!(4)));
+        assert_eq!(
+            client2.balance(Currency::default()).available,
+            Balance(dec!(4))
+        );
+
+        // The limits are part of the event log
+        let replayed = event::replay(&event_store.get_all().await.unwrap()).unwrap();
+        assert_eq!(replayed, vec![client1, client2]);
+    }
+
+    #[tokio::test]
+    async fn test_fees() {
+        let account_store = InMemoryAccountStore::new();
+        let journal_store = InMemoryJournalStore::new();
+        let event_store = InMemoryEventStore::new();
+        let fee_store = InMemoryFeeStore::new();
+        let schedule = FeeSchedule {
+            withdrawal: Some(FeeRule::Flat { amount: dec!(1) }),
+            transfer: Some(FeeRule::Percentage { percent: dec!(10) }),
+            chargeback: Some(FeeRule::Flat { amount: dec!(30) }),
+        };
+        let engine = PaymentEngine::new(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+        )
+        .with_journal(Box::new(journal_store.clone()))
+        .with_event_log(Box::new(event_store.clone()))
+        .with_fees(schedule, Box::new(fee_store.clone()));
+
+        let mut transfer = tx(TransactionType::Transfer, 1, 4, Some("20"));
+        transfer.destination = Some(2);
+        let mut outcomes = Vec::new();
+        for transaction in [
+            tx(TransactionType::Deposit, 1, 1, Some("50")),
+            // The amount and the fee are debited together, or not at all
+            tx(TransactionType::Withdrawal, 1, 2, Some("50")),
+            tx(TransactionType::Withdrawal, 1, 3, Some("9")),
+            transfer,
+            // The chargeback fee takes what is left
+            tx(TransactionType::Deposit, 2, 5, Some("5")),
+            tx(TransactionType::Dispute, 2, 5, None),
+            tx(TransactionType::Chargeback, 2, 5, None),
+        ] {
+            outcomes.push(engine.process_transaction(transaction).await.unwrap());
+        }
+
+        assert_eq!(
+            outcomes,
+            vec![
+                TransactionOutcome::Applied,
+                TransactionOutcome::rejected(RejectionReason::InsufficientFunds),
+                TransactionOutcome::Applied,
+                TransactionOutcome::Applied,
+                TransactionOutcome::Applied,
+                TransactionOutcome::Applied,
+                TransactionOutcome::Applied,
+            ]
+        );
+        let client1 = account_store.get(1).await.unwrap().unwrap();
+        assert_eq!(
+            client1.balance(Currency::default()).total,
+            Balance(dec!(18))
+        );
+        let client2 = account_store.get(2).await.unwrap().unwrap();
+        assert_eq!(client2.balance(Currency::default()).total, Balance::ZERO);
+        assert_eq!(client2.status, AccountStatus::Locked);
+
+        // 1 (withdrawal) + 2 (transfer) + 20 (chargeback, capped at the available balance)
+        let fees = FeeStore::get(&fee_store).await.unwrap();
+        assert_eq!(
+            fees.balances.get(&Currency::default()),
+            Some(&Balance(dec!(23)))
+        );
+        assert_eq!(engine.snapshot().await.unwrap().fees, fees);
+        assert!(engine.trial_balance().await.unwrap().is_empty());
+        let replayed = event::replay(&event_store.get_all().await.unwrap()).unwrap();
+        assert_eq!(replayed, vec![client1, client2]);
+    }
+
+    #[tokio::test]
+    async fn test_dispute_funds_policies() {
+        let outcomes_for = |policy| async move {
+            let account_store = InMemoryAccountStore::new();
+            let transaction_store = InMemoryTransactionStore::new();
+            let event_store = InMemoryEventStore::new();
+            let config = EngineConfig {
+                dispute_funds: policy,
+                strict_invariants: true,
+                ..EngineConfig::default()
+            };
+            let engine = PaymentEngine::with_config(
+                Box::new(account_store.clone()),
+                Box::new(transaction_store.clone()),
+                config,
+            )
+            .with_journal(Box::new(InMemoryJournalStore::new()))
+            .with_event_log(Box::new(event_store.clone()));
+
+            let mut outcomes = Vec::new();
+            for transaction in [
+                tx(TransactionType::Deposit, 1, 1, Some("100")),
+                tx(TransactionType::Withdrawal, 1, 2, Some("70")),
+                tx(TransactionType::Dispute, 1, 1, Some("50")),
+                tx(TransactionType::Dispute, 1, 1, Some("50")),
+                tx(TransactionType::Resolve, 1, 1, Some("50")),
+                tx(TransactionType::Chargeback, 1, 1, None),
+            ] {
+                outcomes.push(engine.process_transaction(transaction).await.unwrap());
+            }
+            assert!(engine.trial_balance().await.unwrap().is_empty());
+            let account = account_store.get(1).await.unwrap().unwrap();
+            let replayed = event::replay(&event_store.get_all().await.unwrap()).unwrap();
+            assert_eq!(replayed, vec![account.clone()]);
+            let record = transaction_store.get_disputes(1).await.unwrap();
+            (outcomes, account, record)
+        };
+        let currency = Currency::default();
+
+        // The dispute is rejected, so are the operations on it
+        let (outcomes, account, _) = outcomes_for(DisputeFundsPolicy::Reject).await;
+        assert_eq!(
+            outcomes[2],
+            TransactionOutcome::rejected(RejectionReason::InsufficientFunds)
+        );
+        assert_eq!(account.balance(currency).available, Balance(dec!(30)));
+
+        // The whole amount is held, then the first dispute released and the second charged back
+        let (outcomes, account, record) = outcomes_for(DisputeFundsPolicy::AllowNegative).await;
+        assert!(outcomes.iter().all(TransactionOutcome::is_applied));
+        assert_eq!(account.balance(currency).available, Balance(dec!(-20)));
+        assert_eq!(account.balance(currency).total, Balance(dec!(-20)));
+        assert_eq!(account.status, AccountStatus::Locked);
+        let record = record.unwrap();
+        assert!(record.disputes.iter().all(|d| d.shortfall == Balance::ZERO));
+
+        // 30 of the first dispute are held and 20 owed, all of the second is owed: the resolve
+        // releases the 30 and cancels 20 of the debt, and the chargeback leaves the other 50 owed
+        let (outcomes, account, record) = outcomes_for(DisputeFundsPolicy::HoldAvailable).await;
+        assert!(outcomes.iter().all(TransactionOutcome::is_applied));
+        assert_eq!(account.balance(currency).available, Balance(dec!(30)));
+        assert_eq!(account.balance(currency).held, Balance::ZERO);
+        assert_eq!(account.receivable(currency), Balance(dec!(50)));
+        assert_eq!(account.status, AccountStatus::Locked);
+        assert_eq!(
+            record.unwrap().disputes,
+            vec![
+                Dispute {
+                    amount: Balance(dec!(50)),
+                    shortfall: Balance(dec!(20)),
+                    status: DisputeStatus::Resolved,
+                },
+                Dispute {
+                    amount: Balance(dec!(50)),
+                    shortfall: Balance(dec!(50)),
+                    status: DisputeStatus::Chargebacked,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_dispute_leaves_account_untouched() {
+        let account_store = InMemoryAccountStore::new();
+        let engine = PaymentEngine::with_config(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+            EngineConfig {
+                dispute_funds: DisputeFundsPolicy::HoldAvailable,
+                ..EngineConfig::default()
+            },
+        );
+        let currency = Currency::default();
+        for transaction in [
+            tx(TransactionType::Deposit, 1, 1, Some("100")),
+            tx(TransactionType::Withdrawal, 1, 2, Some("70")),
+        ] {
+            engine.process_transaction(transaction).await.unwrap();
+        }
+        // Owing the most a balance can hold, so the 20 the dispute cannot hold overflows
+        let mut account = account_store.get(1).await.unwrap().unwrap();
+        account.receivables.insert(currency, Balance(Decimal::MAX));
+        account_store.store(account).await.unwrap();
+
+        let outcome = engine
+            .process_transaction(tx(TransactionType::Dispute, 1, 1, Some("50")))
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome,
+            TransactionOutcome::rejected(RejectionReason::ArithmeticOverflow)
+        );
+        // The 30 the dispute could hold were not held either
+        let account = account_store.get(1).await.unwrap().unwrap();
+        assert_eq!(account.balance(currency).available, Balance(dec!(30)));
+        assert_eq!(account.balance(currency).held, Balance::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_auto_lock() {
+        let account_store = InMemoryAccountStore::new();
+        let event_store = InMemoryEventStore::new();
+        let config = EngineConfig {
+            auto_lock: AutoLockPolicy {
+                max_open_disputes: Some(1),
+                max_disputed_value: Some(dec!(25)),
+            },
+            ..EngineConfig::default()
+        };
+        let engine = PaymentEngine::with_config(
+            Box::new(account_store.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+            config,
+        )
+        .with_event_log(Box::new(event_store.clone()));
+
+        let mut outcomes = Vec::new();
+        for transaction in [
+            // Client 1 has two transactions under dispute at once
+            tx(TransactionType::Deposit, 1, 1, Some("10")),
+            tx(TransactionType::Deposit, 1, 2, Some("10")),
+            tx(TransactionType::Deposit, 1, 3, Some("10")),
+            tx(TransactionType::Dispute, 1, 1, None),
+            tx(TransactionType::Dispute, 1, 2, None),
+            tx(TransactionType::Dispute, 1, 3, None),
+            // Client 2 disputes 40 in total, but never more than one transaction at once
+            tx(TransactionType::Deposit, 2, 4, Some("20")),
+            tx(TransactionType::Deposit, 2, 5, Some("20")),
+            tx(TransactionType::Dispute, 2, 4, None),
+            tx(TransactionType::Resolve, 2, 4, None),
+            tx(TransactionType::Dispute, 2, 5, None),
+        ] {
+            outcomes.push(engine.process_transaction(transaction).await.unwrap());
+        }
+
+        assert_eq!(
+            outcomes[5],
+            TransactionOutcome::rejected(RejectionReason::AccountLocked)
+        );
+        assert!(outcomes[..5].iter().all(TransactionOutcome::is_applied));
+        assert!(outcomes[6..].iter().all(TransactionOutcome::is_applied));
+
+        let client1 = account_store.get(1).await.unwrap().unwrap();
+        assert_eq!(client1.status, AccountStatus::Locked);
+        assert_eq!(
+            client1.lock_reason,
+            Some(LockReason {
+                tx: 2,
+                r#type: TransactionType::Dispute,
+                cause: LockCause::OpenDisputes,
+                position: 4,
+                timestamp: None,
+            })
+        );
+        assert_eq!(client1.disputes.open, 2);
+        let client2 = account_store.get(2).await.unwrap().unwrap();
+        assert_eq!(
+            client2.lock_reason,
+            Some(LockReason {
+                tx: 5,
+                r#type: TransactionType::Dispute,
+                cause: LockCause::DisputedValue,
+                position: 10,
+                timestamp: None,
+            })
+        );
+        assert_eq!(client2.disputes.open, 1);
+
+        let replayed = event::replay(&event_store.get_all().await.unwrap()).unwrap();
+        assert_eq!(replayed, vec![client1, client2]);
+    }
+
+    #[tokio::test]
+    async fn test_locked_dispute_policies() {
+        let balance = |available, held, total| CurrencyBalance {
+            available: Balance::new(available),
+            held: Balance::new(held),
+            total: Balance::new(total),
+        };
+        for (policy, settled, disputed, expected) in [
+            (
+                LockedDisputePolicy::Block,
+                false,
+                false,
+                balance(dec!(30), dec!(20), dec!(50)),
+            ),
+            (
+                LockedDisputePolicy::Settle,
+                true,
+                false,
+                balance(dec!(50), dec!(0), dec!(50)),
+            ),
+            (
+                LockedDisputePolicy::SettleAndDispute,
+                true,
+                true,
+                balance(dec!(20), dec!(0), dec!(20)),
+            ),
+        ] {
+            let account_store = InMemoryAccountStore::new();
+            let event_store = InMemoryEventStore::new();
+            let config = EngineConfig {
+                locked_disputes: policy,
+                ..EngineConfig::default()
+            };
+            let engine = PaymentEngine::with_config(
+                Box::new(account_store.clone()),
+                Box::new(InMemoryTransactionStore::new()),
+                config,
+            )
+            .with_event_log(Box::new(event_store.clone()));
+
+            let mut outcomes = Vec::new();
+            for transaction in [
+                tx(TransactionType::Deposit, 1, 1, Some("10")),
+                tx(TransactionType::Deposit, 1, 2, Some("20")),
+                tx(TransactionType::Deposit, 1, 3, Some("30")),
+                tx(TransactionType::Dispute, 1, 1, None),
+                tx(TransactionType::Dispute, 1, 2, None),
+                // Locks the account while tx 2 is still under dispute
+                tx(TransactionType::Chargeback, 1, 1, None),
+                tx(TransactionType::Resolve, 1, 2, None),
+                tx(TransactionType::Dispute, 1, 3, None),
+                tx(TransactionType::Chargeback, 1, 3, None),
+                tx(TransactionType::Deposit, 1, 4, Some("5")),
+                tx(TransactionType::Withdrawal, 1, 5, Some("5")),
+            ] {
+                outcomes.push(engine.process_transaction(transaction).await.unwrap());
+            }
+
+            assert!(outcomes[..6].iter().all(TransactionOutcome::is_applied));
+            assert_eq!(outcomes[6].is_applied(), settled, "{policy:?}");
+            assert_eq!(outcomes[7].is_applied(), disputed, "{policy:?}");
+            assert_eq!(outcomes[8].is_applied(), disputed, "{policy:?}");
+            for outcome in &outcomes[9..] {
+                assert_eq!(
+                    *outcome,
+                    TransactionOutcome::rejected(RejectionReason::AccountLocked)
+                );
+            }
+
+            let account = account_store.get(1).await.unwrap().unwrap();
+            assert_eq!(account.status, AccountStatus::Locked);
+            assert_eq!(account.balance(Currency::default()), expected, "{policy:?}");
+            // The first chargeback keeps the lock reason
+            assert_eq!(
+                account.lock_reason.as_ref().map(|reason| reason.tx),
+                Some(1)
+            );
+
+            let replayed = event::replay(&event_store.get_all().await.unwrap()).unwrap();
+            assert_eq!(replayed, vec![account]);
+        }
+    }
+
+    /// A unit of work store failing every commit, as if the process crashed before it.
+    struct CrashingUnitOfWork;
+
+    #[async_trait]
+    impl UnitOfWorkStore for CrashingUnitOfWork {
+        async fn commit(&self, _work: UnitOfWork) -> Result<()> {
+            Err(PaymentError::InternalError(Box::new(
+                std::io::Error::other("crashed"),
+            )))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unit_of_work_commits_account_and_transaction_together() {
+        let account_store = InMemoryAccountStore::new();
+        let tx_store = InMemoryTransactionStore::new();
+        let engine =
+            PaymentEngine::new(Box::new(account_store.clone()), Box::new(tx_store.clone()))
+                .with_unit_of_work(Box::new(CrashingUnitOfWork));
+
+        let deposit = tx(TransactionType::Deposit, 1, 1, Some("10"));
+        assert!(engine.process_transaction(deposit.clone()).await.is_err());
+        // Neither the account nor the transaction was written
+        assert!(account_store.get(1).await.unwrap().is_none());
+        assert!(!tx_store.exists(1).await.unwrap());
+
+        // Nor the fee, risk state, journal entries and events of a transaction
+        let fee_store = InMemoryFeeStore::new();
+        let risk_store = InMemoryRiskStore::new();
+        let journal_store = InMemoryJournalStore::new();
+        let event_store = InMemoryEventStore::new();
+        let schedule = FeeSchedule {
+            withdrawal: Some(FeeRule::Flat { amount: dec!(1) }),
+            ..FeeSchedule::default()
+        };
+        let funded = InMemoryAccountStore::new();
+        PaymentEngine::new(
+            Box::new(funded.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+        )
+        .process_transaction(tx(TransactionType::Deposit, 2, 2, Some("10")))
+        .await
+        .unwrap();
+        let engine = PaymentEngine::new(
+            Box::new(funded.clone()),
+            Box::new(InMemoryTransactionStore::new()),
+        )
+        .with_unit_of_work(Box::new(CrashingUnitOfWork))
+        .with_fees(schedule, Box::new(fee_store.clone()))
+        .with_risk_rules(Vec::new(), Box::new(risk_store.clone()))
+        .with_journal(Box::new(journal_store.clone()))
+        .with_event_log(Box::new(event_store.clone()));
+        let withdrawal = tx(TransactionType::Withdrawal, 2, 3, Some("5"));
+        assert!(engine.process_transaction(withdrawal).await.is_err());
+        assert_eq!(fee_store.get().await.unwrap(), FeeAccount::default());
+        assert!(risk_store.get(2).await.unwrap().is_none());
+        assert!(journal_store.get_all().await.unwrap().is_empty());
+        assert!(event_store.get_all().await.unwrap().is_empty());
+
+        // A rerun applies the deposit rather than rejecting it as a duplicate
+        let engine =
+            PaymentEngine::new(Box::new(account_store.clone()), Box::new(tx_store.clone()))
+                .with_unit_of_work(Box::new(InMemoryUnitOfWork::new(
+                    account_store.clone(),
+                    tx_store.clone(),
+                )));
+        assert_eq!(
+            engine.process_transaction(deposit).await.unwrap(),
+            TransactionOutcome::Applied
+        );
+        let account = account_store.get(1).await.unwrap().unwrap();
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(10))
+        );
+        assert!(tx_store.exists(1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_transactions_after_snapshot() {
+        use crate::infrastructure::file::FileSnapshotStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_path = dir.path().join("snapshot.json");
+        let account_store = InMemoryAccountStore::new();
+        let tx_store = InMemoryTransactionStore::new();
+        let unit_of_work = InMemoryUnitOfWork::new(account_store.clone(), tx_store.clone());
+        let engine = || {
+            PaymentEngine::new(Box::new(account_store.clone()), Box::new(tx_store.clone()))
+                .with_unit_of_work(Box::new(unit_of_work.clone()))
+                .with_checkpoints(Box::new(unit_of_work.clone()))
+                .with_snapshots(Box::new(FileSnapshotStore::new(&snapshot_path)), 2)
+        };
+        let fingerprint = Fingerprint::new(100, b"type,client,tx,amount");
+        let end = |record| InputPosition {
+            byte: record * 10,
+            line: record + 1,
+            record,
+        };
+        let input = [
+            tx(TransactionType::Deposit, 1, 1, Some("100")),
+            tx(TransactionType::Deposit, 1, 2, Some("50")),
+            // Applied after the snapshot, then the run stops
+            tx(TransactionType::Dispute, 1, 1, Some("40")),
+            tx(TransactionType::Dispute, 1, 2, None),
+        ];
+
+        let first = engine();
+        for (record, tx) in input[..3].iter().enumerate() {
+            first
+                .process_checkpointed(tx.clone(), fingerprint, end(record as u64 + 1))
+                .await
+                .unwrap();
+        }
+
+        // The stores went past the snapshot taken after the second transaction
+        let second = engine();
+        assert_eq!(second.resume().await.unwrap(), Some(3));
+        for (record, tx) in input.iter().enumerate().skip(3) {
+            assert!(
+                second
+                    .process_checkpointed(tx.clone(), fingerprint, end(record as u64 + 1))
+                    .await
+                    .unwrap()
+                    .is_applied()
+            );
+        }
+
+        // The dispute after the snapshot was held once
+        let account = account_store.get(1).await.unwrap().unwrap();
+        let balance = account.balance(Currency::default());
+        assert_eq!(balance.held, Balance::new(dec!(90)));
+        assert_eq!(balance.available, Balance::new(dec!(60)));
+        // Writes after the snapshot were not collected by the resumed engine, so its snapshot
+        // after the fourth transaction is a full one
+        let snapshots = std::fs::read_to_string(&snapshot_path).unwrap();
+        assert_eq!(snapshots.lines().count(), 1);
+        let restored = second.snapshot_store().unwrap().latest().await.unwrap();
+        assert_eq!(restored.unwrap().position, 4);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_resume_without_reapplying() {
+        let account_store = InMemoryAccountStore::new();
+        let tx_store = InMemoryTransactionStore::new();
+        let unit_of_work = InMemoryUnitOfWork::new(account_store.clone(), tx_store.clone());
+        let engine = || {
+            PaymentEngine::new(Box::new(account_store.clone()), Box::new(tx_store.clone()))
+                .with_unit_of_work(Box::new(unit_of_work.clone()))
+                .with_checkpoints(Box::new(unit_of_work.clone()))
+        };
+        let fingerprint = Fingerprint::new(100, b"type,client,tx,amount");
+        let end = |record| InputPosition {
+            byte: record * 10,
+            line: record + 1,
+            record,
+        };
+        let input = [
+            tx(TransactionType::Deposit, 1, 1, Some("100")),
+            tx(TransactionType::Dispute, 1, 1, Some("40")),
+            // Rejected before anything is written
+            tx(TransactionType::Resolve, 2, 1, None),
+        ];
+
+        let first = engine();
+        assert!(
+            first
+                .process_checkpointed(input[0].clone(), fingerprint, end(1))
+                .await
+                .unwrap()
+                .is_applied()
+        );
+        first
+            .process_checkpointed(input[1].clone(), fingerprint, end(2))
+            .await
+            .unwrap();
+        assert!(
+            !first
+                .process_checkpointed(input[2].clone(), fingerprint, end(3))
+                .await
+                .unwrap()
+                .is_applied()
+        );
+        assert_eq!(
+            unit_of_work.latest().await.unwrap(),
+            Some(Checkpoint {
+                fingerprint,
+                input: end(3),
+                position: 3,
+            })
+        );
+
+        // After a restart, the input resumes after the last record, dispute included
+        let second = engine();
+        let other = Fingerprint::new(100, b"type,client,tx");
+        assert!(second.resume_input(&other).await.is_err());
+        let checkpoint = second.resume_input(&fingerprint).await.unwrap().unwrap();
+        assert_eq!(checkpoint.input, end(3));
+        assert_eq!(second.position(), 3);
+
+        let account = account_store.get(1).await.unwrap().unwrap();
+        assert_eq!(
+            account.balance(Currency::default()).held,
+            Balance::new(dec!(40))
+        );
+
+        // Checkpoints must be enabled
+        let plain = PaymentEngine::new(
+            Box::new(InMemoryAccountStore::new()),
+            Box::new(InMemoryTransactionStore::new()),
+        );
+        assert!(
+            plain
+                .process_checkpointed(input[0].clone(), fingerprint, end(1))
+                .await
+                .is_err()
+        );
     }
 }
@@ -4,4 +4,5 @@
 //! for processing transactions. It uses an Actor-like pattern with `tokio` channels
 //! to manage concurrency and state isolation.
 
+pub mod config;
 pub mod engine;
@@ -1,7 +1,9 @@
+use crate::domain::currency::Currency;
+use crate::domain::transaction::TransactionType;
 use crate::error::PaymentError;
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize, Serializer};
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Represents a monetary value with 4 decimal places precision.
 ///
@@ -30,6 +32,13 @@ impl Amount {
     pub fn value(&self) -> Decimal {
         self.0
     }
+
+    /// Adds two amounts, returning `PaymentError::Overflow` instead of panicking.
+    pub fn checked_add(self, rhs: Self) -> Result<Self, PaymentError> {
+        Balance(self.0)
+            .checked_add(Balance(rhs.0))
+            .map(|sum| Self(sum.0))
+    }
 }
 
 impl TryFrom<Decimal> for Amount {
@@ -58,81 +67,211 @@ impl Balance {
     pub fn new(amount: Decimal) -> Self {
         Self(amount)
     }
-}
 
-// Implement basic arithmetic for Balance to make it a usable Value Object
-impl Add for Balance {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
+    /// Adds two balances, returning `PaymentError::Overflow` instead of panicking.
+    pub fn checked_add(self, rhs: Self) -> Result<Self, PaymentError> {
+        self.0
+            .checked_add(rhs.0)
+            .map(Self)
+            .ok_or_else(|| PaymentError::Overflow(format!("{} + {}", self.0, rhs.0)))
     }
+
+    /// Subtracts two balances, returning `PaymentError::Overflow` instead of panicking.
+    pub fn checked_sub(self, rhs: Self) -> Result<Self, PaymentError> {
+        self.0
+            .checked_sub(rhs.0)
+            .map(Self)
+            .ok_or_else(|| PaymentError::Overflow(format!("{} - {}", self.0, rhs.0)))
+    }
+}
+
+/// The lifecycle status of a client account.
+///
+/// - `Active`: Accepts every transaction.
+/// - `Frozen`: Set by an administrator. Outgoing funds (withdrawals and transfers) are blocked,
+///   everything else is accepted.
+/// - `Locked`: Set by a chargeback. Only administrative operations are accepted.
+/// - `Closed`: Set by an administrator. Terminal, no transaction is accepted.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountStatus {
+    Active,
+    Frozen,
+    Locked,
+    Closed,
 }
 
-impl Sub for Balance {
-    type Output = Self;
-    fn sub(self, rhs: Self) -> Self::Output {
-        Self(self.0 - rhs.0)
+impl AccountStatus {
+    /// Returns `true` if an account in this status accepts a transaction of the given type
+    /// issued by its owner (or by an administrator, for administrative operations).
+    pub fn accepts(&self, r#type: TransactionType) -> bool {
+        match self {
+            Self::Active => true,
+            Self::Frozen => !matches!(
+                r#type,
+                TransactionType::Withdrawal | TransactionType::Transfer
+            ),
+            Self::Locked => r#type.is_admin(),
+            Self::Closed => false,
+        }
     }
+
+    /// Returns `true` if an account in this status can receive funds from a transfer.
+    pub fn accepts_incoming(&self) -> bool {
+        matches!(self, Self::Active | Self::Frozen)
+    }
+}
+
+/// Credit limits of a client account, applying to each of its currencies.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+pub struct AccountLimits {
+    /// How far below zero withdrawals and holds may take the available balance.
+    #[serde(default)]
+    pub overdraft: Balance,
+    /// The available balance withdrawals and holds must leave in the account.
+    #[serde(default)]
+    pub reserve: Balance,
 }
 
-impl AddAssign for Balance {
-    fn add_assign(&mut self, rhs: Self) {
-        self.0 += rhs.0;
+impl AccountLimits {
+    /// Returns the lowest available balance a withdrawal or hold may leave.
+    pub fn floor(&self) -> Result<Balance, PaymentError> {
+        self.reserve.checked_sub(self.overdraft)
+    }
+
+    /// Returns `true` if no limit is set, the available balance then stopping at zero.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
     }
 }
 
-impl SubAssign for Balance {
-    fn sub_assign(&mut self, rhs: Self) {
-        self.0 -= rhs.0;
+/// Dispute counters of a client account, over the processed stream.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct DisputeStats {
+    /// The number of transactions of the client currently under an open dispute.
+    #[serde(default)]
+    pub open: u32,
+    /// The total amount ever disputed by the client, per currency.
+    #[serde(default)]
+    pub value: BTreeMap<Currency, Balance>,
+}
+
+impl DisputeStats {
+    /// Counts a new dispute of `amount`, on a transaction that had no open dispute if
+    /// `opened`.
+    pub fn record_dispute(
+        &mut self,
+        currency: Currency,
+        amount: Balance,
+        opened: bool,
+    ) -> Result<(), PaymentError> {
+        let value = self.disputed_value(currency).checked_add(amount)?;
+        self.value.insert(currency, value);
+        if opened {
+            self.open += 1;
+        }
+        Ok(())
+    }
+
+    /// Counts a transaction whose disputes were all resolved or charged back.
+    pub fn record_closed(&mut self) {
+        self.open = self.open.saturating_sub(1);
+    }
+
+    /// Returns the total amount ever disputed in the given currency.
+    pub fn disputed_value(&self, currency: Currency) -> Balance {
+        self.value.get(&currency).copied().unwrap_or(Balance::ZERO)
+    }
+
+    /// Returns `true` if the client never disputed anything.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
     }
 }
 
+/// Why an account was locked.
+///
+/// - `Chargeback`: A chargeback reversed one of its transactions.
+/// - `OpenDisputes`: It had too many transactions under an open dispute.
+/// - `DisputedValue`: It disputed too large an amount.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LockCause {
+    Chargeback,
+    OpenDisputes,
+    DisputedValue,
+}
+
+/// The transaction that locked an account, and why.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
-#[serde(rename_all = "lowercase")]
-pub enum AccountStatus {
-    Active,
-    Locked,
+pub struct LockReason {
+    /// The id of the triggering transaction.
+    pub tx: u32,
+    /// The type of the triggering transaction.
+    pub r#type: TransactionType,
+    pub cause: LockCause,
+    /// The position of the triggering transaction in the input (0 for the first one).
+    pub position: u64,
+    /// When the account was locked, in seconds since the Unix epoch (if recorded).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
 }
 
-/// Represents the state of a client's account.
-///
-/// Tracks available funds, held funds (for disputes), and the total balance.
-/// Also maintains the account status (Active or Locked).
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-pub struct ClientAccount {
-    /// The unique identifier for the client.
-    pub client: u16,
+/// Balances of a client account in a single currency.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+pub struct CurrencyBalance {
     /// Funds available for withdrawal or trading.
     pub available: Balance,
     /// Funds held due to disputes.
     pub held: Balance,
     /// Total funds (available + held).
     pub total: Balance,
-    /// The status of the account (Active or Locked).
-    #[serde(
-        rename = "locked",
-        serialize_with = "serialize_bool",
-        deserialize_with = "deserialize_bool"
-    )]
-    pub status: AccountStatus,
 }
 
-fn serialize_bool<S>(status: &AccountStatus, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    serializer.serialize_bool(*status == AccountStatus::Locked)
+/// Represents the state of a client's account.
+///
+/// Tracks available funds, held funds (for disputes), and the total balance for every
+/// currency the client transacts in. Also maintains the account status (Active or Locked),
+/// which applies to all currencies.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ClientAccount {
+    /// The unique identifier for the client.
+    pub client: u16,
+    /// The balances per currency.
+    pub balances: BTreeMap<Currency, CurrencyBalance>,
+    /// The status of the account.
+    #[serde(alias = "locked", deserialize_with = "deserialize_status")]
+    pub status: AccountStatus,
+    /// The credit limits of the account.
+    #[serde(default, skip_serializing_if = "AccountLimits::is_default")]
+    pub limits: AccountLimits,
+    /// Disputed funds the client could not cover, per currency, owed by the client.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub receivables: BTreeMap<Currency, Balance>,
+    /// The dispute counters of the client.
+    #[serde(default, skip_serializing_if = "DisputeStats::is_default")]
+    pub disputes: DisputeStats,
+    /// Why the account is locked (`None` unless it is).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock_reason: Option<LockReason>,
 }
 
-fn deserialize_bool<'de, D>(deserializer: D) -> Result<AccountStatus, D::Error>
+/// Accepts both the current status string and the legacy `locked` boolean.
+fn deserialize_status<'de, D>(deserializer: D) -> Result<AccountStatus, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let locked = bool::deserialize(deserializer)?;
-    if locked {
-        Ok(AccountStatus::Locked)
-    } else {
-        Ok(AccountStatus::Active)
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StatusRepr {
+        Locked(bool),
+        Status(AccountStatus),
+    }
+
+    match StatusRepr::deserialize(deserializer)? {
+        StatusRepr::Locked(true) => Ok(AccountStatus::Locked),
+        StatusRepr::Locked(false) => Ok(AccountStatus::Active),
+        StatusRepr::Status(status) => Ok(status),
     }
 }
 
@@ -140,24 +279,65 @@ impl ClientAccount {
     pub fn new(client: u16) -> Self {
         Self {
             client,
-            available: Balance::ZERO,
-            held: Balance::ZERO,
-            total: Balance::ZERO,
+            balances: BTreeMap::new(),
             status: AccountStatus::Active,
+            limits: AccountLimits::default(),
+            receivables: BTreeMap::new(),
+            disputes: DisputeStats::default(),
+            lock_reason: None,
         }
     }
 
+    /// Returns the balances in the given currency (zero if the client never used it).
+    pub fn balance(&self, currency: Currency) -> CurrencyBalance {
+        self.balances.get(&currency).copied().unwrap_or_default()
+    }
+
+    /// Returns a mutable reference to the balances in the given currency, creating them if needed.
+    pub fn balance_mut(&mut self, currency: Currency) -> &mut CurrencyBalance {
+        self.balances.entry(currency).or_default()
+    }
+
     /// Deposits funds into the available balance
-    pub fn deposit(&mut self, amount: Balance) {
-        self.available += amount;
-        self.total += amount;
+    pub fn deposit(&mut self, currency: Currency, amount: Balance) -> Result<(), PaymentError> {
+        let balance = self.balance_mut(currency);
+        let available = balance.available.checked_add(amount)?;
+        let total = balance.total.checked_add(amount)?;
+        balance.available = available;
+        balance.total = total;
+        Ok(())
+    }
+
+    /// Returns how much of the available balance can be taken without going below the floor
+    /// of the account (zero if it is already below).
+    pub fn headroom(&self, currency: Currency) -> Balance {
+        match self
+            .limits
+            .floor()
+            .and_then(|floor| self.balance(currency).available.checked_sub(floor))
+        {
+            Ok(headroom) if headroom > Balance::ZERO => headroom,
+            _ => Balance::ZERO,
+        }
+    }
+
+    /// Returns the disputed funds owed by the client in the given currency.
+    pub fn receivable(&self, currency: Currency) -> Balance {
+        self.receivables
+            .get(&currency)
+            .copied()
+            .unwrap_or(Balance::ZERO)
     }
 
-    /// Withdraws funds from available if sufficient
-    pub fn withdraw(&mut self, amount: Balance) -> Result<(), PaymentError> {
-        if self.available >= amount {
-            self.available -= amount;
-            self.total -= amount;
+    /// Withdraws funds from available if sufficient, within the limits of the account
+    pub fn withdraw(&mut self, currency: Currency, amount: Balance) -> Result<(), PaymentError> {
+        let floor = self.limits.floor()?;
+        let balance = self.balance_mut(currency);
+        let available = balance.available.checked_sub(amount)?;
+        if available >= floor {
+            let total = balance.total.checked_sub(amount)?;
+            balance.available = available;
+            balance.total = total;
             Ok(())
         } else {
             Err(PaymentError::ValidationError(
@@ -166,11 +346,15 @@ impl ClientAccount {
         }
     }
 
-    /// Holds funds (moves from available to held)
-    pub fn hold(&mut self, amount: Balance) -> Result<(), PaymentError> {
-        if self.available >= amount {
-            self.available -= amount;
-            self.held += amount;
+    /// Holds funds (moves from available to held), within the limits of the account
+    pub fn hold(&mut self, currency: Currency, amount: Balance) -> Result<(), PaymentError> {
+        let floor = self.limits.floor()?;
+        let balance = self.balance_mut(currency);
+        let available = balance.available.checked_sub(amount)?;
+        if available >= floor {
+            let held = balance.held.checked_add(amount)?;
+            balance.available = available;
+            balance.held = held;
             Ok(())
         } else {
             Err(PaymentError::ValidationError(
@@ -179,11 +363,66 @@ impl ClientAccount {
         }
     }
 
+    /// Holds funds for a dispute regardless of the limits, letting available go negative
+    pub fn hold_overdrawn(
+        &mut self,
+        currency: Currency,
+        amount: Balance,
+    ) -> Result<(), PaymentError> {
+        let balance = self.balance_mut(currency);
+        let available = balance.available.checked_sub(amount)?;
+        let held = balance.held.checked_add(amount)?;
+        balance.available = available;
+        balance.held = held;
+        Ok(())
+    }
+
+    /// Records disputed funds the client could not cover as owed by the client
+    pub fn add_receivable(
+        &mut self,
+        currency: Currency,
+        amount: Balance,
+    ) -> Result<(), PaymentError> {
+        if amount == Balance::ZERO {
+            return Ok(());
+        }
+        let receivable = self.receivable(currency).checked_add(amount)?;
+        self.receivables.insert(currency, receivable);
+        Ok(())
+    }
+
+    /// Cancels owed funds (the dispute they covered was resolved)
+    pub fn clear_receivable(
+        &mut self,
+        currency: Currency,
+        amount: Balance,
+    ) -> Result<(), PaymentError> {
+        let receivable = self.receivable(currency);
+        if amount == Balance::ZERO {
+            return Ok(());
+        }
+        if receivable < amount {
+            return Err(PaymentError::ValidationError(
+                "Receivable mismatch".to_string(),
+            ));
+        }
+        let receivable = receivable.checked_sub(amount)?;
+        if receivable == Balance::ZERO {
+            self.receivables.remove(&currency);
+        } else {
+            self.receivables.insert(currency, receivable);
+        }
+        Ok(())
+    }
+
     /// Resolves a hold (moves from held to available (i.e. inverse from `hold`)
-    pub fn resolve(&mut self, amount: Balance) -> Result<(), PaymentError> {
-        if self.held >= amount {
-            self.held -= amount;
-            self.available += amount;
+    pub fn resolve(&mut self, currency: Currency, amount: Balance) -> Result<(), PaymentError> {
+        let balance = self.balance_mut(currency);
+        if balance.held >= amount {
+            let held = balance.held.checked_sub(amount)?;
+            let available = balance.available.checked_add(amount)?;
+            balance.held = held;
+            balance.available = available;
             Ok(())
         } else {
             Err(PaymentError::ValidationError(
@@ -193,10 +432,117 @@ impl ClientAccount {
     }
 
     /// Chargeback (removes from held and locks account)
-    pub fn chargeback(&mut self, amount: Balance) -> Result<(), PaymentError> {
-        if self.held >= amount {
-            self.held -= amount;
-            self.total -= amount;
+    pub fn chargeback(&mut self, currency: Currency, amount: Balance) -> Result<(), PaymentError> {
+        let balance = self.balance_mut(currency);
+        if balance.held >= amount {
+            let held = balance.held.checked_sub(amount)?;
+            let total = balance.total.checked_sub(amount)?;
+            balance.held = held;
+            balance.total = total;
+            self.status = AccountStatus::Locked;
+            Ok(())
+        } else {
+            Err(PaymentError::ValidationError(
+                "Held funds mismatch".to_string(),
+            ))
+        }
+    }
+
+    /// Freezes the account (administrative, only from Active)
+    pub fn freeze(&mut self) -> Result<(), PaymentError> {
+        self.transition(AccountStatus::Frozen, &[AccountStatus::Active])
+    }
+
+    /// Locks the account, recording why
+    pub fn lock(&mut self, reason: LockReason) {
+        self.status = AccountStatus::Locked;
+        self.lock_reason = Some(reason);
+    }
+
+    /// Unlocks or unfreezes the account (administrative, from Frozen or Locked)
+    pub fn unlock(&mut self) -> Result<(), PaymentError> {
+        self.transition(
+            AccountStatus::Active,
+            &[AccountStatus::Frozen, AccountStatus::Locked],
+        )?;
+        self.lock_reason = None;
+        Ok(())
+    }
+
+    /// Closes the account for good (administrative, from any other status)
+    pub fn close(&mut self) -> Result<(), PaymentError> {
+        self.transition(
+            AccountStatus::Closed,
+            &[
+                AccountStatus::Active,
+                AccountStatus::Frozen,
+                AccountStatus::Locked,
+            ],
+        )
+    }
+
+    fn transition(
+        &mut self,
+        to: AccountStatus,
+        from: &[AccountStatus],
+    ) -> Result<(), PaymentError> {
+        if from.contains(&self.status) {
+            self.status = to;
+            Ok(())
+        } else {
+            Err(PaymentError::ValidationError(format!(
+                "Cannot change account status from {:?} to {:?}",
+                self.status, to
+            )))
+        }
+    }
+
+    /// Disputes a withdrawal (credits the withdrawn funds back as held)
+    pub fn hold_withdrawal(
+        &mut self,
+        currency: Currency,
+        amount: Balance,
+    ) -> Result<(), PaymentError> {
+        let balance = self.balance_mut(currency);
+        let held = balance.held.checked_add(amount)?;
+        let total = balance.total.checked_add(amount)?;
+        balance.held = held;
+        balance.total = total;
+        Ok(())
+    }
+
+    /// Resolves a withdrawal dispute (the withdrawal stands, so the held credit is removed)
+    pub fn resolve_withdrawal(
+        &mut self,
+        currency: Currency,
+        amount: Balance,
+    ) -> Result<(), PaymentError> {
+        let balance = self.balance_mut(currency);
+        if balance.held >= amount {
+            let held = balance.held.checked_sub(amount)?;
+            let total = balance.total.checked_sub(amount)?;
+            balance.held = held;
+            balance.total = total;
+            Ok(())
+        } else {
+            Err(PaymentError::ValidationError(
+                "Held funds mismatch".to_string(),
+            ))
+        }
+    }
+
+    /// Chargeback of a withdrawal (releases the held credit to available and locks account)
+    pub fn chargeback_withdrawal(
+        &mut self,
+        currency: Currency,
+        amount: Balance,
+    ) -> Result<(), PaymentError> {
+        let balance = self.balance_mut(currency);
+        if balance.held >= amount {
+            let held = balance.held.checked_sub(amount)?;
+            let available = balance.available.checked_add(amount)?;
+            balance.held = held;
+            balance.available = available;
             self.status = AccountStatus::Locked;
             Ok(())
         } else {
@@ -216,8 +562,39 @@ mod tests {
     fn test_balance_arithmetic() {
         let b1 = Balance::new(dec!(10.0));
         let b2 = Balance::new(dec!(5.0));
-        assert_eq!(b1 + b2, Balance::new(dec!(15.0)));
-        assert_eq!(b1 - b2, Balance::new(dec!(5.0)));
+        assert_eq!(b1.checked_add(b2).unwrap(), Balance::new(dec!(15.0)));
+        assert_eq!(b1.checked_sub(b2).unwrap(), Balance::new(dec!(5.0)));
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let max = Balance::new(Decimal::MAX);
+        let one = Balance::new(dec!(1));
+        assert_eq!(one.checked_add(one).unwrap(), Balance::new(dec!(2)));
+        assert!(matches!(
+            max.checked_add(one),
+            Err(PaymentError::Overflow(_))
+        ));
+        assert!(matches!(
+            Balance::new(Decimal::MIN).checked_sub(one),
+            Err(PaymentError::Overflow(_))
+        ));
+        assert!(matches!(
+            Amount::new(Decimal::MAX)
+                .unwrap()
+                .checked_add(Amount::new(dec!(1)).unwrap()),
+            Err(PaymentError::Overflow(_))
+        ));
+
+        // A failed operation leaves the account unchanged
+        let mut account = ClientAccount::new(1);
+        account.deposit(Currency::default(), max).unwrap();
+        assert!(matches!(
+            account.deposit(Currency::default(), one),
+            Err(PaymentError::Overflow(_))
+        ));
+        assert_eq!(account.balance(Currency::default()).available, max);
+        assert_eq!(account.balance(Currency::default()).total, max);
     }
 
     #[test]
@@ -236,72 +613,336 @@ mod tests {
     #[test]
     fn test_account_deposit() {
         let mut account = ClientAccount::new(1);
-        account.deposit(Balance::new(dec!(10.0)));
-        assert_eq!(account.available, Balance::new(dec!(10.0)));
-        assert_eq!(account.total, Balance::new(dec!(10.0)));
+        account
+            .deposit(Currency::default(), Balance::new(dec!(10.0)))
+            .unwrap();
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(10.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(10.0))
+        );
     }
 
     #[test]
     fn test_account_withdraw_success() {
         let mut account = ClientAccount::new(1);
-        account.available = Balance::new(dec!(10.0));
-        account.total = Balance::new(dec!(10.0));
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(10.0));
+        account.balance_mut(Currency::default()).total = Balance::new(dec!(10.0));
 
-        let result = account.withdraw(Balance::new(dec!(5.0)));
+        let result = account.withdraw(Currency::default(), Balance::new(dec!(5.0)));
         assert!(result.is_ok());
-        assert_eq!(account.available, Balance::new(dec!(5.0)));
-        assert_eq!(account.total, Balance::new(dec!(5.0)));
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(5.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(5.0))
+        );
     }
 
     #[test]
     fn test_account_withdraw_insufficient() {
         let mut account = ClientAccount::new(1);
-        account.available = Balance::new(dec!(10.0));
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(10.0));
 
-        let result = account.withdraw(Balance::new(dec!(20.0)));
+        let result = account.withdraw(Currency::default(), Balance::new(dec!(20.0)));
         assert!(matches!(result, Err(PaymentError::ValidationError(_))));
-        assert_eq!(account.available, Balance::new(dec!(10.0)));
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(10.0))
+        );
+    }
+
+    #[test]
+    fn test_account_limits() {
+        let currency = Currency::default();
+        let mut account = ClientAccount::new(1);
+        account.deposit(currency, Balance::new(dec!(10))).unwrap();
+
+        // An overdraft lets the available balance go below zero, down to the ceiling
+        account.limits.overdraft = Balance::new(dec!(5));
+        account.withdraw(currency, Balance::new(dec!(14))).unwrap();
+        assert_eq!(account.balance(currency).available, Balance::new(dec!(-4)));
+        assert!(account.hold(currency, Balance::new(dec!(2))).is_err());
+        account.hold(currency, Balance::new(dec!(1))).unwrap();
+        assert_eq!(account.balance(currency).available, Balance::new(dec!(-5)));
+
+        // A reserve must be left in the account
+        let mut account = ClientAccount::new(2);
+        account.deposit(currency, Balance::new(dec!(10))).unwrap();
+        account.limits.reserve = Balance::new(dec!(3));
+        assert!(account.withdraw(currency, Balance::new(dec!(8))).is_err());
+        account.withdraw(currency, Balance::new(dec!(7))).unwrap();
+        assert_eq!(account.balance(currency).available, Balance::new(dec!(3)));
     }
 
     #[test]
     fn test_account_hold_success() {
         let mut account = ClientAccount::new(1);
-        account.available = Balance::new(dec!(10.0));
-        account.total = Balance::new(dec!(10.0));
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(10.0));
+        account.balance_mut(Currency::default()).total = Balance::new(dec!(10.0));
 
-        let result = account.hold(Balance::new(dec!(5.0)));
+        let result = account.hold(Currency::default(), Balance::new(dec!(5.0)));
         assert!(result.is_ok());
-        assert_eq!(account.available, Balance::new(dec!(5.0)));
-        assert_eq!(account.held, Balance::new(dec!(5.0)));
-        assert_eq!(account.total, Balance::new(dec!(10.0)));
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(5.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).held,
+            Balance::new(dec!(5.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(10.0))
+        );
     }
 
     #[test]
     fn test_account_resolve() {
         let mut account = ClientAccount::new(1);
-        account.available = Balance::new(dec!(5.0));
-        account.held = Balance::new(dec!(5.0));
-        account.total = Balance::new(dec!(10.0));
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(5.0));
+        account.balance_mut(Currency::default()).held = Balance::new(dec!(5.0));
+        account.balance_mut(Currency::default()).total = Balance::new(dec!(10.0));
 
-        let result = account.resolve(Balance::new(dec!(5.0)));
+        let result = account.resolve(Currency::default(), Balance::new(dec!(5.0)));
         assert!(result.is_ok());
-        assert_eq!(account.available, Balance::new(dec!(10.0)));
-        assert_eq!(account.held, Balance::new(dec!(0.0)));
-        assert_eq!(account.total, Balance::new(dec!(10.0)));
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(10.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).held,
+            Balance::new(dec!(0.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(10.0))
+        );
     }
 
     #[test]
     fn test_account_chargeback() {
         let mut account = ClientAccount::new(1);
-        account.available = Balance::new(dec!(5.0));
-        account.held = Balance::new(dec!(5.0));
-        account.total = Balance::new(dec!(10.0));
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(5.0));
+        account.balance_mut(Currency::default()).held = Balance::new(dec!(5.0));
+        account.balance_mut(Currency::default()).total = Balance::new(dec!(10.0));
+
+        let result = account.chargeback(Currency::default(), Balance::new(dec!(5.0)));
+        assert!(result.is_ok());
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(5.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).held,
+            Balance::new(dec!(0.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(5.0))
+        );
+        assert_eq!(account.status, AccountStatus::Locked);
+    }
+
+    #[test]
+    fn test_account_withdrawal_dispute_resolve() {
+        let mut account = ClientAccount::new(1);
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(5.0));
+        account.balance_mut(Currency::default()).total = Balance::new(dec!(5.0));
+
+        account
+            .hold_withdrawal(Currency::default(), Balance::new(dec!(5.0)))
+            .unwrap();
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(5.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).held,
+            Balance::new(dec!(5.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(10.0))
+        );
+
+        let result = account.resolve_withdrawal(Currency::default(), Balance::new(dec!(5.0)));
+        assert!(result.is_ok());
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(5.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).held,
+            Balance::new(dec!(0.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(5.0))
+        );
+    }
 
-        let result = account.chargeback(Balance::new(dec!(5.0)));
+    #[test]
+    fn test_account_withdrawal_chargeback() {
+        let mut account = ClientAccount::new(1);
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(5.0));
+        account.balance_mut(Currency::default()).held = Balance::new(dec!(5.0));
+        account.balance_mut(Currency::default()).total = Balance::new(dec!(10.0));
+
+        let result = account.chargeback_withdrawal(Currency::default(), Balance::new(dec!(5.0)));
         assert!(result.is_ok());
-        assert_eq!(account.available, Balance::new(dec!(5.0)));
-        assert_eq!(account.held, Balance::new(dec!(0.0)));
-        assert_eq!(account.total, Balance::new(dec!(5.0)));
+        assert_eq!(
+            account.balance(Currency::default()).available,
+            Balance::new(dec!(10.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).held,
+            Balance::new(dec!(0.0))
+        );
+        assert_eq!(
+            account.balance(Currency::default()).total,
+            Balance::new(dec!(10.0))
+        );
+        assert_eq!(account.status, AccountStatus::Locked);
+    }
+
+    #[test]
+    fn test_account_currencies_are_independent() {
+        let eur = Currency::new("EUR").unwrap();
+        let usd = Currency::new("USD").unwrap();
+        let mut account = ClientAccount::new(1);
+        account.deposit(eur, Balance::new(dec!(10.0))).unwrap();
+
+        let result = account.withdraw(usd, Balance::new(dec!(5.0)));
+        assert!(matches!(result, Err(PaymentError::ValidationError(_))));
+        assert_eq!(account.balance(eur).available, Balance::new(dec!(10.0)));
+        assert_eq!(account.balance(usd).available, Balance::ZERO);
+    }
+
+    #[test]
+    fn test_account_json_roundtrip() {
+        let mut account = ClientAccount::new(1);
+        account
+            .deposit(Currency::default(), Balance::new(dec!(1.5)))
+            .unwrap();
+        account
+            .deposit(Currency::new("EUR").unwrap(), Balance::new(dec!(2.0)))
+            .unwrap();
+
+        let json = serde_json::to_vec(&account).unwrap();
+        let parsed: ClientAccount = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed, account);
+    }
+
+    #[test]
+    fn test_account_status_transitions() {
+        let mut account = ClientAccount::new(1);
+        assert!(account.unlock().is_err());
+
+        assert!(account.freeze().is_ok());
+        assert_eq!(account.status, AccountStatus::Frozen);
+        assert!(account.freeze().is_err());
+        assert!(account.unlock().is_ok());
+        assert_eq!(account.status, AccountStatus::Active);
+
+        account.status = AccountStatus::Locked;
+        assert!(account.freeze().is_err());
+        assert!(account.unlock().is_ok());
+
+        assert!(account.close().is_ok());
+        assert_eq!(account.status, AccountStatus::Closed);
+        assert!(account.unlock().is_err());
+        assert!(account.close().is_err());
+    }
+
+    #[test]
+    fn test_account_status_accepts() {
+        use TransactionType::*;
+        assert!(AccountStatus::Active.accepts(Withdrawal));
+        assert!(AccountStatus::Frozen.accepts(Deposit));
+        assert!(AccountStatus::Frozen.accepts(Dispute));
+        assert!(!AccountStatus::Frozen.accepts(Withdrawal));
+        assert!(!AccountStatus::Frozen.accepts(Transfer));
+        assert!(!AccountStatus::Locked.accepts(Deposit));
+        assert!(AccountStatus::Locked.accepts(Unlock));
+        assert!(!AccountStatus::Closed.accepts(Unlock));
+        assert!(AccountStatus::Frozen.accepts_incoming());
+        assert!(!AccountStatus::Locked.accepts_incoming());
+    }
+
+    #[test]
+    fn test_account_legacy_locked_flag() {
+        let json = r#"{"client":1,"balances":{},"locked":true}"#;
+        let account: ClientAccount = serde_json::from_str(json).unwrap();
+        assert_eq!(account.status, AccountStatus::Locked);
+
+        let json = serde_json::to_string(&ClientAccount::new(2)).unwrap();
+        assert!(json.contains(r#""status":"active""#));
+    }
+
+    #[test]
+    fn test_overdrawn_hold_and_receivables() {
+        let currency = Currency::default();
+        let mut account = ClientAccount::new(1);
+        account.deposit(currency, Balance::new(dec!(10))).unwrap();
+        account.limits.reserve = Balance::new(dec!(4));
+        assert_eq!(account.headroom(currency), Balance::new(dec!(6)));
+
+        account
+            .hold_overdrawn(currency, Balance::new(dec!(15)))
+            .unwrap();
+        assert_eq!(account.balance(currency).available, Balance::new(dec!(-5)));
+        assert_eq!(account.balance(currency).total, Balance::new(dec!(10)));
+        assert_eq!(account.headroom(currency), Balance::ZERO);
+
+        account
+            .add_receivable(currency, Balance::new(dec!(3)))
+            .unwrap();
+        assert_eq!(account.receivable(currency), Balance::new(dec!(3)));
+        assert!(
+            account
+                .clear_receivable(currency, Balance::new(dec!(4)))
+                .is_err()
+        );
+        account
+            .clear_receivable(currency, Balance::new(dec!(3)))
+            .unwrap();
+        assert!(account.receivables.is_empty());
+    }
+
+    #[test]
+    fn test_dispute_stats_and_lock_reason() {
+        let currency = Currency::default();
+        let mut stats = DisputeStats::default();
+        stats
+            .record_dispute(currency, Balance::new(dec!(10)), true)
+            .unwrap();
+        stats
+            .record_dispute(currency, Balance::new(dec!(5)), false)
+            .unwrap();
+        assert_eq!(stats.open, 1);
+        assert_eq!(stats.disputed_value(currency), Balance::new(dec!(15)));
+        stats.record_closed();
+        stats.record_closed();
+        assert_eq!(stats.open, 0);
+
+        let mut account = ClientAccount::new(1);
+        let reason = LockReason {
+            tx: 7,
+            r#type: TransactionType::Dispute,
+            cause: LockCause::OpenDisputes,
+            position: 12,
+            timestamp: None,
+        };
+        account.lock(reason);
         assert_eq!(account.status, AccountStatus::Locked);
+        assert_eq!(account.lock_reason, Some(reason));
+        account.unlock().unwrap();
+        assert!(account.lock_reason.is_none());
     }
 }
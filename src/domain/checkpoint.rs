@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Number of leading bytes of an input hashed into its fingerprint.
+pub const FINGERPRINT_HEAD_BYTES: usize = 64 * 1024;
+
+/// Identifies an input, so a checkpoint is only used to resume the input it was taken on.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct Fingerprint {
+    /// The size of the input, in bytes.
+    pub size: u64,
+    /// The FNV-1a hash of the first bytes of the input (up to `FINGERPRINT_HEAD_BYTES`).
+    pub head: u64,
+}
+
+impl Fingerprint {
+    /// Fingerprints an input of `size` bytes starting with `head`.
+    pub fn new(size: u64, head: &[u8]) -> Self {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        let head = head[..head.len().min(FINGERPRINT_HEAD_BYTES)]
+            .iter()
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+            });
+        Self { size, head }
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes, hash {:016x}", self.size, self.head)
+    }
+}
+
+/// A position in the input, right before a record.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub struct InputPosition {
+    /// The byte offset.
+    pub byte: u64,
+    /// The 1-based line number.
+    pub line: u64,
+    /// The index of the record, the header being record 0.
+    pub record: u64,
+}
+
+/// How far the processing of an input went.
+///
+/// Committed together with the writes of each transaction, so the input can be resumed right
+/// after the last transaction applied, and none is applied twice.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct Checkpoint {
+    /// The input the checkpoint was taken on.
+    pub fingerprint: Fingerprint,
+    /// The position right after the last record processed.
+    pub input: InputPosition,
+    /// The number of input transactions processed up to that record.
+    pub position: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = Fingerprint::new(3, b"abc");
+        assert_eq!(fingerprint, Fingerprint::new(3, b"abc"));
+        assert_ne!(fingerprint, Fingerprint::new(3, b"abd"));
+        assert_ne!(fingerprint, Fingerprint::new(4, b"abc"));
+        // Reference value of FNV-1a 64
+        assert_eq!(Fingerprint::new(1, b"a").head, 0xaf63_dc4c_8601_ec8c);
+
+        // Only the head of the input counts
+        let mut long = vec![0; FINGERPRINT_HEAD_BYTES];
+        let head = Fingerprint::new(10, &long);
+        long.push(1);
+        assert_eq!(Fingerprint::new(10, &long), head);
+    }
+}
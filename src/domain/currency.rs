@@ -0,0 +1,114 @@
+use crate::error::PaymentError;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+/// An ISO 4217 style currency code (e.g. `EUR`).
+///
+/// Stored inline as three ASCII bytes to keep transaction records small. The default value
+/// represents an unspecified currency, used when the input has no currency column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    /// Parses a currency code, normalizing it to uppercase.
+    ///
+    /// An empty code yields the default (unspecified) currency.
+    pub fn new(code: &str) -> Result<Self, PaymentError> {
+        let code = code.trim();
+        if code.is_empty() {
+            return Ok(Self::default());
+        }
+        match code.as_bytes() {
+            [a, b, c] if code.bytes().all(|b| b.is_ascii_alphabetic()) => Ok(Self([
+                a.to_ascii_uppercase(),
+                b.to_ascii_uppercase(),
+                c.to_ascii_uppercase(),
+            ])),
+            _ => Err(PaymentError::ValidationError(format!(
+                "Invalid currency code: {}",
+                code
+            ))),
+        }
+    }
+
+    /// Returns `true` if this is the default (unspecified) currency.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns the raw bytes of the code (all zero for the default currency).
+    pub fn to_bytes(self) -> [u8; 3] {
+        self.0
+    }
+
+    /// Rebuilds a currency from the bytes returned by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: [u8; 3]) -> Result<Self, PaymentError> {
+        if bytes == [0; 3] || bytes.iter().all(u8::is_ascii_uppercase) {
+            Ok(Self(bytes))
+        } else {
+            Err(PaymentError::ValidationError(format!(
+                "Invalid currency bytes: {:?}",
+                bytes
+            )))
+        }
+    }
+
+    /// Returns the currency code, or an empty string for the default currency.
+    pub fn as_str(&self) -> &str {
+        if self.is_default() {
+            ""
+        } else {
+            // Only ASCII letters are ever stored
+            std::str::from_utf8(&self.0).unwrap_or_default()
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let code = String::deserialize(deserializer)?;
+        Self::new(&code).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_currency_parsing() {
+        assert_eq!(Currency::new("eur").unwrap().as_str(), "EUR");
+        assert!(Currency::new("").unwrap().is_default());
+        assert!(Currency::new(" ").unwrap().is_default());
+        assert!(Currency::new("EU").is_err());
+        assert!(Currency::new("EURO").is_err());
+        assert!(Currency::new("E1R").is_err());
+    }
+
+    #[test]
+    fn test_currency_serde_roundtrip() {
+        let currency = Currency::new("GBP").unwrap();
+        let json = serde_json::to_string(&currency).unwrap();
+        assert_eq!(json, "\"GBP\"");
+        let parsed: Currency = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, currency);
+    }
+}
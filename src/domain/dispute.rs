@@ -0,0 +1,168 @@
+use crate::domain::account::Balance;
+use crate::error::PaymentError;
+use serde::{Deserialize, Serialize};
+
+/// Represents the state of a single dispute.
+///
+/// - `Open`: The dispute is pending and its funds are held (or owed, for its shortfall).
+/// - `Resolved`: The dispute was resolved, releasing its held funds.
+/// - `Chargebacked`: The dispute was finalized as a chargeback.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum DisputeStatus {
+    Open,
+    Resolved,
+    Chargebacked,
+}
+
+/// A dispute raised on part or all of a transaction, by a single dispute row.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct Dispute {
+    /// The disputed amount.
+    pub amount: Balance,
+    /// The portion of the amount the client could not cover when the dispute was raised,
+    /// which is owed by the client rather than held (see `DisputeFundsPolicy::HoldAvailable`).
+    pub shortfall: Balance,
+    /// The current status of the dispute.
+    pub status: DisputeStatus,
+}
+
+impl Dispute {
+    /// Creates an open dispute of `amount`, of which `shortfall` is owed rather than held.
+    pub fn open(amount: Balance, shortfall: Balance) -> Self {
+        Self {
+            amount,
+            shortfall,
+            status: DisputeStatus::Open,
+        }
+    }
+
+    /// Returns `true` if the dispute has not been resolved or charged back yet.
+    pub fn is_open(&self) -> bool {
+        self.status == DisputeStatus::Open
+    }
+
+    /// Returns the portion of the amount held by the dispute.
+    pub fn held(&self) -> Result<Balance, PaymentError> {
+        self.amount.checked_sub(self.shortfall)
+    }
+}
+
+/// The disputes raised on a transaction, in the order of their dispute rows.
+///
+/// Kept apart from the transaction record, keyed by the transaction ID, since only a small
+/// fraction of transactions are ever disputed.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DisputeRecord {
+    /// The ID of the disputed transaction.
+    pub tx: u32,
+    /// Every dispute raised on the transaction, open or settled.
+    pub disputes: Vec<Dispute>,
+}
+
+impl DisputeRecord {
+    /// Creates the record of a transaction that was never disputed.
+    pub fn new(tx: u32) -> Self {
+        Self {
+            tx,
+            disputes: Vec::new(),
+        }
+    }
+
+    /// Returns the portion of the transaction ever disputed (open, resolved or charged back),
+    /// which can never be disputed again.
+    pub fn total_disputed(&self) -> Result<Balance, PaymentError> {
+        self.disputes
+            .iter()
+            .try_fold(Balance::ZERO, |total, dispute| {
+                total.checked_add(dispute.amount)
+            })
+    }
+
+    /// Returns `true` if any dispute of the transaction is still open.
+    pub fn has_open(&self) -> bool {
+        self.disputes.iter().any(Dispute::is_open)
+    }
+
+    /// Returns the indexes of the open disputes settled by a resolve or chargeback.
+    ///
+    /// With an amount, the operation targets the oldest open dispute of exactly that amount;
+    /// without one, it targets every open dispute. Empty if no open dispute matches.
+    pub fn targets(&self, amount: Option<Balance>) -> Vec<usize> {
+        let mut open = self
+            .disputes
+            .iter()
+            .enumerate()
+            .filter(|(_, dispute)| dispute.is_open());
+        match amount {
+            Some(amount) => open
+                .find(|(_, dispute)| dispute.amount == amount)
+                .map(|(index, _)| index)
+                .into_iter()
+                .collect(),
+            None => open.map(|(index, _)| index).collect(),
+        }
+    }
+
+    /// Returns the total amount, held funds and shortfall of the given disputes.
+    pub fn sum(&self, targets: &[usize]) -> Result<(Balance, Balance, Balance), PaymentError> {
+        targets.iter().map(|&index| self.disputes[index]).try_fold(
+            (Balance::ZERO, Balance::ZERO, Balance::ZERO),
+            |(amount, held, shortfall), dispute| {
+                Ok((
+                    amount.checked_add(dispute.amount)?,
+                    held.checked_add(dispute.held()?)?,
+                    shortfall.checked_add(dispute.shortfall)?,
+                ))
+            },
+        )
+    }
+
+    /// Closes the given disputes with `status`.
+    pub fn settle(&mut self, targets: &[usize], status: DisputeStatus) {
+        for &index in targets {
+            self.disputes[index].status = status;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_targets_open_disputes() {
+        let mut record = DisputeRecord::new(1);
+        record
+            .disputes
+            .push(Dispute::open(Balance::new(dec!(30)), Balance::ZERO));
+        record.disputes.push(Dispute::open(
+            Balance::new(dec!(50)),
+            Balance::new(dec!(20)),
+        ));
+        record
+            .disputes
+            .push(Dispute::open(Balance::new(dec!(30)), Balance::ZERO));
+        assert_eq!(record.total_disputed().unwrap(), Balance::new(dec!(110)));
+
+        // The oldest open dispute of the amount is targeted, and nothing matches other amounts
+        assert_eq!(record.targets(Some(Balance::new(dec!(30)))), vec![0]);
+        assert!(record.targets(Some(Balance::new(dec!(80)))).is_empty());
+        record.settle(&[0], DisputeStatus::Resolved);
+        assert_eq!(record.targets(Some(Balance::new(dec!(30)))), vec![2]);
+
+        let targets = record.targets(None);
+        assert_eq!(targets, vec![1, 2]);
+        assert_eq!(
+            record.sum(&targets).unwrap(),
+            (
+                Balance::new(dec!(80)),
+                Balance::new(dec!(60)),
+                Balance::new(dec!(20))
+            )
+        );
+        record.settle(&targets, DisputeStatus::Chargebacked);
+        assert!(!record.has_open());
+        assert_eq!(record.total_disputed().unwrap(), Balance::new(dec!(110)));
+    }
+}
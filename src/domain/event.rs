@@ -0,0 +1,264 @@
+use crate::domain::account::{
+    AccountLimits, AccountStatus, Balance, ClientAccount, DisputeStats, LockReason,
+};
+use crate::domain::currency::Currency;
+use crate::error::{PaymentError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A fact about a client account, recorded when a transaction is applied.
+///
+/// Folding the events of a client in order yields its `ClientAccount` state.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DomainEvent {
+    /// The account was created.
+    AccountOpened,
+    /// Funds were credited to the available balance (deposits, incoming transfers).
+    Deposited { currency: Currency, amount: Balance },
+    /// Funds were debited from the available balance (withdrawals, outgoing transfers).
+    Withdrawn { currency: Currency, amount: Balance },
+    /// Funds were held by a dispute. For a disputed withdrawal, the withdrawn funds are
+    /// credited back as held.
+    FundsHeld {
+        currency: Currency,
+        amount: Balance,
+        withdrawal: bool,
+    },
+    /// Funds were held by a dispute beyond the available balance, which went negative.
+    FundsHeldOverdrawn { currency: Currency, amount: Balance },
+    /// Disputed funds the client could not cover were recorded as owed by the client.
+    ReceivableRecorded { currency: Currency, amount: Balance },
+    /// Owed funds were cancelled by a resolve.
+    ReceivableCleared { currency: Currency, amount: Balance },
+    /// Held funds were released by a resolve.
+    HoldReleased {
+        currency: Currency,
+        amount: Balance,
+        withdrawal: bool,
+    },
+    /// Held funds were reversed by a chargeback.
+    ChargedBack {
+        currency: Currency,
+        amount: Balance,
+        withdrawal: bool,
+    },
+    /// The account was locked, by a chargeback or once it crossed a dispute threshold.
+    AccountLocked {
+        #[serde(default)]
+        reason: Option<LockReason>,
+    },
+    /// The account was frozen by an administrator.
+    AccountFrozen,
+    /// The account was unlocked or unfrozen by an administrator.
+    AccountUnlocked,
+    /// The account was closed by an administrator.
+    AccountClosed,
+    /// A fee was debited from the available balance.
+    FeeCharged { currency: Currency, amount: Balance },
+    /// The credit limits of the account were set, by an administrator or from the
+    /// configuration when the account was opened.
+    LimitsChanged { limits: AccountLimits },
+    /// The dispute counters of the account changed.
+    DisputeStatsChanged { stats: DisputeStats },
+}
+
+impl DomainEvent {
+    /// Applies the event to an account.
+    ///
+    /// Events are only recorded for valid operations, so a failure means the log does not
+    /// match the state it is applied to.
+    pub fn apply(&self, account: &mut ClientAccount) -> Result<()> {
+        match *self {
+            Self::AccountOpened => Ok(()),
+            Self::Deposited { currency, amount } => account.deposit(currency, amount),
+            Self::Withdrawn { currency, amount } => account.withdraw(currency, amount),
+            Self::FundsHeld {
+                currency,
+                amount,
+                withdrawal,
+            } => {
+                if withdrawal {
+                    account.hold_withdrawal(currency, amount)
+                } else {
+                    account.hold(currency, amount)
+                }
+            }
+            Self::FundsHeldOverdrawn { currency, amount } => {
+                account.hold_overdrawn(currency, amount)
+            }
+            Self::ReceivableRecorded { currency, amount } => {
+                account.add_receivable(currency, amount)
+            }
+            Self::ReceivableCleared { currency, amount } => {
+                account.clear_receivable(currency, amount)
+            }
+            Self::HoldReleased {
+                currency,
+                amount,
+                withdrawal,
+            } => {
+                if withdrawal {
+                    account.resolve_withdrawal(currency, amount)
+                } else {
+                    account.resolve(currency, amount)
+                }
+            }
+            Self::ChargedBack {
+                currency,
+                amount,
+                withdrawal,
+            } => {
+                if withdrawal {
+                    account.chargeback_withdrawal(currency, amount)
+                } else {
+                    account.chargeback(currency, amount)
+                }
+            }
+            Self::AccountLocked { reason } => {
+                account.status = AccountStatus::Locked;
+                account.lock_reason = reason;
+                Ok(())
+            }
+            Self::AccountFrozen => account.freeze(),
+            Self::AccountUnlocked => account.unlock(),
+            Self::AccountClosed => account.close(),
+            Self::FeeCharged { currency, amount } => account.withdraw(currency, amount),
+            Self::LimitsChanged { limits } => {
+                account.limits = limits;
+                Ok(())
+            }
+            Self::DisputeStatsChanged { ref stats } => {
+                account.disputes = stats.clone();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A domain event together with its position in the log and its origin.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct EventRecord {
+    /// The position of the event in the log, assigned by the `EventStore`.
+    pub seq: u64,
+    /// The client whose account the event applies to.
+    pub client: u16,
+    /// The transaction that caused the event.
+    pub tx: u32,
+    /// The event itself.
+    pub event: DomainEvent,
+}
+
+impl EventRecord {
+    /// Creates a record that has not been appended to a log yet.
+    pub fn new(client: u16, tx: u32, event: DomainEvent) -> Self {
+        Self {
+            seq: 0,
+            client,
+            tx,
+            event,
+        }
+    }
+}
+
+/// Rebuilds the state of every account found in the log by folding its events in order.
+pub fn replay(records: &[EventRecord]) -> Result<Vec<ClientAccount>> {
+    let mut accounts: BTreeMap<u16, ClientAccount> = BTreeMap::new();
+    for record in records {
+        let account = accounts
+            .entry(record.client)
+            .or_insert_with(|| ClientAccount::new(record.client));
+        record.event.apply(account).map_err(|e| {
+            PaymentError::ValidationError(format!(
+                "Cannot replay event {} of client {}: {}",
+                record.seq, record.client, e
+            ))
+        })?;
+    }
+    Ok(accounts.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_replay_folds_events() {
+        let currency = Currency::default();
+        let records = vec![
+            EventRecord::new(1, 1, DomainEvent::AccountOpened),
+            EventRecord::new(
+                1,
+                1,
+                DomainEvent::Deposited {
+                    currency,
+                    amount: Balance::new(dec!(10)),
+                },
+            ),
+            EventRecord::new(
+                2,
+                2,
+                DomainEvent::Deposited {
+                    currency,
+                    amount: Balance::new(dec!(5)),
+                },
+            ),
+            EventRecord::new(
+                1,
+                1,
+                DomainEvent::FundsHeld {
+                    currency,
+                    amount: Balance::new(dec!(4)),
+                    withdrawal: false,
+                },
+            ),
+            EventRecord::new(
+                1,
+                1,
+                DomainEvent::ChargedBack {
+                    currency,
+                    amount: Balance::new(dec!(4)),
+                    withdrawal: false,
+                },
+            ),
+            EventRecord::new(1, 1, DomainEvent::AccountLocked { reason: None }),
+        ];
+
+        let accounts = replay(&records).unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].balance(currency).total, Balance::new(dec!(6)));
+        assert_eq!(accounts[0].balance(currency).held, Balance::ZERO);
+        assert_eq!(accounts[0].status, AccountStatus::Locked);
+        assert_eq!(
+            accounts[1].balance(currency).available,
+            Balance::new(dec!(5))
+        );
+    }
+
+    #[test]
+    fn test_replay_rejects_inconsistent_log() {
+        let records = vec![EventRecord::new(
+            1,
+            1,
+            DomainEvent::Withdrawn {
+                currency: Currency::default(),
+                amount: Balance::new(dec!(1)),
+            },
+        )];
+
+        assert!(replay(&records).is_err());
+    }
+
+    #[test]
+    fn test_event_serde_roundtrip() {
+        let record = EventRecord::new(1, 7, DomainEvent::AccountFrozen);
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            json,
+            r#"{"seq":0,"client":1,"tx":7,"event":{"type":"account_frozen"}}"#
+        );
+        let parsed: EventRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, record);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::domain::account::Balance;
+use crate::domain::currency::Currency;
+use crate::domain::transaction::TransactionType;
+use crate::error::PaymentError;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Number of decimal places fees are rounded to (half to even).
+const FEE_SCALE: u32 = 4;
+
+/// One bracket of a tiered fee, applying to amounts up to `up_to` (inclusive).
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct FeeTier {
+    /// The upper bound of the bracket; the last bracket usually has none.
+    pub up_to: Option<Decimal>,
+    /// A fixed fee.
+    #[serde(default)]
+    pub flat: Decimal,
+    /// A fee proportional to the amount, in percent.
+    #[serde(default)]
+    pub percent: Decimal,
+}
+
+/// How the fee of a transaction is computed from its amount.
+///
+/// - `Flat`: The same fee for every transaction.
+/// - `Percentage`: A percentage of the amount.
+/// - `Tiered`: The flat and percentage fees of the first bracket containing the amount, or
+///   no fee if the amount is above every bracket.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeeRule {
+    Flat { amount: Decimal },
+    Percentage { percent: Decimal },
+    Tiered { tiers: Vec<FeeTier> },
+}
+
+impl FeeRule {
+    /// Returns the fee of a transaction of the given amount, or `PaymentError::Overflow` if
+    /// it is beyond the range of a balance.
+    pub fn fee(&self, amount: Balance) -> Result<Balance, PaymentError> {
+        let percentage = |percent: Decimal| {
+            amount
+                .0
+                .checked_mul(percent)
+                .map(|product| product / Decimal::ONE_HUNDRED)
+        };
+        let fee = match self {
+            Self::Flat { amount } => Some(*amount),
+            Self::Percentage { percent } => percentage(*percent),
+            Self::Tiered { tiers } => tiers
+                .iter()
+                .find(|tier| tier.up_to.is_none_or(|up_to| amount.0 <= up_to))
+                .map_or(Some(Decimal::ZERO), |tier| {
+                    percentage(tier.percent).and_then(|fee| tier.flat.checked_add(fee))
+                }),
+        };
+        fee.map(|fee| Balance::new(fee.round_dp(FEE_SCALE).normalize()))
+            .ok_or_else(|| PaymentError::Overflow(format!("fee on {}", amount.0)))
+    }
+
+    fn validate(&self) -> Result<(), PaymentError> {
+        let negative = match self {
+            Self::Flat { amount } => amount.is_sign_negative(),
+            Self::Percentage { percent } => percent.is_sign_negative(),
+            Self::Tiered { tiers } => tiers
+                .iter()
+                .any(|tier| tier.flat.is_sign_negative() || tier.percent.is_sign_negative()),
+        };
+        if negative {
+            Err(PaymentError::ValidationError(
+                "Fees cannot be negative".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The fees charged to clients, per transaction type.
+///
+/// Withdrawals and transfers are charged on the amount moved, on top of it. Chargebacks are
+/// charged on the amount reversed, as a penalty.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FeeSchedule {
+    pub withdrawal: Option<FeeRule>,
+    pub transfer: Option<FeeRule>,
+    pub chargeback: Option<FeeRule>,
+}
+
+impl FeeSchedule {
+    /// Returns the fee of a transaction of the given type and amount (zero if it has none).
+    pub fn fee(&self, r#type: TransactionType, amount: Balance) -> Result<Balance, PaymentError> {
+        let rule = match r#type {
+            TransactionType::Withdrawal => &self.withdrawal,
+            TransactionType::Transfer => &self.transfer,
+            TransactionType::Chargeback => &self.chargeback,
+            _ => &None,
+        };
+        rule.as_ref()
+            .map_or(Ok(Balance::ZERO), |rule| rule.fee(amount))
+    }
+
+    /// Fails if any rule of the schedule has a negative fee.
+    pub fn validate(&self) -> Result<(), PaymentError> {
+        [&self.withdrawal, &self.transfer, &self.chargeback]
+            .into_iter()
+            .flatten()
+            .try_for_each(FeeRule::validate)
+    }
+}
+
+/// The system account collecting the fees, per currency.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct FeeAccount {
+    pub balances: BTreeMap<Currency, Balance>,
+}
+
+impl FeeAccount {
+    /// Adds a collected fee to the account.
+    pub fn credit(&mut self, currency: Currency, fee: Balance) -> Result<(), PaymentError> {
+        let balance = self.balances.entry(currency).or_default();
+        *balance = balance.checked_add(fee)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_fee_rules() {
+        let amount = Balance::new(dec!(250));
+        assert_eq!(
+            FeeRule::Flat { amount: dec!(1.5) }.fee(amount).unwrap(),
+            Balance::new(dec!(1.5))
+        );
+        assert_eq!(
+            FeeRule::Percentage { percent: dec!(0.3) }
+                .fee(amount)
+                .unwrap(),
+            Balance::new(dec!(0.75))
+        );
+
+        let tiered = FeeRule::Tiered {
+            tiers: vec![
+                FeeTier {
+                    up_to: Some(dec!(100)),
+                    flat: dec!(1),
+                    percent: Decimal::ZERO,
+                },
+                FeeTier {
+                    up_to: None,
+                    flat: dec!(0.5),
+                    percent: dec!(1),
+                },
+            ],
+        };
+        assert_eq!(
+            tiered.fee(Balance::new(dec!(100))).unwrap(),
+            Balance::new(dec!(1))
+        );
+        assert_eq!(tiered.fee(amount).unwrap(), Balance::new(dec!(3)));
+
+        // A fee beyond the range of a balance is an error rather than a panic
+        let huge = Balance::new(Decimal::MAX);
+        assert!(matches!(
+            FeeRule::Percentage { percent: dec!(200) }.fee(huge),
+            Err(PaymentError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn test_fee_schedule() {
+        let schedule = FeeSchedule {
+            withdrawal: Some(FeeRule::Flat { amount: dec!(1) }),
+            ..FeeSchedule::default()
+        };
+        let amount = Balance::new(dec!(10));
+        assert_eq!(
+            schedule.fee(TransactionType::Withdrawal, amount).unwrap(),
+            Balance::new(dec!(1))
+        );
+        assert_eq!(
+            schedule.fee(TransactionType::Deposit, amount).unwrap(),
+            Balance::ZERO
+        );
+        assert!(schedule.validate().is_ok());
+
+        let negative = FeeSchedule {
+            chargeback: Some(FeeRule::Flat { amount: dec!(-1) }),
+            ..FeeSchedule::default()
+        };
+        assert!(negative.validate().is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Compressed set of `u32` transaction ids.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// Number of ids of a container of [`IdBitmap`].
+const CONTAINER_IDS: usize = 1 << 16;
+/// Number of ids above which a container is stored as a bitmap rather than a sorted array.
+const ARRAY_MAX_IDS: usize = 4096;
+
+/// A compressed set of `u32` ids.
+///
+/// Ids are grouped by their 16 high bits. The low bits of a sparse group are kept in a sorted
+/// array (2 bytes per id); a group of more than 4096 ids switches to a bitmap of 8 KiB. The
+/// whole `u32` space thus fits in 512 MiB, and dense ids cost about one bit each. It is
+/// serialized the same way, as the arrays or bitmaps of its groups.
+#[derive(Debug, Default, Clone)]
+pub struct IdBitmap {
+    containers: BTreeMap<u16, Container>,
+    len: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Container {
+    Array(Vec<u16>),
+    Bitmap(#[serde(with = "words")] Box<[u64; CONTAINER_IDS / 64]>),
+}
+
+impl IdBitmap {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an id, returning whether it was not already present.
+    pub fn insert(&mut self, id: u32) -> bool {
+        let (high, low) = split(id);
+        let container = self
+            .containers
+            .entry(high)
+            .or_insert_with(|| Container::Array(Vec::new()));
+        let inserted = match container {
+            Container::Array(lows) => match lows.binary_search(&low) {
+                Ok(_) => false,
+                Err(index) => {
+                    lows.insert(index, low);
+                    if lows.len() > ARRAY_MAX_IDS {
+                        *container = Container::Bitmap(to_bitmap(lows));
+                    }
+                    true
+                }
+            },
+            Container::Bitmap(words) => {
+                let (word, bit) = (usize::from(low) / 64, 1u64 << (low % 64));
+                let inserted = words[word] & bit == 0;
+                words[word] |= bit;
+                inserted
+            }
+        };
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Returns whether an id is present.
+    pub fn contains(&self, id: u32) -> bool {
+        let (high, low) = split(id);
+        match self.containers.get(&high) {
+            Some(Container::Array(lows)) => lows.binary_search(&low).is_ok(),
+            Some(Container::Bitmap(words)) => {
+                words[usize::from(low) / 64] & (1u64 << (low % 64)) != 0
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the number of ids.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the ids in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.containers.iter().flat_map(|(high, container)| {
+            let (lows, words) = match container {
+                Container::Array(lows) => (Some(lows), None),
+                Container::Bitmap(words) => (None, Some(words)),
+            };
+            let set_bits = words.into_iter().flat_map(|words| {
+                (0..=u16::MAX)
+                    .filter(move |low| words[usize::from(*low) / 64] & (1 << (low % 64)) != 0)
+            });
+            lows.into_iter()
+                .flatten()
+                .copied()
+                .chain(set_bits)
+                .map(move |low| u32::from(*high) << 16 | u32::from(low))
+        })
+    }
+}
+
+impl Extend<u32> for IdBitmap {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, ids: I) {
+        for id in ids {
+            self.insert(id);
+        }
+    }
+}
+
+impl FromIterator<u32> for IdBitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(ids: I) -> Self {
+        let mut set = Self::new();
+        set.extend(ids);
+        set
+    }
+}
+
+impl PartialEq for IdBitmap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Serialize for IdBitmap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.containers.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for IdBitmap {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let containers = BTreeMap::<u16, Container>::deserialize(deserializer)?;
+        let mut len = 0;
+        for container in containers.values() {
+            len += match container {
+                // The ids of an array are looked up by binary search
+                Container::Array(lows) => {
+                    if lows.windows(2).any(|pair| pair[0] >= pair[1]) {
+                        return Err(serde::de::Error::custom("ids not in increasing order"));
+                    }
+                    lows.len() as u64
+                }
+                Container::Bitmap(words) => {
+                    words.iter().map(|word| u64::from(word.count_ones())).sum()
+                }
+            };
+        }
+        Ok(Self { containers, len })
+    }
+}
+
+fn split(id: u32) -> (u16, u16) {
+    ((id >> 16) as u16, id as u16)
+}
+
+fn to_bitmap(lows: &[u16]) -> Box<[u64; CONTAINER_IDS / 64]> {
+    let mut words = Box::new([0u64; CONTAINER_IDS / 64]);
+    for low in lows {
+        words[usize::from(*low) / 64] |= 1u64 << (low % 64);
+    }
+    words
+}
+
+/// (De)serializes the words of a bitmap container as a sequence.
+mod words {
+    use super::CONTAINER_IDS;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(words: &[u64; CONTAINER_IDS / 64], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(words.iter())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Box<[u64; CONTAINER_IDS / 64]>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let words = Vec::<u64>::deserialize(deserializer)?;
+        let len = words.len();
+        words
+            .into_boxed_slice()
+            .try_into()
+            .map_err(|_| serde::de::Error::invalid_length(len, &"1024 words"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_bitmap() {
+        let mut ids = IdBitmap::new();
+        assert!(ids.insert(7));
+        assert!(!ids.insert(7));
+        assert!(ids.insert(u32::MAX));
+        assert!(ids.contains(7) && ids.contains(u32::MAX));
+        assert!(!ids.contains(8) && !ids.contains(7 + (1 << 16)));
+
+        // A dense group switches to a bitmap and keeps its ids
+        for id in (1 << 16)..(1 << 16) + 5000 {
+            assert!(ids.insert(id));
+        }
+        assert!(matches!(ids.containers[&1], Container::Bitmap(_)));
+        assert!(ids.contains((1 << 16) + 4999));
+        assert!(!ids.contains((1 << 16) + 5000));
+        assert!(!ids.insert(1 << 16));
+        assert_eq!(ids.len(), 5002);
+    }
+
+    #[test]
+    fn test_id_bitmap_serde_roundtrip() {
+        let mut ids: IdBitmap = [3, 1, u32::MAX].into_iter().collect();
+        ids.extend((1 << 16)..(1 << 16) + 5000);
+        assert_eq!(
+            ids.iter().take(4).collect::<Vec<_>>(),
+            vec![1, 3, 1 << 16, (1 << 16) + 1]
+        );
+        assert_eq!(ids.iter().count(), 5003);
+
+        let json = serde_json::to_string(&ids).unwrap();
+        let decoded: IdBitmap = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, ids);
+        assert_eq!(decoded.len(), 5003);
+        assert!(decoded.contains((1 << 16) + 4999) && !decoded.contains(2));
+
+        // Arrays out of order would break the lookups
+        assert!(serde_json::from_str::<IdBitmap>(r#"{"0":{"array":[3,1]}}"#).is_err());
+        assert!(serde_json::from_str::<IdBitmap>(r#"{"0":{"bitmap":[1]}}"#).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::domain::account::{AccountLimits, Balance, ClientAccount, CurrencyBalance};
+use crate::domain::currency::Currency;
+use std::fmt;
+
+/// A rule every `ClientAccount` must satisfy, for each of its currencies.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Invariant {
+    /// `available + held == total`
+    TotalMatchesParts,
+    /// `held >= 0`
+    NonNegativeHeld,
+    /// `total >= 0`, or `total >= -overdraft` for an account with an overdraft
+    NonNegativeTotal,
+}
+
+impl Invariant {
+    /// Returns a machine-readable code for the invariant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::TotalMatchesParts => "total_mismatch",
+            Self::NonNegativeHeld => "negative_held",
+            Self::NonNegativeTotal => "negative_total",
+        }
+    }
+
+    fn holds(&self, balance: &CurrencyBalance, limits: &AccountLimits) -> bool {
+        match self {
+            Self::TotalMatchesParts => balance
+                .available
+                .checked_add(balance.held)
+                .is_ok_and(|sum| sum == balance.total),
+            Self::NonNegativeHeld => balance.held >= Balance::ZERO,
+            Self::NonNegativeTotal => balance.total.0 >= -limits.overdraft.0,
+        }
+    }
+}
+
+/// An invariant broken by the balances of an account in one currency.
+#[derive(Debug, PartialEq, Clone)]
+pub struct InvariantViolation {
+    /// The client owning the account.
+    pub client: u16,
+    /// The currency of the offending balances.
+    pub currency: Currency,
+    /// The broken invariant.
+    pub invariant: Invariant,
+    /// The offending balances.
+    pub balance: CurrencyBalance,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "client {}", self.client)?;
+        if !self.currency.is_default() {
+            write!(f, " {}", self.currency)?;
+        }
+        write!(
+            f,
+            ": {} (available {}, held {}, total {})",
+            self.invariant.code(),
+            self.balance.available.0,
+            self.balance.held.0,
+            self.balance.total.0
+        )
+    }
+}
+
+/// Checks the invariants of every balance of an account.
+///
+/// Returns the violations found, which is empty for a consistent account.
+pub fn check(account: &ClientAccount) -> Vec<InvariantViolation> {
+    const INVARIANTS: [Invariant; 3] = [
+        Invariant::TotalMatchesParts,
+        Invariant::NonNegativeHeld,
+        Invariant::NonNegativeTotal,
+    ];
+
+    account
+        .balances
+        .iter()
+        .flat_map(|(currency, balance)| {
+            INVARIANTS
+                .iter()
+                .filter(|invariant| !invariant.holds(balance, &account.limits))
+                .map(|invariant| InvariantViolation {
+                    client: account.client,
+                    currency: *currency,
+                    invariant: *invariant,
+                    balance: *balance,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_consistent_account() {
+        let mut account = ClientAccount::new(1);
+        account
+            .deposit(Currency::default(), Balance::new(dec!(10)))
+            .unwrap();
+        account
+            .hold(Currency::default(), Balance::new(dec!(4)))
+            .unwrap();
+
+        assert!(check(&account).is_empty());
+    }
+
+    #[test]
+    fn test_detects_violations() {
+        let mut account = ClientAccount::new(1);
+        let balance = account.balance_mut(Currency::default());
+        balance.available = Balance::new(dec!(5));
+        balance.held = Balance::new(dec!(-1));
+        balance.total = Balance::new(dec!(-1));
+
+        let violations = check(&account);
+        assert_eq!(
+            violations
+                .iter()
+                .map(|violation| violation.invariant)
+                .collect::<Vec<_>>(),
+            vec![
+                Invariant::TotalMatchesParts,
+                Invariant::NonNegativeHeld,
+                Invariant::NonNegativeTotal,
+            ]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "client 1: total_mismatch (available 5, held -1, total -1)"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::domain::account::{Balance, ClientAccount};
+use crate::domain::currency::Currency;
+use crate::error::PaymentError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// An account of the double-entry ledger.
+///
+/// Client sub-ledgers mirror the `available` and `held` balances of a `ClientAccount`,
+/// while system accounts record where funds come from and go to.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerAccount {
+    /// Funds available to a client.
+    Available(u16),
+    /// Funds held for a client due to disputes.
+    Held(u16),
+    /// Funds entering or leaving the system (deposits, withdrawals, reversed deposits).
+    Cash,
+    /// Funds credited back to clients on disputed withdrawals.
+    ChargebackLosses,
+    /// Fees collected from clients.
+    Fees,
+}
+
+impl LedgerAccount {
+    /// Returns the client owning this sub-ledger, if any.
+    pub fn client(&self) -> Option<u16> {
+        match self {
+            Self::Available(client) | Self::Held(client) => Some(*client),
+            Self::Cash | Self::ChargebackLosses | Self::Fees => None,
+        }
+    }
+}
+
+impl fmt::Display for LedgerAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Available(client) => write!(f, "client:{}:available", client),
+            Self::Held(client) => write!(f, "client:{}:held", client),
+            Self::Cash => f.write_str("system:cash"),
+            Self::ChargebackLosses => f.write_str("system:chargeback_losses"),
+            Self::Fees => f.write_str("system:fees"),
+        }
+    }
+}
+
+/// A single balanced journal entry, moving `amount` from the `debit` to the `credit` account.
+///
+/// Client sub-ledgers are liabilities of the system: a credit increases the client's balance
+/// and a debit decreases it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct JournalEntry {
+    /// The position of the entry in the journal, assigned by the `JournalStore`.
+    pub seq: u64,
+    /// The transaction that caused the movement.
+    pub tx: u32,
+    /// The currency of the movement.
+    pub currency: Currency,
+    /// The account being debited.
+    pub debit: LedgerAccount,
+    /// The account being credited.
+    pub credit: LedgerAccount,
+    /// The amount moved.
+    pub amount: Balance,
+}
+
+impl JournalEntry {
+    /// Creates an entry that has not been appended to a journal yet.
+    pub fn new(
+        tx: u32,
+        currency: Currency,
+        debit: LedgerAccount,
+        credit: LedgerAccount,
+        amount: Balance,
+    ) -> Self {
+        Self {
+            seq: 0,
+            tx,
+            currency,
+            debit,
+            credit,
+            amount,
+        }
+    }
+
+    /// Returns `true` if the entry touches one of the client's sub-ledgers.
+    pub fn involves_client(&self, client: u16) -> bool {
+        self.debit.client() == Some(client) || self.credit.client() == Some(client)
+    }
+}
+
+/// A difference between a client balance and the matching journal sub-ledger.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LedgerMismatch {
+    /// The sub-ledger that does not match.
+    pub ledger: LedgerAccount,
+    /// The currency of the balance.
+    pub currency: Currency,
+    /// The balance recorded in the client account.
+    pub account_balance: Balance,
+    /// The balance derived from the journal entries.
+    pub journal_balance: Balance,
+}
+
+impl fmt::Display for LedgerMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: account balance {} != journal balance {}",
+            self.ledger, self.currency, self.account_balance.0, self.journal_balance.0
+        )
+    }
+}
+
+/// Checks the journal against the client accounts.
+///
+/// Every entry is balanced by construction, so the journal always sums to zero. The check
+/// proves that, for every client and currency, the entries posted to the `available` and
+/// `held` sub-ledgers add up to the balances of the account (and therefore to its total).
+/// Returns the mismatches found, which is empty when the trial balance holds, or
+/// `PaymentError::Overflow` if a sub-ledger sums beyond the range of a balance.
+pub fn trial_balance(
+    entries: &[JournalEntry],
+    accounts: &[ClientAccount],
+) -> Result<Vec<LedgerMismatch>, PaymentError> {
+    // Only the client sub-ledgers are checked; the system ones, summing the movements of
+    // every client, are not
+    let mut ledgers: BTreeMap<(LedgerAccount, Currency), Balance> = BTreeMap::new();
+    for entry in entries {
+        if entry.debit.client().is_some() {
+            let ledger = ledgers.entry((entry.debit, entry.currency)).or_default();
+            *ledger = ledger.checked_sub(entry.amount)?;
+        }
+        if entry.credit.client().is_some() {
+            let ledger = ledgers.entry((entry.credit, entry.currency)).or_default();
+            *ledger = ledger.checked_add(entry.amount)?;
+        }
+    }
+
+    let mut mismatches = Vec::new();
+    let mut check = |ledger: LedgerAccount, currency: Currency, account_balance: Balance| {
+        let journal_balance = ledgers.remove(&(ledger, currency)).unwrap_or(Balance::ZERO);
+        if journal_balance != account_balance {
+            mismatches.push(LedgerMismatch {
+                ledger,
+                currency,
+                account_balance,
+                journal_balance,
+            });
+        }
+    };
+    for account in accounts {
+        for (currency, balance) in &account.balances {
+            check(
+                LedgerAccount::Available(account.client),
+                *currency,
+                balance.available,
+            );
+            check(LedgerAccount::Held(account.client), *currency, balance.held);
+        }
+    }
+
+    // Client sub-ledgers without a matching account balance
+    for ((ledger, currency), journal_balance) in ledgers {
+        if journal_balance != Balance::ZERO {
+            mismatches.push(LedgerMismatch {
+                ledger,
+                currency,
+                account_balance: Balance::ZERO,
+                journal_balance,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_trial_balance_matches_accounts() {
+        let currency = Currency::default();
+        let mut account = ClientAccount::new(1);
+        account.deposit(currency, Balance::new(dec!(10))).unwrap();
+        account.hold(currency, Balance::new(dec!(4))).unwrap();
+
+        let entries = vec![
+            JournalEntry::new(
+                1,
+                currency,
+                LedgerAccount::Cash,
+                LedgerAccount::Available(1),
+                Balance::new(dec!(10)),
+            ),
+            JournalEntry::new(
+                1,
+                currency,
+                LedgerAccount::Available(1),
+                LedgerAccount::Held(1),
+                Balance::new(dec!(4)),
+            ),
+        ];
+
+        assert!(trial_balance(&entries, &[account]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trial_balance_reports_mismatches() {
+        let currency = Currency::default();
+        let mut account = ClientAccount::new(1);
+        account.deposit(currency, Balance::new(dec!(10))).unwrap();
+
+        let entries = vec![JournalEntry::new(
+            2,
+            currency,
+            LedgerAccount::Cash,
+            LedgerAccount::Available(2),
+            Balance::new(dec!(3)),
+        )];
+
+        let mismatches = trial_balance(&entries, &[account]).unwrap();
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0].ledger, LedgerAccount::Available(1));
+        assert_eq!(mismatches[0].journal_balance, Balance::ZERO);
+        assert_eq!(mismatches[1].ledger, LedgerAccount::Available(2));
+        assert_eq!(mismatches[1].journal_balance, Balance::new(dec!(3)));
+    }
+
+    #[test]
+    fn test_trial_balance_does_not_sum_system_ledgers() {
+        // The cash ledger of two huge deposits would overflow
+        let currency = Currency::default();
+        let amount = Balance::new(dec!(50000000000000000000000000000));
+        let mut accounts = Vec::new();
+        let mut entries = Vec::new();
+        for client in [1, 2] {
+            let mut account = ClientAccount::new(client);
+            account.deposit(currency, amount).unwrap();
+            accounts.push(account);
+            entries.push(JournalEntry::new(
+                client.into(),
+                currency,
+                LedgerAccount::Cash,
+                LedgerAccount::Available(client),
+                amount,
+            ));
+        }
+        assert!(trial_balance(&entries, &accounts).unwrap().is_empty());
+
+        // A client sub-ledger overflowing is reported as an error
+        entries.push(JournalEntry::new(
+            3,
+            currency,
+            LedgerAccount::Cash,
+            LedgerAccount::Available(1),
+            amount,
+        ));
+        assert!(matches!(
+            trial_balance(&entries, &accounts),
+            Err(PaymentError::Overflow(_))
+        ));
+    }
+}
@@ -1,3 +1,14 @@
 pub mod account;
+pub mod checkpoint;
+pub mod currency;
+pub mod dispute;
+pub mod event;
+pub mod fee;
+pub mod ids;
+pub mod invariant;
+pub mod ledger;
+pub mod outcome;
 pub mod ports;
+pub mod risk;
+pub mod snapshot;
 pub mod transaction;
@@ -0,0 +1,96 @@
+use serde::{Serialize, Serializer};
+
+/// The reason a transaction had no effect on the client's account.
+///
+/// Serialized as its [code](Self::code).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RejectionReason {
+    /// The account does not have enough available funds.
+    InsufficientFunds,
+    /// A deposit, withdrawal or transfer reused an already processed transaction ID.
+    DuplicateTransaction,
+    /// The referenced transaction does not exist.
+    UnknownTransaction,
+    /// The referenced transaction belongs to a different client.
+    ClientMismatch,
+    /// The referenced transaction is in a different currency.
+    CurrencyMismatch,
+    /// The referenced transaction cannot be disputed (e.g. a withdrawal).
+    NotDisputable,
+    /// The referenced transaction is not in the dispute state required by the operation.
+    InvalidDisputeState,
+    /// The amount exceeds the undisputed remainder (dispute) or the open disputed amount
+    /// (resolve/chargeback) of the referenced transaction.
+    InvalidDisputeAmount,
+    /// The client's account is locked.
+    AccountLocked,
+    /// The client's account is frozen.
+    AccountFrozen,
+    /// The client's account is closed.
+    AccountClosed,
+    /// An administrative operation does not apply to the account's current status.
+    InvalidStatusTransition,
+    /// A deposit, withdrawal or transfer was submitted without an amount.
+    MissingAmount,
+    /// A transfer has no destination client, or the destination is the source client.
+    InvalidDestination,
+    /// The amount has more decimal places than the precision policy allows.
+    ExcessPrecision,
+    /// Applying the amount would overflow a balance.
+    ArithmeticOverflow,
+    /// A risk rule rejected the transaction.
+    RiskRejected,
+}
+
+impl RejectionReason {
+    /// Returns a stable, machine-readable code for the reason.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InsufficientFunds => "insufficient_funds",
+            Self::DuplicateTransaction => "duplicate_transaction",
+            Self::UnknownTransaction => "unknown_transaction",
+            Self::ClientMismatch => "client_mismatch",
+            Self::CurrencyMismatch => "currency_mismatch",
+            Self::NotDisputable => "not_disputable",
+            Self::InvalidDisputeState => "invalid_dispute_state",
+            Self::InvalidDisputeAmount => "invalid_dispute_amount",
+            Self::AccountLocked => "account_locked",
+            Self::AccountFrozen => "account_frozen",
+            Self::AccountClosed => "account_closed",
+            Self::InvalidStatusTransition => "invalid_status_transition",
+            Self::MissingAmount => "missing_amount",
+            Self::InvalidDestination => "invalid_destination",
+            Self::ExcessPrecision => "excess_precision",
+            Self::ArithmeticOverflow => "arithmetic_overflow",
+            Self::RiskRejected => "risk_rejected",
+        }
+    }
+}
+
+impl Serialize for RejectionReason {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+/// The result of processing a single transaction.
+///
+/// - `Applied`: The transaction changed the account or dispute state.
+/// - `Rejected`: The transaction was ignored, with the reason why.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+pub enum TransactionOutcome {
+    Applied,
+    Rejected { reason: RejectionReason },
+}
+
+impl TransactionOutcome {
+    /// Creates a `Rejected` outcome with the given reason.
+    pub fn rejected(reason: RejectionReason) -> Self {
+        Self::Rejected { reason }
+    }
+
+    /// Returns `true` if the transaction was applied.
+    pub fn is_applied(&self) -> bool {
+        matches!(self, Self::Applied)
+    }
+}
@@ -1,4 +1,12 @@
 use super::account::ClientAccount;
+use super::checkpoint::Checkpoint;
+use super::dispute::DisputeRecord;
+use super::event::EventRecord;
+use super::fee::FeeAccount;
+use super::ids::IdBitmap;
+use super::ledger::JournalEntry;
+use super::risk::RiskState;
+use super::snapshot::Snapshot;
 use super::transaction::Transaction;
 use crate::error::Result;
 use async_trait::async_trait;
@@ -8,6 +16,8 @@ use async_trait::async_trait;
 pub trait AccountStore: Send + Sync {
     /// Persists the current state of a client account.
     async fn store(&self, account: ClientAccount) -> Result<()>;
+    /// Persists several client accounts in a single write.
+    async fn store_all(&self, accounts: Vec<ClientAccount>) -> Result<()>;
     /// Retrieves a client account by ID.
     async fn get(&self, client_id: u16) -> Result<Option<ClientAccount>>;
     /// Retrieves all client accounts currently in the store.
@@ -21,11 +31,125 @@ pub trait AccountStore: Send + Sync {
 pub trait TransactionStore: Send + Sync {
     /// Stores a transaction record.
     async fn store(&self, tx: Transaction) -> Result<()>;
+    /// Records a transaction ID as processed without keeping its record, so the ID is a
+    /// duplicate for later transactions but cannot be disputed.
+    async fn store_id(&self, tx_id: u32) -> Result<()>;
     /// Retrieves a transaction by its global ID.
     async fn get(&self, tx_id: u32) -> Result<Option<Transaction>>;
     /// Checks if a transaction ID has already been processed.
     async fn exists(&self, tx_id: u32) -> Result<bool>;
+    /// Retrieves all transaction records currently in the store.
+    async fn get_all(&self) -> Result<Vec<Transaction>>;
+    /// Retrieves the IDs of all processed transactions, whether their record was kept or not.
+    async fn get_all_ids(&self) -> Result<IdBitmap>;
+    /// Stores the disputes of a transaction, replacing the previous ones.
+    async fn store_disputes(&self, record: DisputeRecord) -> Result<()>;
+    /// Retrieves the disputes of a transaction, if it was ever disputed.
+    async fn get_disputes(&self, tx_id: u32) -> Result<Option<DisputeRecord>>;
+    /// Retrieves the disputes of all disputed transactions.
+    async fn get_all_disputes(&self) -> Result<Vec<DisputeRecord>>;
+}
+
+/// Every write of a single input transaction.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UnitOfWork {
+    /// The client accounts to persist.
+    pub accounts: Vec<ClientAccount>,
+    /// The transaction records to persist.
+    pub transactions: Vec<Transaction>,
+    /// The IDs of transactions that had no effect but must still be rejected as duplicates.
+    pub transaction_ids: Vec<u32>,
+    /// The dispute records to persist, replacing the previous ones of their transactions.
+    pub disputes: Vec<DisputeRecord>,
+    /// The fee account to persist, replacing the previous one, if fees were collected.
+    pub fees: Option<FeeAccount>,
+    /// The risk state of the client to persist, replacing the previous one, if it changed.
+    pub risk: Option<RiskState>,
+    /// The journal entries to append, numbered by the commit.
+    pub journal: Vec<JournalEntry>,
+    /// The events to append to the event log, numbered by the commit.
+    pub events: Vec<EventRecord>,
+    /// The input checkpoint to persist, replacing the previous one.
+    pub checkpoint: Option<Checkpoint>,
+}
+
+#[async_trait]
+/// Interface for persisting every write of a transaction together.
+///
+/// Without it, a crash between the writes could leave a transaction recorded as seen while its
+/// account was never updated, and a rerun would then reject it as a duplicate, or leave
+/// journal entries and events behind for a transaction that was never applied.
+pub trait UnitOfWorkStore: Send + Sync {
+    /// Persists every write of the unit of work, or none of them.
+    async fn commit(&self, work: UnitOfWork) -> Result<()>;
+}
+
+#[async_trait]
+/// Interface for the append-only double-entry journal.
+pub trait JournalStore: Send + Sync {
+    /// Appends entries to the journal, assigning their sequence numbers.
+    async fn append(&self, entries: Vec<JournalEntry>) -> Result<()>;
+    /// Retrieves the entries touching a client's sub-ledgers, in posting order.
+    async fn get_for_client(&self, client_id: u16) -> Result<Vec<JournalEntry>>;
+    /// Retrieves all journal entries, in posting order.
+    async fn get_all(&self) -> Result<Vec<JournalEntry>>;
+}
+
+#[async_trait]
+/// Interface for the append-only log of domain events.
+pub trait EventStore: Send + Sync {
+    /// Appends events to the log, assigning their sequence numbers.
+    async fn append(&self, events: Vec<EventRecord>) -> Result<()>;
+    /// Retrieves the events of a client, in log order.
+    async fn get_for_client(&self, client_id: u16) -> Result<Vec<EventRecord>>;
+    /// Retrieves all events, in log order.
+    async fn get_all(&self) -> Result<Vec<EventRecord>>;
+}
+
+#[async_trait]
+/// Interface for persisting engine snapshots.
+pub trait SnapshotStore: Send + Sync {
+    /// Persists a snapshot: a full snapshot replaces the previous ones, an incremental one is
+    /// kept on top of them.
+    async fn save(&self, snapshot: &Snapshot) -> Result<()>;
+    /// Retrieves the latest full snapshot with the incremental snapshots taken after it
+    /// applied, if any was taken.
+    async fn latest(&self) -> Result<Option<Snapshot>>;
+}
+
+#[async_trait]
+/// Interface for persisting the checkpoint of the input being processed.
+pub trait CheckpointStore: Send + Sync {
+    /// Persists a checkpoint, replacing the previous one.
+    async fn save(&self, checkpoint: Checkpoint) -> Result<()>;
+    /// Retrieves the latest checkpoint, if any was saved.
+    async fn latest(&self) -> Result<Option<Checkpoint>>;
+}
+
+#[async_trait]
+/// Interface for persisting the per-client state of the risk rules.
+pub trait RiskStore: Send + Sync {
+    /// Persists the risk state of a client, replacing the previous one.
+    async fn store(&self, state: RiskState) -> Result<()>;
+    /// Retrieves the risk state of a client.
+    async fn get(&self, client_id: u16) -> Result<Option<RiskState>>;
+}
+
+#[async_trait]
+/// Interface for persisting the system account collecting the fees.
+pub trait FeeStore: Send + Sync {
+    /// Persists the fee account, replacing the previous one.
+    async fn store(&self, account: FeeAccount) -> Result<()>;
+    /// Retrieves the fee account (empty if no fee was ever collected).
+    async fn get(&self) -> Result<FeeAccount>;
 }
 
 pub type AccountStoreBox = Box<dyn AccountStore>;
 pub type TransactionStoreBox = Box<dyn TransactionStore>;
+pub type JournalStoreBox = Box<dyn JournalStore>;
+pub type EventStoreBox = Box<dyn EventStore>;
+pub type SnapshotStoreBox = Box<dyn SnapshotStore>;
+pub type CheckpointStoreBox = Box<dyn CheckpointStore>;
+pub type RiskStoreBox = Box<dyn RiskStore>;
+pub type FeeStoreBox = Box<dyn FeeStore>;
+pub type UnitOfWorkStoreBox = Box<dyn UnitOfWorkStore>;
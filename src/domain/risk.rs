@@ -0,0 +1,329 @@
+use crate::domain::account::Balance;
+use crate::domain::transaction::{Transaction, TransactionType};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The verdict of a risk rule on a transaction.
+///
+/// - `Allow`: The transaction is applied.
+/// - `Reject`: The transaction is ignored.
+/// - `Flag`: The transaction is applied, but reported for review.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RiskDecision {
+    Allow,
+    Reject { rule: &'static str },
+    Flag { rule: &'static str },
+}
+
+/// An applied transaction flagged for review by a risk rule.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RiskFlag {
+    /// The client who issued the transaction.
+    pub client: u16,
+    /// The flagged transaction.
+    pub tx: u32,
+    /// The name of the rule that flagged it.
+    pub rule: &'static str,
+}
+
+impl fmt::Display for RiskFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "transaction {} of client {}: {}",
+            self.tx, self.client, self.rule
+        )
+    }
+}
+
+/// What a rule decides when a transaction matches it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskAction {
+    #[default]
+    Reject,
+    Flag,
+}
+
+impl RiskAction {
+    /// Returns the decision of the rule named `rule`.
+    pub fn decide(&self, rule: &'static str) -> RiskDecision {
+        match self {
+            Self::Reject => RiskDecision::Reject { rule },
+            Self::Flag => RiskDecision::Flag { rule },
+        }
+    }
+}
+
+/// An applied transaction that moved funds, as remembered by the risk rules.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct Activity {
+    /// The position of the transaction in the input.
+    pub position: u64,
+    /// The type of the transaction (deposit, withdrawal or transfer).
+    pub r#type: TransactionType,
+    /// The amount moved.
+    pub amount: Balance,
+}
+
+/// The recent activity of a client, kept between transactions for the risk rules.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct RiskState {
+    /// The client the activity belongs to.
+    pub client: u16,
+    /// The funds movements of the client, oldest first.
+    pub activity: Vec<Activity>,
+}
+
+impl RiskState {
+    pub fn new(client: u16) -> Self {
+        Self {
+            client,
+            activity: Vec::new(),
+        }
+    }
+
+    /// Forgets the activity older than `window` positions before `position`.
+    pub fn prune(&mut self, position: u64, window: u64) {
+        self.activity
+            .retain(|activity| activity.position + window >= position);
+    }
+
+    /// Returns the activity within `window` positions before `position`.
+    pub fn recent(&self, position: u64, window: u64) -> impl Iterator<Item = &Activity> {
+        self.activity
+            .iter()
+            .filter(move |activity| activity.position + window >= position)
+    }
+}
+
+/// A fraud or velocity control evaluated before a transaction is applied.
+///
+/// Positions are the indices of transactions in the input, which act as the clock of the
+/// rules: windows are expressed in number of input transactions.
+pub trait RiskRule: Send + Sync {
+    /// Returns a stable, machine-readable name for the rule.
+    fn name(&self) -> &'static str;
+
+    /// Returns how many positions of past activity the rule looks at.
+    fn window(&self) -> u64 {
+        0
+    }
+
+    /// Evaluates a transaction at `position` against the recent activity of its client.
+    fn evaluate(&self, tx: &Transaction, state: &RiskState, position: u64) -> RiskDecision;
+}
+
+pub type RiskRuleBox = Box<dyn RiskRule>;
+
+/// Returns `true` for transactions taking funds out of the client's account.
+fn is_outgoing(r#type: TransactionType) -> bool {
+    matches!(
+        r#type,
+        TransactionType::Withdrawal | TransactionType::Transfer
+    )
+}
+
+/// Allows at most `max` withdrawals (and outgoing transfers) per client within `window`
+/// transactions.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct WithdrawalVelocity {
+    pub max: usize,
+    pub window: u64,
+    #[serde(default)]
+    pub action: RiskAction,
+}
+
+impl RiskRule for WithdrawalVelocity {
+    fn name(&self) -> &'static str {
+        "withdrawal_velocity"
+    }
+
+    fn window(&self) -> u64 {
+        self.window
+    }
+
+    fn evaluate(&self, tx: &Transaction, state: &RiskState, position: u64) -> RiskDecision {
+        if !is_outgoing(tx.r#type) {
+            return RiskDecision::Allow;
+        }
+        let count = state
+            .recent(position, self.window)
+            .filter(|activity| is_outgoing(activity.r#type))
+            .count();
+        if count >= self.max {
+            self.action.decide(self.name())
+        } else {
+            RiskDecision::Allow
+        }
+    }
+}
+
+/// Limits the amount of a single withdrawal (or outgoing transfer).
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct MaxWithdrawalAmount {
+    pub limit: Decimal,
+    #[serde(default)]
+    pub action: RiskAction,
+}
+
+impl RiskRule for MaxWithdrawalAmount {
+    fn name(&self) -> &'static str {
+        "max_withdrawal_amount"
+    }
+
+    fn evaluate(&self, tx: &Transaction, _state: &RiskState, _position: u64) -> RiskDecision {
+        match tx.amount {
+            Some(amount) if is_outgoing(tx.r#type) && amount.value() > self.limit => {
+                self.action.decide(self.name())
+            }
+            _ => RiskDecision::Allow,
+        }
+    }
+}
+
+/// Catches withdrawals (or outgoing transfers) within `window` transactions of a deposit by
+/// the same client, a common pattern to cash out stolen funds.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DepositThenWithdrawal {
+    pub window: u64,
+    #[serde(default)]
+    pub action: RiskAction,
+}
+
+impl RiskRule for DepositThenWithdrawal {
+    fn name(&self) -> &'static str {
+        "deposit_then_withdrawal"
+    }
+
+    fn window(&self) -> u64 {
+        self.window
+    }
+
+    fn evaluate(&self, tx: &Transaction, state: &RiskState, position: u64) -> RiskDecision {
+        let deposited = state
+            .recent(position, self.window)
+            .any(|activity| activity.r#type == TransactionType::Deposit);
+        if is_outgoing(tx.r#type) && deposited {
+            self.action.decide(self.name())
+        } else {
+            RiskDecision::Allow
+        }
+    }
+}
+
+/// The configuration of a built-in rule, as found in a rules file.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleConfig {
+    WithdrawalVelocity(WithdrawalVelocity),
+    MaxWithdrawalAmount(MaxWithdrawalAmount),
+    DepositThenWithdrawal(DepositThenWithdrawal),
+}
+
+impl RuleConfig {
+    /// Builds the configured rule.
+    pub fn into_rule(self) -> RiskRuleBox {
+        match self {
+            Self::WithdrawalVelocity(rule) => Box::new(rule),
+            Self::MaxWithdrawalAmount(rule) => Box::new(rule),
+            Self::DepositThenWithdrawal(rule) => Box::new(rule),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::currency::Currency;
+    use rust_decimal_macros::dec;
+
+    fn tx(r#type: TransactionType, amount: Decimal) -> Transaction {
+        Transaction {
+            r#type,
+            client: 1,
+            tx: 1,
+            amount: Some(amount.try_into().unwrap()),
+            currency: Currency::default(),
+            destination: None,
+        }
+    }
+
+    fn activity(position: u64, r#type: TransactionType) -> Activity {
+        Activity {
+            position,
+            r#type,
+            amount: Balance::new(dec!(1)),
+        }
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let withdrawal = tx(TransactionType::Withdrawal, dec!(50));
+        let mut state = RiskState::new(1);
+        state.activity = vec![
+            activity(1, TransactionType::Deposit),
+            activity(5, TransactionType::Withdrawal),
+            activity(8, TransactionType::Withdrawal),
+        ];
+
+        let velocity = WithdrawalVelocity {
+            max: 2,
+            window: 5,
+            action: RiskAction::Reject,
+        };
+        assert_eq!(
+            velocity.evaluate(&withdrawal, &state, 10),
+            RiskDecision::Reject {
+                rule: "withdrawal_velocity"
+            }
+        );
+        assert_eq!(
+            velocity.evaluate(&withdrawal, &state, 11),
+            RiskDecision::Allow
+        );
+
+        let max_amount = MaxWithdrawalAmount {
+            limit: dec!(20),
+            action: RiskAction::Flag,
+        };
+        assert_eq!(
+            max_amount.evaluate(&withdrawal, &state, 10),
+            RiskDecision::Flag {
+                rule: "max_withdrawal_amount"
+            }
+        );
+        let deposit = tx(TransactionType::Deposit, dec!(50));
+        assert_eq!(
+            max_amount.evaluate(&deposit, &state, 10),
+            RiskDecision::Allow
+        );
+
+        let quick = DepositThenWithdrawal {
+            window: 2,
+            action: RiskAction::Reject,
+        };
+        assert_eq!(
+            quick.evaluate(&withdrawal, &state, 3),
+            RiskDecision::Reject {
+                rule: "deposit_then_withdrawal"
+            }
+        );
+        assert_eq!(quick.evaluate(&withdrawal, &state, 4), RiskDecision::Allow);
+    }
+
+    #[test]
+    fn test_prune_keeps_the_window() {
+        let mut state = RiskState::new(1);
+        state.activity = vec![
+            activity(1, TransactionType::Deposit),
+            activity(5, TransactionType::Withdrawal),
+        ];
+        state.prune(8, 3);
+        assert_eq!(
+            state.activity,
+            vec![activity(5, TransactionType::Withdrawal)]
+        );
+    }
+}
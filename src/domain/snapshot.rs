@@ -0,0 +1,132 @@
+use crate::domain::account::ClientAccount;
+use crate::domain::dispute::DisputeRecord;
+use crate::domain::fee::FeeAccount;
+use crate::domain::ids::IdBitmap;
+use crate::domain::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A consistent image of the engine state after a number of input transactions.
+///
+/// A full snapshot holds every account, every transaction record kept for disputes, the open
+/// and settled disputes of each transaction and the IDs of every transaction processed, so
+/// the engine can carry on from the snapshot
+/// as if it had never stopped. An incremental snapshot only holds what changed since the
+/// previous snapshot, and is applied on top of it with [`Snapshot::extend`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Snapshot {
+    /// The number of input transactions applied when the snapshot was taken.
+    pub position: u64,
+    /// Whether the snapshot only holds the changes since the previous one.
+    #[serde(default)]
+    pub incremental: bool,
+    /// The state of all accounts, or of those modified since the previous snapshot.
+    pub accounts: Vec<ClientAccount>,
+    /// The recorded transactions, or those recorded since the previous snapshot.
+    pub transactions: Vec<Transaction>,
+    /// The disputes of the disputed transactions, or of those disputed since the previous
+    /// snapshot.
+    #[serde(default)]
+    pub disputes: Vec<DisputeRecord>,
+    /// The IDs of all processed transactions, or of those processed since the previous
+    /// snapshot, including those whose record is not kept, so they are still duplicates.
+    #[serde(default)]
+    pub transaction_ids: IdBitmap,
+    /// The fees collected so far.
+    #[serde(default)]
+    pub fees: FeeAccount,
+}
+
+impl Snapshot {
+    /// Applies an incremental snapshot taken after this one, which then describes the state
+    /// at the position of `increment`.
+    ///
+    /// The accounts and dispute records of the increment replace those of the same client or
+    /// transaction, and its transaction records and IDs are added.
+    pub fn extend(&mut self, increment: Snapshot) {
+        let mut accounts: BTreeMap<u16, ClientAccount> = self
+            .accounts
+            .drain(..)
+            .map(|account| (account.client, account))
+            .collect();
+        accounts.extend(
+            increment
+                .accounts
+                .into_iter()
+                .map(|account| (account.client, account)),
+        );
+        let mut disputes: BTreeMap<u32, DisputeRecord> = self
+            .disputes
+            .drain(..)
+            .map(|record| (record.tx, record))
+            .collect();
+        disputes.extend(
+            increment
+                .disputes
+                .into_iter()
+                .map(|record| (record.tx, record)),
+        );
+
+        self.position = increment.position;
+        self.accounts = accounts.into_values().collect();
+        self.transactions.extend(increment.transactions);
+        self.transaction_ids
+            .extend(increment.transaction_ids.iter());
+        self.disputes = disputes.into_values().collect();
+        self.fees = increment.fees;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::account::Balance;
+    use crate::domain::currency::Currency;
+    use crate::domain::dispute::Dispute;
+    use crate::domain::transaction::TransactionType;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_extend_applies_increment() {
+        let deposit = |client, tx| Transaction {
+            r#type: TransactionType::Deposit,
+            client,
+            tx,
+            amount: Some(dec!(10).try_into().unwrap()),
+            currency: Currency::default(),
+            destination: None,
+        };
+        let mut snapshot = Snapshot {
+            position: 2,
+            accounts: vec![ClientAccount::new(1), ClientAccount::new(2)],
+            transactions: vec![deposit(1, 1), deposit(2, 2)],
+            ..Snapshot::default()
+        };
+
+        let mut updated = ClientAccount::new(2);
+        updated.balance_mut(Currency::default()).available = Balance::new(dec!(10));
+        let mut record = DisputeRecord::new(1);
+        record
+            .disputes
+            .push(Dispute::open(Balance::new(dec!(10)), Balance::ZERO));
+        snapshot.extend(Snapshot {
+            position: 4,
+            incremental: true,
+            accounts: vec![updated.clone()],
+            transactions: vec![deposit(3, 3)],
+            disputes: vec![record.clone()],
+            transaction_ids: [3, 4].into_iter().collect(),
+            ..Snapshot::default()
+        });
+
+        assert_eq!(snapshot.position, 4);
+        assert!(!snapshot.incremental);
+        assert_eq!(snapshot.accounts, vec![ClientAccount::new(1), updated]);
+        assert_eq!(snapshot.transactions.len(), 3);
+        assert_eq!(snapshot.disputes, vec![record]);
+        assert_eq!(
+            snapshot.transaction_ids.iter().collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+    }
+}
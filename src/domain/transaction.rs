@@ -1,21 +1,8 @@
 use crate::domain::account::Amount;
+use crate::domain::currency::Currency;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-/// Represents the dispute state of a transaction.
-///
-/// - `None`: The transaction is not under dispute.
-/// - `Disputed`: The transaction has been disputed and funds are held.
-/// - `Chargebacked`: The dispute was finalized as a chargeback.
-#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
-pub enum DisputeStatus {
-    #[default]
-    None,
-    Disputed,
-    Resolved,
-    Chargebacked,
-}
-
 /// The type of operation requested by a transaction.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
@@ -30,6 +17,34 @@ pub enum TransactionType {
     Resolve,
     /// A finalization of a dispute, reversing the transaction.
     Chargeback,
+    /// A movement of funds from the client's account to a destination client's account.
+    Transfer,
+    /// Administrative: freezes the client's account, blocking outgoing funds.
+    Freeze,
+    /// Administrative: returns a frozen or locked account to active.
+    #[serde(alias = "unfreeze")]
+    Unlock,
+    /// Administrative: closes the client's account for good.
+    Close,
+    /// Administrative: sets the overdraft ceiling of the account to the amount (removes it
+    /// without an amount).
+    #[serde(rename = "set_overdraft")]
+    SetOverdraft,
+    /// Administrative: sets the minimum reserve balance of the account to the amount (removes
+    /// it without an amount).
+    #[serde(rename = "set_reserve")]
+    SetReserve,
+}
+
+impl TransactionType {
+    /// Returns `true` for administrative operations, which change the account status or
+    /// limits only.
+    pub fn is_admin(&self) -> bool {
+        matches!(
+            self,
+            Self::Freeze | Self::Unlock | Self::Close | Self::SetOverdraft | Self::SetReserve
+        )
+    }
 }
 
 /// Represents a single financial transaction or operation.
@@ -41,12 +56,19 @@ pub struct Transaction {
     pub client: u16,
     /// The global unique transaction identifier.
     pub tx: u32,
-    /// The amount involved in the transaction (optional for disputes/resolves/chargebacks).
+    /// The amount involved in the transaction.
+    ///
+    /// Optional for disputes, where it limits the dispute to part of the referenced transaction
+    /// (a partial dispute), and for resolves/chargebacks, where it selects the open partial
+    /// dispute of that amount to settle.
     #[serde(deserialize_with = "deserialize_optional_amount")]
     pub amount: Option<Amount>,
-    /// The current dispute status of this transaction.
+    /// The currency of the amount (optional column; unspecified when absent).
+    #[serde(default)]
+    pub currency: Currency,
+    /// The client receiving the funds of a transfer (optional column).
     #[serde(default)]
-    pub dispute_status: DisputeStatus,
+    pub destination: Option<u16>,
 }
 
 fn deserialize_optional_amount<'de, D>(deserializer: D) -> Result<Option<Amount>, D::Error>
@@ -67,7 +89,7 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_transaction_deserialization_skips_status() {
+    fn test_transaction_deserialization_optional_columns() {
         let csv = "type, client, tx, amount\ndeposit, 1, 1, 1.0";
         let mut reader = csv::ReaderBuilder::new()
             .trim(csv::Trim::All)
@@ -80,6 +102,7 @@ mod tests {
             .expect("Failed to deserialize transaction");
 
         assert_eq!(result.r#type, TransactionType::Deposit);
-        assert_eq!(result.dispute_status, DisputeStatus::None);
+        assert_eq!(result.currency, Currency::default());
+        assert_eq!(result.destination, None);
     }
 }
@@ -1,4 +1,6 @@
+use crate::domain::invariant::InvariantViolation;
 use miette::Diagnostic;
+use rust_decimal::Decimal;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, PaymentError>;
@@ -10,6 +12,42 @@ pub enum PaymentError {
 
     #[error("Internal error: {0}")]
     InternalError(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Arithmetic overflow: {0}")]
+    Overflow(String),
+
+    #[error("Amount {amount} exceeds the maximum transaction amount {limit}")]
+    AmountLimitExceeded { amount: Decimal, limit: Decimal },
+
+    #[error("Account invariant violated: {}", join_violations(.0))]
+    InvariantViolation(Vec<InvariantViolation>),
+
+    #[error("{}", describe_schema(*.found, *.supported))]
+    IncompatibleSchema { found: u32, supported: u32 },
+}
+
+fn describe_schema(found: u32, supported: u32) -> String {
+    if found < supported {
+        format!(
+            "The database is at schema version {}, older than the version {} of this build; \
+             upgrade it with the `migrate` command",
+            found, supported
+        )
+    } else {
+        format!(
+            "The database is at schema version {}, newer than the version {} of this build; \
+             it was written by a more recent release",
+            found, supported
+        )
+    }
+}
+
+fn join_violations(violations: &[InvariantViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 impl From<csv::Error> for PaymentError {
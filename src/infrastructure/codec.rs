@@ -0,0 +1,851 @@
+//! Compact binary encoding of the values stored for accounts, transactions and disputes.
+//!
+//! Every encoded value starts with a two-byte header: [`FORMAT_MAGIC`], which can never start
+//! a JSON document, then the format version. Integers are fixed-width big-endian, decimals use
+//! the 16-byte form of `rust_decimal`, currencies their three code bytes, and enums a single
+//! byte. Values without the header are decoded as JSON, the encoding of older databases.
+//!
+//! Version 1 of the format kept the dispute state of a transaction in its value; it is only
+//! read by [`decode_legacy_transaction`], when migrating a database.
+
+use crate::domain::account::{
+    AccountLimits, AccountStatus, Amount, Balance, ClientAccount, CurrencyBalance, DisputeStats,
+    LockCause, LockReason,
+};
+use crate::domain::currency::Currency;
+use crate::domain::dispute::{Dispute, DisputeRecord, DisputeStatus};
+use crate::domain::transaction::{Transaction, TransactionType};
+use crate::error::{PaymentError, Result};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+/// First byte of a binary encoded value.
+pub const FORMAT_MAGIC: u8 = 0xB1;
+/// Version of the binary encoding written by the `encode_*` functions.
+pub const FORMAT_VERSION: u8 = 2;
+/// Version of the binary encoding of transactions along with their dispute state.
+const LEGACY_FORMAT_VERSION: u8 = 1;
+
+/// Presence flags of the optional parts of an encoded transaction.
+const TX_AMOUNT: u8 = 1;
+const TX_DESTINATION: u8 = 1 << 1;
+/// Presence flag of the disputed amounts of a transaction, in the legacy format.
+const TX_DISPUTED: u8 = 1 << 2;
+
+/// Presence flags of the optional parts of an encoded account.
+const ACCOUNT_LIMITS: u8 = 1;
+const ACCOUNT_RECEIVABLES: u8 = 1 << 1;
+const ACCOUNT_DISPUTES: u8 = 1 << 2;
+const ACCOUNT_LOCK_REASON: u8 = 1 << 3;
+const LOCK_TIMESTAMP: u8 = 1;
+
+/// Encodes a transaction record.
+pub fn encode_transaction(tx: &Transaction) -> Vec<u8> {
+    let mut flags = 0;
+    if tx.amount.is_some() {
+        flags |= TX_AMOUNT;
+    }
+    if tx.destination.is_some() {
+        flags |= TX_DESTINATION;
+    }
+
+    let mut out = Encoder::new();
+    out.u8(type_code(tx.r#type));
+    out.u16(tx.client);
+    out.u32(tx.tx);
+    out.u8(flags);
+    out.currency(tx.currency);
+    if let Some(amount) = tx.amount {
+        out.decimal(amount.value());
+    }
+    if let Some(destination) = tx.destination {
+        out.u16(destination);
+    }
+    out.bytes
+}
+
+/// Decodes a transaction record, in the binary encoding or in JSON.
+///
+/// Fails on a value still holding the dispute state of the transaction, which only databases
+/// awaiting a migration have.
+pub fn decode_transaction(bytes: &[u8]) -> Result<Transaction> {
+    match decode_legacy_transaction(bytes)? {
+        (tx, None) => Ok(tx),
+        (_, Some(_)) => Err(corrupted("dispute state in a transaction record")),
+    }
+}
+
+/// Decodes a transaction record in any encoding, including the legacy ones keeping the dispute
+/// state of the transaction, which is then returned as its dispute record.
+pub fn decode_legacy_transaction(bytes: &[u8]) -> Result<(Transaction, Option<DisputeRecord>)> {
+    let Some((version, mut input)) = Decoder::binary(bytes)? else {
+        return decode_json::<LegacyTransaction>(bytes)?.split();
+    };
+    let r#type = type_from_code(input.u8()?)?;
+    let client = input.u16()?;
+    let tx = input.u32()?;
+    let flags = input.u8()?;
+    let legacy_status = match version {
+        LEGACY_FORMAT_VERSION => Some(legacy_status_from_code(input.u8()?)?),
+        _ => None,
+    };
+    let currency = input.currency()?;
+    let amount = match flags & TX_AMOUNT {
+        0 => None,
+        _ => Some(Amount::new(input.decimal()?)?),
+    };
+    let destination = match flags & TX_DESTINATION {
+        0 => None,
+        _ => Some(input.u16()?),
+    };
+    let disputes = match (legacy_status, flags & TX_DISPUTED) {
+        (Some(status), TX_DISPUTED) => legacy_disputes(
+            tx,
+            amount,
+            status,
+            input.balance()?,
+            input.balance()?,
+            input.balance()?,
+        )?,
+        (Some(status), _) => legacy_disputes(
+            tx,
+            amount,
+            status,
+            Balance::ZERO,
+            Balance::ZERO,
+            Balance::ZERO,
+        )?,
+        (None, TX_DISPUTED) => return Err(corrupted("unknown transaction flags")),
+        (None, _) => None,
+    };
+    input.finish()?;
+    let transaction = Transaction {
+        r#type,
+        client,
+        tx,
+        amount,
+        currency,
+        destination,
+    };
+    Ok((transaction, disputes))
+}
+
+/// A transaction record in JSON, as written before the dispute records were kept apart.
+#[derive(Deserialize)]
+pub struct LegacyTransaction {
+    #[serde(flatten)]
+    tx: Transaction,
+    #[serde(default)]
+    dispute_status: LegacyDisputeStatus,
+    #[serde(default)]
+    open_disputed: Balance,
+    #[serde(default)]
+    total_disputed: Balance,
+    #[serde(default)]
+    shortfall: Balance,
+}
+
+impl LegacyTransaction {
+    /// Splits the record into the transaction and its dispute record, if it was disputed.
+    pub fn split(self) -> Result<(Transaction, Option<DisputeRecord>)> {
+        let disputes = legacy_disputes(
+            self.tx.tx,
+            self.tx.amount,
+            self.dispute_status,
+            self.open_disputed,
+            self.total_disputed,
+            self.shortfall,
+        )?;
+        Ok((self.tx, disputes))
+    }
+}
+
+/// The dispute state of a transaction, as recorded before the dispute records were kept apart.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Deserialize)]
+enum LegacyDisputeStatus {
+    #[default]
+    None,
+    Disputed,
+    Resolved,
+    Chargebacked,
+}
+
+/// Rebuilds the dispute record of a transaction from its aggregated dispute state.
+///
+/// The individual disputes were not recorded, so the open amount becomes a single open
+/// dispute, and the settled amount a single dispute settled as the last one was. Records
+/// written before partial disputes have a status but no amounts, their dispute covering the
+/// whole `amount` of the transaction.
+fn legacy_disputes(
+    tx: u32,
+    amount: Option<Amount>,
+    status: LegacyDisputeStatus,
+    open_disputed: Balance,
+    total_disputed: Balance,
+    shortfall: Balance,
+) -> Result<Option<DisputeRecord>> {
+    let (open_disputed, total_disputed) =
+        if total_disputed == Balance::ZERO && status != LegacyDisputeStatus::None {
+            let amount = amount.map_or(Balance::ZERO, Balance::from);
+            match status {
+                LegacyDisputeStatus::Disputed => (amount, amount),
+                _ => (Balance::ZERO, amount),
+            }
+        } else {
+            (open_disputed, total_disputed)
+        };
+    let mut record = DisputeRecord::new(tx);
+    let settled = total_disputed.checked_sub(open_disputed)?;
+    if settled > Balance::ZERO {
+        let status = match status {
+            LegacyDisputeStatus::Chargebacked => DisputeStatus::Chargebacked,
+            _ => DisputeStatus::Resolved,
+        };
+        record.disputes.push(Dispute {
+            amount: settled,
+            shortfall: Balance::ZERO,
+            status,
+        });
+    }
+    if open_disputed > Balance::ZERO {
+        record
+            .disputes
+            .push(Dispute::open(open_disputed, shortfall));
+    }
+    Ok((!record.disputes.is_empty()).then_some(record))
+}
+
+/// Encodes the dispute record of a transaction.
+pub fn encode_disputes(record: &DisputeRecord) -> Vec<u8> {
+    let mut out = Encoder::new();
+    out.u32(record.tx);
+    out.u32(record.disputes.len() as u32);
+    for dispute in &record.disputes {
+        out.u8(dispute_status_code(dispute.status));
+        out.balance(dispute.amount);
+        out.balance(dispute.shortfall);
+    }
+    out.bytes
+}
+
+/// Decodes the dispute record of a transaction, in the binary encoding or in JSON.
+pub fn decode_disputes(bytes: &[u8]) -> Result<DisputeRecord> {
+    let Some((_, mut input)) = Decoder::binary(bytes)? else {
+        return decode_json(bytes);
+    };
+    let mut record = DisputeRecord::new(input.u32()?);
+    for _ in 0..input.u32()? {
+        let status = dispute_status_from_code(input.u8()?)?;
+        record.disputes.push(Dispute {
+            amount: input.balance()?,
+            shortfall: input.balance()?,
+            status,
+        });
+    }
+    input.finish()?;
+    Ok(record)
+}
+
+/// Encodes a client account.
+pub fn encode_account(account: &ClientAccount) -> Vec<u8> {
+    let mut flags = 0;
+    if !account.limits.is_default() {
+        flags |= ACCOUNT_LIMITS;
+    }
+    if !account.receivables.is_empty() {
+        flags |= ACCOUNT_RECEIVABLES;
+    }
+    if !account.disputes.is_default() {
+        flags |= ACCOUNT_DISPUTES;
+    }
+    if account.lock_reason.is_some() {
+        flags |= ACCOUNT_LOCK_REASON;
+    }
+
+    let mut out = Encoder::new();
+    out.u16(account.client);
+    out.u8(status_code(account.status));
+    out.u8(flags);
+    out.len(account.balances.len());
+    for (currency, balance) in &account.balances {
+        out.currency(*currency);
+        out.balance(balance.available);
+        out.balance(balance.held);
+        out.balance(balance.total);
+    }
+    if flags & ACCOUNT_LIMITS != 0 {
+        out.balance(account.limits.overdraft);
+        out.balance(account.limits.reserve);
+    }
+    if flags & ACCOUNT_RECEIVABLES != 0 {
+        out.balances(&account.receivables);
+    }
+    if flags & ACCOUNT_DISPUTES != 0 {
+        out.u32(account.disputes.open);
+        out.balances(&account.disputes.value);
+    }
+    if let Some(reason) = &account.lock_reason {
+        out.u32(reason.tx);
+        out.u8(type_code(reason.r#type));
+        out.u8(lock_cause_code(reason.cause));
+        out.u64(reason.position);
+        match reason.timestamp {
+            Some(timestamp) => {
+                out.u8(LOCK_TIMESTAMP);
+                out.u64(timestamp);
+            }
+            None => out.u8(0),
+        }
+    }
+    out.bytes
+}
+
+/// Decodes a client account, in the binary encoding or in JSON.
+pub fn decode_account(bytes: &[u8]) -> Result<ClientAccount> {
+    // The layout of accounts is the same in every version
+    let Some((_, mut input)) = Decoder::binary(bytes)? else {
+        return decode_json::<JsonAccount>(bytes).map(ClientAccount::from);
+    };
+    let mut account = ClientAccount::new(input.u16()?);
+    account.status = status_from_code(input.u8()?)?;
+    let flags = input.u8()?;
+    for _ in 0..input.len()? {
+        let currency = input.currency()?;
+        let balance = CurrencyBalance {
+            available: input.balance()?,
+            held: input.balance()?,
+            total: input.balance()?,
+        };
+        account.balances.insert(currency, balance);
+    }
+    if flags & ACCOUNT_LIMITS != 0 {
+        account.limits = AccountLimits {
+            overdraft: input.balance()?,
+            reserve: input.balance()?,
+        };
+    }
+    if flags & ACCOUNT_RECEIVABLES != 0 {
+        account.receivables = input.balances()?;
+    }
+    if flags & ACCOUNT_DISPUTES != 0 {
+        account.disputes = DisputeStats {
+            open: input.u32()?,
+            value: input.balances()?,
+        };
+    }
+    if flags & ACCOUNT_LOCK_REASON != 0 {
+        account.lock_reason = Some(LockReason {
+            tx: input.u32()?,
+            r#type: type_from_code(input.u8()?)?,
+            cause: lock_cause_from_code(input.u8()?)?,
+            position: input.u64()?,
+            timestamp: match input.u8()? & LOCK_TIMESTAMP {
+                0 => None,
+                _ => Some(input.u64()?),
+            },
+        });
+    }
+    input.finish()?;
+    Ok(account)
+}
+
+/// A client account in JSON, in the current layout or in the one written before the
+/// balances were kept per currency.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonAccount {
+    Current(ClientAccount),
+    Legacy(LegacyAccount),
+}
+
+/// A client account in JSON, as written before the balances were kept per currency.
+#[derive(Deserialize)]
+struct LegacyAccount {
+    client: u16,
+    available: Balance,
+    held: Balance,
+    total: Balance,
+    locked: bool,
+}
+
+impl From<JsonAccount> for ClientAccount {
+    fn from(account: JsonAccount) -> Self {
+        match account {
+            JsonAccount::Current(account) => account,
+            JsonAccount::Legacy(legacy) => {
+                // The balances of older databases are all in the default currency
+                let mut account = ClientAccount::new(legacy.client);
+                account.balances.insert(
+                    Currency::default(),
+                    CurrencyBalance {
+                        available: legacy.available,
+                        held: legacy.held,
+                        total: legacy.total,
+                    },
+                );
+                if legacy.locked {
+                    account.status = AccountStatus::Locked;
+                }
+                account
+            }
+        }
+    }
+}
+
+fn decode_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| {
+        PaymentError::InternalError(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Deserialization error: {}", e),
+        )))
+    })
+}
+
+fn corrupted(what: &str) -> PaymentError {
+    PaymentError::InternalError(Box::new(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Corrupted value: {}", what),
+    )))
+}
+
+/// Appends the fields of a value after the header.
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn new() -> Self {
+        Self {
+            bytes: vec![FORMAT_MAGIC, FORMAT_VERSION],
+        }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Writes the length of a collection; there are far fewer currencies than `u16::MAX`.
+    fn len(&mut self, len: usize) {
+        self.u16(len as u16);
+    }
+
+    fn decimal(&mut self, value: Decimal) {
+        self.bytes.extend_from_slice(&value.serialize());
+    }
+
+    fn balance(&mut self, value: Balance) {
+        self.decimal(value.0);
+    }
+
+    fn currency(&mut self, currency: Currency) {
+        self.bytes.extend_from_slice(&currency.to_bytes());
+    }
+
+    fn balances(&mut self, balances: &BTreeMap<Currency, Balance>) {
+        self.len(balances.len());
+        for (currency, balance) in balances {
+            self.currency(*currency);
+            self.balance(*balance);
+        }
+    }
+}
+
+/// Reads the fields of a value after the header.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    /// Checks the header of a binary value, returning its format version with a decoder of its
+    /// fields, or `None` for a value without one.
+    fn binary(bytes: &'a [u8]) -> Result<Option<(u8, Self)>> {
+        match bytes {
+            [
+                FORMAT_MAGIC,
+                version @ (LEGACY_FORMAT_VERSION | FORMAT_VERSION),
+                rest @ ..,
+            ] => Ok(Some((*version, Self { bytes: rest }))),
+            [FORMAT_MAGIC, version, ..] => {
+                Err(PaymentError::InternalError(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Unsupported value format version {}", version),
+                ))))
+            }
+            [FORMAT_MAGIC] => Err(corrupted("missing format version")),
+            _ => Ok(None),
+        }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let (head, rest) = self
+            .bytes
+            .split_first_chunk::<N>()
+            .ok_or_else(|| corrupted("truncated"))?;
+        self.bytes = rest;
+        Ok(*head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take()?))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        Ok(self.u16()?.into())
+    }
+
+    fn decimal(&mut self) -> Result<Decimal> {
+        Ok(Decimal::deserialize(self.take()?))
+    }
+
+    fn balance(&mut self) -> Result<Balance> {
+        Ok(Balance::new(self.decimal()?))
+    }
+
+    fn currency(&mut self) -> Result<Currency> {
+        Currency::from_bytes(self.take()?)
+    }
+
+    fn balances(&mut self) -> Result<BTreeMap<Currency, Balance>> {
+        let mut balances = BTreeMap::new();
+        for _ in 0..self.len()? {
+            let currency = self.currency()?;
+            balances.insert(currency, self.balance()?);
+        }
+        Ok(balances)
+    }
+
+    /// Fails if bytes are left after the last field.
+    fn finish(self) -> Result<()> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(corrupted("trailing bytes"))
+        }
+    }
+}
+
+fn type_code(r#type: TransactionType) -> u8 {
+    match r#type {
+        TransactionType::Deposit => 0,
+        TransactionType::Withdrawal => 1,
+        TransactionType::Dispute => 2,
+        TransactionType::Resolve => 3,
+        TransactionType::Chargeback => 4,
+        TransactionType::Transfer => 5,
+        TransactionType::Freeze => 6,
+        TransactionType::Unlock => 7,
+        TransactionType::Close => 8,
+        TransactionType::SetOverdraft => 9,
+        TransactionType::SetReserve => 10,
+    }
+}
+
+fn type_from_code(code: u8) -> Result<TransactionType> {
+    Ok(match code {
+        0 => TransactionType::Deposit,
+        1 => TransactionType::Withdrawal,
+        2 => TransactionType::Dispute,
+        3 => TransactionType::Resolve,
+        4 => TransactionType::Chargeback,
+        5 => TransactionType::Transfer,
+        6 => TransactionType::Freeze,
+        7 => TransactionType::Unlock,
+        8 => TransactionType::Close,
+        9 => TransactionType::SetOverdraft,
+        10 => TransactionType::SetReserve,
+        _ => return Err(corrupted("unknown transaction type")),
+    })
+}
+
+fn dispute_status_code(status: DisputeStatus) -> u8 {
+    match status {
+        DisputeStatus::Open => 0,
+        DisputeStatus::Resolved => 1,
+        DisputeStatus::Chargebacked => 2,
+    }
+}
+
+fn dispute_status_from_code(code: u8) -> Result<DisputeStatus> {
+    Ok(match code {
+        0 => DisputeStatus::Open,
+        1 => DisputeStatus::Resolved,
+        2 => DisputeStatus::Chargebacked,
+        _ => return Err(corrupted("unknown dispute status")),
+    })
+}
+
+fn legacy_status_from_code(code: u8) -> Result<LegacyDisputeStatus> {
+    Ok(match code {
+        0 => LegacyDisputeStatus::None,
+        1 => LegacyDisputeStatus::Disputed,
+        2 => LegacyDisputeStatus::Resolved,
+        3 => LegacyDisputeStatus::Chargebacked,
+        _ => return Err(corrupted("unknown dispute status")),
+    })
+}
+
+fn status_code(status: AccountStatus) -> u8 {
+    match status {
+        AccountStatus::Active => 0,
+        AccountStatus::Frozen => 1,
+        AccountStatus::Locked => 2,
+        AccountStatus::Closed => 3,
+    }
+}
+
+fn status_from_code(code: u8) -> Result<AccountStatus> {
+    Ok(match code {
+        0 => AccountStatus::Active,
+        1 => AccountStatus::Frozen,
+        2 => AccountStatus::Locked,
+        3 => AccountStatus::Closed,
+        _ => return Err(corrupted("unknown account status")),
+    })
+}
+
+fn lock_cause_code(cause: LockCause) -> u8 {
+    match cause {
+        LockCause::Chargeback => 0,
+        LockCause::OpenDisputes => 1,
+        LockCause::DisputedValue => 2,
+    }
+}
+
+fn lock_cause_from_code(code: u8) -> Result<LockCause> {
+    Ok(match code {
+        0 => LockCause::Chargeback,
+        1 => LockCause::OpenDisputes,
+        2 => LockCause::DisputedValue,
+        _ => return Err(corrupted("unknown lock cause")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn deposit() -> Transaction {
+        Transaction {
+            r#type: TransactionType::Deposit,
+            client: 1,
+            tx: 7,
+            amount: Some(dec!(12.3456).try_into().unwrap()),
+            currency: Currency::new("EUR").unwrap(),
+            destination: None,
+        }
+    }
+
+    #[test]
+    fn test_transaction_roundtrip() {
+        let tx = deposit();
+        let bytes = encode_transaction(&tx);
+        assert_eq!(&bytes[..2], &[FORMAT_MAGIC, FORMAT_VERSION]);
+        // Header, type, client, tx, flags, currency and amount
+        assert_eq!(bytes.len(), 2 + 1 + 2 + 4 + 1 + 3 + 16);
+        assert_eq!(decode_transaction(&bytes).unwrap(), tx);
+
+        let transfer = Transaction {
+            r#type: TransactionType::Transfer,
+            destination: Some(2),
+            ..deposit()
+        };
+        assert_eq!(
+            decode_transaction(&encode_transaction(&transfer)).unwrap(),
+            transfer
+        );
+    }
+
+    #[test]
+    fn test_disputes_roundtrip() {
+        let mut record = DisputeRecord::new(7);
+        assert_eq!(decode_disputes(&encode_disputes(&record)).unwrap(), record);
+
+        record.disputes.push(Dispute {
+            amount: Balance::new(dec!(3)),
+            shortfall: Balance::ZERO,
+            status: DisputeStatus::Chargebacked,
+        });
+        record
+            .disputes
+            .push(Dispute::open(Balance::new(dec!(2)), Balance::new(dec!(1))));
+        assert_eq!(decode_disputes(&encode_disputes(&record)).unwrap(), record);
+    }
+
+    #[test]
+    fn test_splits_legacy_dispute_state() {
+        // A transfer (2 open, 5 disputed in total, 1 owed) in the first binary format
+        let mut bytes = vec![FORMAT_MAGIC, LEGACY_FORMAT_VERSION, 5, 0, 1, 0, 0, 0, 7];
+        bytes.push(TX_AMOUNT | TX_DESTINATION | TX_DISPUTED);
+        bytes.push(1);
+        bytes.extend_from_slice(b"EUR");
+        bytes.extend_from_slice(&dec!(12.3456).serialize());
+        bytes.extend_from_slice(&2u16.to_be_bytes());
+        for amount in [dec!(2), dec!(5), dec!(1)] {
+            bytes.extend_from_slice(&amount.serialize());
+        }
+        let transfer = Transaction {
+            r#type: TransactionType::Transfer,
+            destination: Some(2),
+            ..deposit()
+        };
+        let disputes = DisputeRecord {
+            tx: 7,
+            disputes: vec![
+                Dispute {
+                    amount: Balance::new(dec!(3)),
+                    shortfall: Balance::ZERO,
+                    status: DisputeStatus::Resolved,
+                },
+                Dispute::open(Balance::new(dec!(2)), Balance::new(dec!(1))),
+            ],
+        };
+        assert_eq!(
+            decode_legacy_transaction(&bytes).unwrap(),
+            (transfer.clone(), Some(disputes.clone()))
+        );
+        // Current records never hold dispute state
+        assert!(decode_transaction(&bytes).is_err());
+
+        let json = br#"{"type":"transfer","client":1,"tx":7,"amount":"12.3456","currency":"EUR",
+            "destination":2,"dispute_status":"Disputed","open_disputed":"2","total_disputed":"5",
+            "shortfall":"1"}"#;
+        assert_eq!(
+            decode_legacy_transaction(json).unwrap(),
+            (transfer, Some(disputes))
+        );
+    }
+
+    #[test]
+    fn test_splits_baseline_dispute_status() {
+        // Records written before partial disputes have a status but no disputed amounts
+        let deposit = Transaction {
+            currency: Currency::default(),
+            ..deposit()
+        };
+        let json = |status: &str| {
+            format!(
+                r#"{{"type":"deposit","client":1,"tx":7,"amount":"12.3456","dispute_status":"{}"}}"#,
+                status
+            )
+        };
+        let amount = Balance::new(dec!(12.3456));
+        let settled = |status| DisputeRecord {
+            tx: 7,
+            disputes: vec![Dispute {
+                amount,
+                shortfall: Balance::ZERO,
+                status,
+            }],
+        };
+        let cases = [
+            ("None", None),
+            (
+                "Disputed",
+                Some(DisputeRecord {
+                    tx: 7,
+                    disputes: vec![Dispute::open(amount, Balance::ZERO)],
+                }),
+            ),
+            ("Resolved", Some(settled(DisputeStatus::Resolved))),
+            ("Chargebacked", Some(settled(DisputeStatus::Chargebacked))),
+        ];
+        for (status, disputes) in cases {
+            assert_eq!(
+                decode_legacy_transaction(json(status).as_bytes()).unwrap(),
+                (deposit.clone(), disputes)
+            );
+        }
+    }
+
+    #[test]
+    fn test_account_roundtrip() {
+        let mut account = ClientAccount::new(3);
+        let eur = Currency::new("EUR").unwrap();
+        account.deposit(eur, Balance::new(dec!(10.5))).unwrap();
+        // Negative balances, such as overdrawn ones, keep their sign
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(-0.0001));
+        assert_eq!(decode_account(&encode_account(&account)).unwrap(), account);
+
+        account.limits.overdraft = Balance::new(dec!(50));
+        account.add_receivable(eur, Balance::new(dec!(3))).unwrap();
+        account
+            .disputes
+            .record_dispute(eur, Balance::new(dec!(4)), true)
+            .unwrap();
+        account.lock(LockReason {
+            tx: 9,
+            r#type: TransactionType::Chargeback,
+            cause: LockCause::Chargeback,
+            position: 12,
+            timestamp: Some(1700000000),
+        });
+        assert_eq!(decode_account(&encode_account(&account)).unwrap(), account);
+    }
+
+    #[test]
+    fn test_decodes_json_values() {
+        let tx = deposit();
+        let json = serde_json::to_vec(&tx).unwrap();
+        assert_eq!(decode_transaction(&json).unwrap(), tx);
+
+        let account = ClientAccount::new(1);
+        let json = serde_json::to_vec(&account).unwrap();
+        assert_eq!(decode_account(&json).unwrap(), account);
+    }
+
+    #[test]
+    fn test_decodes_baseline_json_account() {
+        let json = br#"{"client":1,"available":"1","held":"0.5","total":"1.5","locked":true}"#;
+        let mut account = ClientAccount::new(1);
+        account.balances.insert(
+            Currency::default(),
+            CurrencyBalance {
+                available: Balance::new(dec!(1)),
+                held: Balance::new(dec!(0.5)),
+                total: Balance::new(dec!(1.5)),
+            },
+        );
+        account.status = AccountStatus::Locked;
+        assert_eq!(decode_account(json).unwrap(), account);
+    }
+
+    #[test]
+    fn test_rejects_unknown_or_corrupted_values() {
+        let mut bytes = encode_transaction(&deposit());
+        bytes[1] = FORMAT_VERSION + 1;
+        assert!(decode_transaction(&bytes).is_err());
+
+        let bytes = encode_transaction(&deposit());
+        assert!(decode_transaction(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(decode_transaction(&trailing).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Memory-compact map keyed by `u32` transaction ids, for the in-memory stores.
+//!
+//! Transaction ids are mostly allocated in increasing order, so the map splits the id space
+//! in fixed ranges, only allocates the ranges in use, and keeps the sparse ranges in sorted
+//! arrays (see also [`crate::domain::ids::IdBitmap`], the set of ids built the same way).
+
+use std::collections::BTreeMap;
+
+/// Number of bits of an id giving its slot within a page of [`PagedMap`].
+const PAGE_BITS: u32 = 12;
+/// Number of slots of a page of [`PagedMap`].
+const PAGE_SLOTS: usize = 1 << PAGE_BITS;
+/// Number of values above which a page is stored as a full array of slots rather than sorted
+/// arrays of slots and values.
+const SPARSE_MAX_VALUES: usize = PAGE_SLOTS / 8;
+
+/// A map from `u32` ids to small `Copy` values, grouped in pages of 4096 consecutive ids.
+///
+/// A page is allocated on the first id of its range. The values of a sparse page are kept in
+/// a sorted array, along with their slot (2 bytes each); a page of more than 512 values
+/// switches to a full array holding a value per id plus a bit telling which slots are
+/// occupied, without any per-entry overhead. Dense ids thus cost `size_of::<T>()` each, while
+/// isolated ids, random or strided, cost little more than their value and slot.
+#[derive(Debug, Clone)]
+pub struct PagedMap<T> {
+    pages: BTreeMap<u32, Page<T>>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Page<T> {
+    Sparse { slots: Vec<u16>, values: Vec<T> },
+    Dense(Box<DensePage<T>>),
+}
+
+#[derive(Debug, Clone)]
+struct DensePage<T> {
+    occupied: [u64; PAGE_SLOTS / 64],
+    count: usize,
+    slots: Box<[T]>,
+}
+
+impl<T> Default for PagedMap<T> {
+    fn default() -> Self {
+        Self {
+            pages: BTreeMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T: Copy + Default> PagedMap<T> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of an id.
+    pub fn get(&self, id: u32) -> Option<&T> {
+        let (page, slot) = locate(id);
+        match self.pages.get(&page)? {
+            Page::Sparse { slots, values } => {
+                let index = slots.binary_search(&slot).ok()?;
+                Some(&values[index])
+            }
+            Page::Dense(page) => {
+                let slot = usize::from(slot);
+                page.is_occupied(slot).then(|| &page.slots[slot])
+            }
+        }
+    }
+
+    /// Sets the value of an id, returning its previous value.
+    pub fn insert(&mut self, id: u32, value: T) -> Option<T> {
+        let (page, slot) = locate(id);
+        let page = self.pages.entry(page).or_insert_with(|| Page::Sparse {
+            slots: Vec::new(),
+            values: Vec::new(),
+        });
+        let previous = match page {
+            Page::Sparse { slots, values } => match slots.binary_search(&slot) {
+                Ok(index) => Some(std::mem::replace(&mut values[index], value)),
+                Err(index) => {
+                    insert_sparse(slots, index, slot);
+                    insert_sparse(values, index, value);
+                    if values.len() > SPARSE_MAX_VALUES {
+                        *page = Page::Dense(DensePage::from_sparse(slots, values));
+                    }
+                    None
+                }
+            },
+            Page::Dense(page) => {
+                let slot = usize::from(slot);
+                let previous = page.is_occupied(slot).then_some(page.slots[slot]);
+                if previous.is_none() {
+                    page.occupied[slot / 64] |= 1 << (slot % 64);
+                    page.count += 1;
+                }
+                page.slots[slot] = value;
+                previous
+            }
+        };
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Removes the value of an id, freeing its page once empty.
+    pub fn remove(&mut self, id: u32) -> Option<T> {
+        let (index, slot) = locate(id);
+        let page = self.pages.get_mut(&index)?;
+        let (value, empty) = match page {
+            Page::Sparse { slots, values } => {
+                let position = slots.binary_search(&slot).ok()?;
+                slots.remove(position);
+                (values.remove(position), values.is_empty())
+            }
+            Page::Dense(page) => {
+                let slot = usize::from(slot);
+                if !page.is_occupied(slot) {
+                    return None;
+                }
+                page.occupied[slot / 64] &= !(1 << (slot % 64));
+                page.count -= 1;
+                (page.slots[slot], page.count == 0)
+            }
+        };
+        self.len -= 1;
+        if empty {
+            self.pages.remove(&index);
+        }
+        Some(value)
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the ids and values, by increasing id.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &T)> {
+        self.pages.iter().flat_map(|(&index, page)| {
+            let entries: Box<dyn Iterator<Item = (usize, &T)>> = match page {
+                Page::Sparse { slots, values } => Box::new(
+                    slots
+                        .iter()
+                        .map(|&slot| usize::from(slot))
+                        .zip(values.iter()),
+                ),
+                Page::Dense(page) => Box::new(
+                    (0..PAGE_SLOTS)
+                        .filter(|slot| page.is_occupied(*slot))
+                        .map(|slot| (slot, &page.slots[slot])),
+                ),
+            };
+            entries.map(move |(slot, value)| ((index << PAGE_BITS) | slot as u32, value))
+        })
+    }
+}
+
+impl<T: Copy + Default> DensePage<T> {
+    fn from_sparse(slots: &[u16], values: &[T]) -> Box<Self> {
+        let mut page = Box::new(Self {
+            occupied: [0; PAGE_SLOTS / 64],
+            count: values.len(),
+            slots: vec![T::default(); PAGE_SLOTS].into_boxed_slice(),
+        });
+        for (&slot, &value) in slots.iter().zip(values) {
+            let slot = usize::from(slot);
+            page.occupied[slot / 64] |= 1 << (slot % 64);
+            page.slots[slot] = value;
+        }
+        page
+    }
+
+    fn is_occupied(&self, slot: usize) -> bool {
+        self.occupied[slot / 64] & (1 << (slot % 64)) != 0
+    }
+}
+
+/// Inserts into an array of a sparse page, growing it by half rather than doubling it, since
+/// most sparse pages only ever hold a few values.
+fn insert_sparse<V>(array: &mut Vec<V>, index: usize, value: V) {
+    if array.len() == array.capacity() {
+        array.reserve_exact(array.len() / 2 + 1);
+    }
+    array.insert(index, value);
+}
+
+fn locate(id: u32) -> (u32, u16) {
+    (id >> PAGE_BITS, (id as usize & (PAGE_SLOTS - 1)) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paged_map() {
+        let mut map = PagedMap::new();
+        assert_eq!(map.insert(3, 30u64), None);
+        assert_eq!(map.insert(3, 31), Some(30));
+        assert_eq!(map.insert(u32::MAX, 1), None);
+        assert_eq!(map.insert(PAGE_SLOTS as u32, 2), None);
+        assert_eq!(map.get(3), Some(&31));
+        assert_eq!(map.get(4), None);
+        assert_eq!(map.len(), 3);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(3, &31), (PAGE_SLOTS as u32, &2), (u32::MAX, &1)]
+        );
+
+        // Removing the last value of a page frees it
+        assert_eq!(map.remove(PAGE_SLOTS as u32), Some(2));
+        assert_eq!(map.remove(PAGE_SLOTS as u32), None);
+        assert!(!map.pages.contains_key(&1));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_paged_map_dense_page() {
+        let mut map = PagedMap::new();
+        // Every other slot of the first page, in decreasing order
+        for id in (0..PAGE_SLOTS as u32).step_by(2).rev() {
+            assert_eq!(map.insert(id, u64::from(id)), None);
+        }
+        assert!(matches!(map.pages[&0], Page::Dense(_)));
+        assert_eq!(map.len(), PAGE_SLOTS / 2);
+        assert_eq!(map.get(10), Some(&10));
+        assert_eq!(map.get(11), None);
+        assert_eq!(map.insert(10, 0), Some(10));
+        assert_eq!(map.iter().nth(5), Some((10, &0)));
+
+        // An isolated id stays in a sparse page
+        map.insert(5 * PAGE_SLOTS as u32 + 1, 1);
+        assert!(matches!(map.pages[&5], Page::Sparse { .. }));
+
+        for id in (0..PAGE_SLOTS as u32).step_by(2) {
+            assert!(map.remove(id).is_some());
+        }
+        assert!(!map.pages.contains_key(&0));
+        assert_eq!(map.len(), 1);
+    }
+}
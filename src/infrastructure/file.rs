@@ -0,0 +1,414 @@
+use crate::domain::account::{AccountLimits, Balance};
+use crate::domain::checkpoint::{FINGERPRINT_HEAD_BYTES, Fingerprint};
+use crate::domain::fee::FeeSchedule;
+use crate::domain::ports::SnapshotStore;
+use crate::domain::risk::{RiskRuleBox, RuleConfig};
+use crate::domain::snapshot::Snapshot;
+use crate::error::{PaymentError, Result};
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::{Read, SeekFrom};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// A snapshot store keeping the latest snapshot in a JSON file.
+///
+/// Lets the in-memory stores survive restarts. A full snapshot is first written to a temporary
+/// file next to the target, then renamed over it, so a crash while saving never leaves a
+/// truncated snapshot behind. Incremental snapshots are appended to the file, one JSON
+/// document per line; one cut short by a crash is ignored, the file then describing the state
+/// of the previous snapshot.
+#[derive(Clone)]
+pub struct FileSnapshotStore {
+    path: PathBuf,
+}
+
+impl FileSnapshotStore {
+    /// Creates a snapshot store writing to the given file.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl FileSnapshotStore {
+    /// Appends an incremental snapshot to the file, first dropping the end of one cut short.
+    async fn append(&self, bytes: &[u8]) -> Result<()> {
+        let mut file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.path)
+            .await?;
+        let mut last = [b'\n'];
+        if file.metadata().await?.len() > 0 {
+            file.seek(SeekFrom::End(-1)).await?;
+            file.read_exact(&mut last).await?;
+        }
+        if last[0] != b'\n' {
+            let content = tokio::fs::read(&self.path).await?;
+            match content.iter().rposition(|&byte| byte == b'\n') {
+                Some(end) => file.set_len(end as u64 + 1).await?,
+                // A lone full snapshot saved without a line terminator
+                None => file.write_all(b"\n").await?,
+            }
+        }
+        file.seek(SeekFrom::End(0)).await?;
+        file.write_all(bytes).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for FileSnapshotStore {
+    async fn save(&self, snapshot: &Snapshot) -> Result<()> {
+        let mut bytes = serde_json::to_vec(snapshot).map_err(|e| {
+            PaymentError::InternalError(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Serialization error: {}", e),
+            )))
+        })?;
+        bytes.push(b'\n');
+
+        if snapshot.incremental {
+            return self.append(&bytes).await;
+        }
+
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        tokio::fs::write(&temp_path, bytes).await?;
+        tokio::fs::rename(&temp_path, &self.path).await?;
+
+        Ok(())
+    }
+
+    async fn latest(&self) -> Result<Option<Snapshot>> {
+        let bytes = match tokio::fs::read(&self.path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut latest: Option<Snapshot> = None;
+        for result in serde_json::Deserializer::from_slice(&bytes).into_iter::<Snapshot>() {
+            match (result, latest.as_mut()) {
+                (Ok(snapshot), None) => latest = Some(snapshot),
+                (Ok(increment), Some(snapshot)) => snapshot.extend(increment),
+                // An increment whose append was cut short
+                (Err(e), Some(_)) if e.is_eof() => break,
+                (Err(e), _) => {
+                    return Err(PaymentError::InternalError(Box::new(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Deserialization error: {}", e),
+                    ))));
+                }
+            }
+        }
+        Ok(latest)
+    }
+}
+
+/// Fingerprints an input file from its size and its first bytes, without reading it whole.
+pub fn fingerprint_file(path: &Path) -> Result<Fingerprint> {
+    let file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut head = Vec::with_capacity(FINGERPRINT_HEAD_BYTES);
+    file.take(FINGERPRINT_HEAD_BYTES as u64)
+        .read_to_end(&mut head)?;
+    Ok(Fingerprint::new(size, &head))
+}
+
+/// The layout of a risk rules file: a TOML array of `[[rules]]` tables.
+#[derive(Deserialize)]
+struct RiskRulesFile {
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+}
+
+/// Loads the built-in risk rules configured in a TOML file, in the order they are listed.
+///
+/// Each `[[rules]]` table names a rule in its `type` key, for example:
+///
+/// ```toml
+/// [[rules]]
+/// type = "withdrawal_velocity"
+/// max = 3
+/// window = 100
+/// action = "flag"
+/// ```
+pub fn load_risk_rules(path: &Path) -> Result<Vec<RiskRuleBox>> {
+    let content = std::fs::read_to_string(path)?;
+    let file: RiskRulesFile = toml::from_str(&content).map_err(|e| {
+        PaymentError::ValidationError(format!("Invalid risk rules file {}: {}", path.display(), e))
+    })?;
+    Ok(file.rules.into_iter().map(RuleConfig::into_rule).collect())
+}
+
+/// Loads a fee schedule from a TOML file, with one optional table per charged transaction
+/// type (`withdrawal`, `transfer` and `chargeback`), for example:
+///
+/// ```toml
+/// [withdrawal]
+/// type = "flat"
+/// amount = "0.5"
+///
+/// [chargeback]
+/// type = "tiered"
+/// tiers = [{ up_to = "100", flat = "5" }, { percent = "2" }]
+/// ```
+pub fn load_fee_schedule(path: &Path) -> Result<FeeSchedule> {
+    let content = std::fs::read_to_string(path)?;
+    let schedule: FeeSchedule = toml::from_str(&content).map_err(|e| {
+        PaymentError::ValidationError(format!("Invalid fee schedule {}: {}", path.display(), e))
+    })?;
+    schedule.validate()?;
+    Ok(schedule)
+}
+
+/// The limits of one client, as listed in a limits file.
+#[derive(Deserialize)]
+struct LimitsRow {
+    client: u16,
+    overdraft: Option<Decimal>,
+    reserve: Option<Decimal>,
+}
+
+/// The layout of a TOML limits file: an array of `[[limits]]` tables.
+#[derive(Deserialize)]
+struct LimitsFile {
+    #[serde(default)]
+    limits: Vec<LimitsRow>,
+}
+
+/// Loads per-client credit limits from a file.
+///
+/// A `.toml` file lists `[[limits]]` tables, any other file is read as a CSV with a
+/// `client, overdraft, reserve` header. Missing limits default to zero.
+pub fn load_limits(path: &Path) -> Result<BTreeMap<u16, AccountLimits>> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        PaymentError::ValidationError(format!("Invalid limits file {}: {}", path.display(), e))
+    };
+
+    let rows: Vec<LimitsRow> = if path.extension().is_some_and(|ext| ext == "toml") {
+        let content = std::fs::read_to_string(path)?;
+        let file: LimitsFile = toml::from_str(&content).map_err(|e| invalid(&e))?;
+        file.limits
+    } else {
+        csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)?
+            .into_deserialize()
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| invalid(&e))?
+    };
+
+    let mut limits = BTreeMap::new();
+    for row in rows {
+        let overdraft = Balance::new(row.overdraft.unwrap_or_default());
+        let reserve = Balance::new(row.reserve.unwrap_or_default());
+        if overdraft < Balance::ZERO || reserve < Balance::ZERO {
+            return Err(invalid(&format!(
+                "negative limit for client {}",
+                row.client
+            )));
+        }
+        limits.insert(row.client, AccountLimits { overdraft, reserve });
+    }
+    Ok(limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::account::ClientAccount;
+    use crate::domain::fee::{FeeAccount, FeeRule};
+    use crate::domain::transaction::TransactionType;
+    use rust_decimal_macros::dec;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_file_snapshot_store() {
+        let dir = tempdir().unwrap();
+        let store = FileSnapshotStore::new(dir.path().join("snapshot.json"));
+        assert!(store.latest().await.unwrap().is_none());
+
+        let snapshot = Snapshot {
+            position: 7,
+            incremental: false,
+            accounts: vec![ClientAccount::new(1)],
+            transactions: Vec::new(),
+            disputes: Vec::new(),
+            transaction_ids: [7].into_iter().collect(),
+            fees: FeeAccount::default(),
+        };
+        store.save(&Snapshot::default()).await.unwrap();
+        store.save(&snapshot).await.unwrap();
+
+        let reopened = FileSnapshotStore::new(dir.path().join("snapshot.json"));
+        assert_eq!(reopened.latest().await.unwrap(), Some(snapshot));
+    }
+
+    #[tokio::test]
+    async fn test_file_snapshot_store_increments() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        let store = FileSnapshotStore::new(&path);
+        let increment = |position, client| Snapshot {
+            position,
+            incremental: true,
+            accounts: vec![ClientAccount::new(client)],
+            ..Snapshot::default()
+        };
+
+        store
+            .save(&Snapshot {
+                position: 2,
+                accounts: vec![ClientAccount::new(1)],
+                ..Snapshot::default()
+            })
+            .await
+            .unwrap();
+        store.save(&increment(4, 2)).await.unwrap();
+        store.save(&increment(6, 3)).await.unwrap();
+        let latest = store.latest().await.unwrap().unwrap();
+        assert_eq!(latest.position, 6);
+        assert_eq!(latest.accounts.len(), 3);
+
+        // An increment cut short by a crash is ignored
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        std::io::Write::write_all(&mut file, b"{\"position\":8,\"incre").unwrap();
+        assert_eq!(store.latest().await.unwrap().unwrap().position, 6);
+        store.save(&increment(8, 4)).await.unwrap();
+        let latest = store.latest().await.unwrap().unwrap();
+        assert_eq!(latest.position, 8);
+        assert_eq!(latest.accounts.len(), 4);
+
+        // A full snapshot replaces the increments
+        store.save(&Snapshot::default()).await.unwrap();
+        assert_eq!(store.latest().await.unwrap(), Some(Snapshot::default()));
+    }
+
+    #[test]
+    fn test_load_risk_rules() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            "[[rules]]\n\
+             type = \"max_withdrawal_amount\"\n\
+             limit = \"100\"\n\
+             \n\
+             [[rules]]\n\
+             type = \"deposit_then_withdrawal\"\n\
+             window = 1\n\
+             action = \"flag\"\n",
+        )
+        .unwrap();
+
+        let rules = load_risk_rules(&path).unwrap();
+        let names: Vec<_> = rules.iter().map(|rule| rule.name()).collect();
+        assert_eq!(
+            names,
+            vec!["max_withdrawal_amount", "deposit_then_withdrawal"]
+        );
+
+        std::fs::write(&path, "[[rules]]\ntype = \"unknown\"\n").unwrap();
+        assert!(matches!(
+            load_risk_rules(&path),
+            Err(PaymentError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_limits() {
+        let dir = tempdir().unwrap();
+        let csv_path = dir.path().join("limits.csv");
+        std::fs::write(&csv_path, "client, overdraft, reserve\n1, 100, \n2, , 50\n").unwrap();
+        let toml_path = dir.path().join("limits.toml");
+        std::fs::write(
+            &toml_path,
+            "[[limits]]\nclient = 1\noverdraft = 100\n\n[[limits]]\nclient = 2\nreserve = 50\n",
+        )
+        .unwrap();
+
+        let expected = BTreeMap::from([
+            (
+                1,
+                AccountLimits {
+                    overdraft: Balance::new(Decimal::from(100)),
+                    reserve: Balance::ZERO,
+                },
+            ),
+            (
+                2,
+                AccountLimits {
+                    overdraft: Balance::ZERO,
+                    reserve: Balance::new(Decimal::from(50)),
+                },
+            ),
+        ]);
+        assert_eq!(load_limits(&csv_path).unwrap(), expected);
+        assert_eq!(load_limits(&toml_path).unwrap(), expected);
+
+        std::fs::write(&csv_path, "client, overdraft, reserve\n1, -1, \n").unwrap();
+        assert!(matches!(
+            load_limits(&csv_path),
+            Err(PaymentError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_fee_schedule() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fees.toml");
+        std::fs::write(
+            &path,
+            "[withdrawal]\n\
+             type = \"flat\"\n\
+             amount = \"0.5\"\n\
+             \n\
+             [chargeback]\n\
+             type = \"tiered\"\n\
+             tiers = [{ up_to = \"100\", flat = \"5\" }, { percent = \"2\" }]\n",
+        )
+        .unwrap();
+
+        let schedule = load_fee_schedule(&path).unwrap();
+        assert_eq!(
+            schedule.withdrawal,
+            Some(FeeRule::Flat { amount: dec!(0.5) })
+        );
+        assert!(schedule.transfer.is_none());
+        assert_eq!(
+            schedule
+                .fee(TransactionType::Chargeback, Balance::new(dec!(500)))
+                .unwrap(),
+            Balance::new(dec!(10))
+        );
+
+        std::fs::write(&path, "[deposit]\ntype = \"flat\"\namount = \"1\"\n").unwrap();
+        assert!(matches!(
+            load_fee_schedule(&path),
+            Err(PaymentError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_fingerprint_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("input.csv");
+        std::fs::write(&path, "type,client,tx,amount\n").unwrap();
+
+        let fingerprint = fingerprint_file(&path).unwrap();
+        assert_eq!(
+            fingerprint,
+            Fingerprint::new(22, b"type,client,tx,amount\n")
+        );
+        std::fs::write(&path, "type,client,tx,amount\ndeposit,1,1,1\n").unwrap();
+        assert_ne!(fingerprint_file(&path).unwrap(), fingerprint);
+    }
+}
@@ -1,20 +1,117 @@
 use crate::domain::account::{Amount, ClientAccount};
-use crate::domain::ports::{AccountStore, TransactionStore};
-use crate::domain::transaction::{DisputeStatus, Transaction, TransactionType};
-use crate::error::Result;
+use crate::domain::checkpoint::Checkpoint;
+use crate::domain::currency::Currency;
+use crate::domain::dispute::DisputeRecord;
+use crate::domain::event::EventRecord;
+use crate::domain::fee::FeeAccount;
+use crate::domain::ids::IdBitmap;
+use crate::domain::ledger::JournalEntry;
+use crate::domain::ports::{
+    AccountStore, CheckpointStore, EventStore, FeeStore, JournalStore, RiskStore, TransactionStore,
+    UnitOfWork, UnitOfWorkStore,
+};
+use crate::domain::risk::RiskState;
+use crate::domain::transaction::{Transaction, TransactionType};
+use crate::error::{PaymentError, Result};
+use crate::infrastructure::compact::PagedMap;
 use async_trait::async_trait;
-use std::collections::{HashMap, HashSet};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, RwLockWriteGuard};
 
 /// A minimalist representation of a transaction for in-memory storage.
 ///
 /// Reduces RAM footprint by only storing fields essential for the dispute lifecycle.
 #[derive(Clone, Copy)]
 pub struct LeanTransaction {
+    pub r#type: TransactionType,
     pub client_id: u16,
     pub amount: Amount,
-    pub dispute_status: DisputeStatus,
+    pub currency: Currency,
+}
+
+/// Type bit of a `PackedRecord` (withdrawal when set, deposit otherwise).
+const PACKED_WITHDRAWAL: u8 = 1;
+
+/// A `LeanTransaction` packed in 16 bytes, by storing its amount as an `i64` mantissa and a
+/// scale. Amounts with a wider mantissa are kept as a `LeanTransaction`.
+#[derive(Clone, Copy, Default)]
+struct PackedRecord {
+    mantissa: i64,
+    client_id: u16,
+    currency: Currency,
+    scale: u8,
+    /// The type bit.
+    flags: u8,
+}
+
+impl PackedRecord {
+    fn pack(record: &LeanTransaction) -> Option<Self> {
+        let r#type = match record.r#type {
+            TransactionType::Deposit => 0,
+            TransactionType::Withdrawal => PACKED_WITHDRAWAL,
+            _ => return None,
+        };
+        let amount = Decimal::from(record.amount);
+        Some(Self {
+            mantissa: i64::try_from(amount.mantissa()).ok()?,
+            client_id: record.client_id,
+            currency: record.currency,
+            scale: amount.scale() as u8,
+            flags: r#type,
+        })
+    }
+
+    fn unpack(self) -> Result<LeanTransaction> {
+        Ok(LeanTransaction {
+            r#type: if self.flags & PACKED_WITHDRAWAL != 0 {
+                TransactionType::Withdrawal
+            } else {
+                TransactionType::Deposit
+            },
+            client_id: self.client_id,
+            amount: Amount::new(Decimal::new(self.mantissa, self.scale.into()))?,
+            currency: self.currency,
+        })
+    }
+}
+
+/// The records of an `InMemoryTransactionStore`, packed whenever their amount allows it.
+#[derive(Default)]
+struct TransactionRecords {
+    packed: PagedMap<PackedRecord>,
+    unpacked: HashMap<u32, LeanTransaction>,
+}
+
+impl TransactionRecords {
+    fn insert(&mut self, tx_id: u32, record: LeanTransaction) {
+        match PackedRecord::pack(&record) {
+            Some(packed) => {
+                self.packed.insert(tx_id, packed);
+                self.unpacked.remove(&tx_id);
+            }
+            None => {
+                self.packed.remove(tx_id);
+                self.unpacked.insert(tx_id, record);
+            }
+        }
+    }
+
+    fn get(&self, tx_id: u32) -> Result<Option<LeanTransaction>> {
+        match self.packed.get(tx_id) {
+            Some(packed) => packed.unpack().map(Some),
+            None => Ok(self.unpacked.get(&tx_id).copied()),
+        }
+    }
+
+    fn ids(&self) -> Vec<u32> {
+        self.packed
+            .iter()
+            .map(|(tx_id, _)| tx_id)
+            .chain(self.unpacked.keys().copied())
+            .collect()
+    }
 }
 
 /// A thread-safe in-memory store for client accounts.
@@ -41,6 +138,14 @@ impl AccountStore for InMemoryAccountStore {
         Ok(())
     }
 
+    async fn store_all(&self, batch: Vec<ClientAccount>) -> Result<()> {
+        let mut accounts = self.accounts.write().await;
+        for account in batch {
+            accounts.insert(account.client, account);
+        }
+        Ok(())
+    }
+
     async fn get(&self, client_id: u16) -> Result<Option<ClientAccount>> {
         let accounts = self.accounts.read().await;
         Ok(accounts.get(&client_id).cloned())
@@ -56,13 +161,19 @@ impl AccountStore for InMemoryAccountStore {
 ///
 /// Uses `Arc<RwLock<...>>` for shared concurrent access.
 /// Optimized for memory efficiency by:
-/// 1. Only storing disputable transactions (Deposits) in the `records` map.
-/// 2. Using `LeanTransaction` to minimize per-record overhead.
-/// 3. Using a `seen_ids` set for global uniqueness tracking of all transaction types.
+/// 1. Only storing disputable transactions (Deposits, and Withdrawals when enabled) in the
+///    `records`.
+/// 2. Packing records in 16 bytes, in pages of consecutive ids (`PagedMap`) rather than a
+///    hash map.
+/// 3. Using a compressed `IdBitmap` of `seen_ids` for global uniqueness tracking of all
+///    transaction types, which holds the whole `u32` id space in 512 MiB.
+/// 4. Keeping the dispute records apart, for the few transactions that have been disputed.
 #[derive(Default, Clone)]
 pub struct InMemoryTransactionStore {
-    records: Arc<RwLock<HashMap<u32, LeanTransaction>>>,
-    seen_ids: Arc<RwLock<HashSet<u32>>>,
+    records: Arc<RwLock<TransactionRecords>>,
+    disputes: Arc<RwLock<HashMap<u32, DisputeRecord>>>,
+    seen_ids: Arc<RwLock<IdBitmap>>,
+    keep_withdrawals: bool,
 }
 
 impl InMemoryTransactionStore {
@@ -70,38 +181,84 @@ impl InMemoryTransactionStore {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Creates a new, empty in-memory transaction store that also keeps withdrawal records,
+    /// so withdrawals can be disputed.
+    pub fn with_withdrawals() -> Self {
+        Self {
+            keep_withdrawals: true,
+            ..Self::default()
+        }
+    }
+
+    fn is_recorded(&self, r#type: TransactionType) -> bool {
+        match r#type {
+            TransactionType::Deposit => true,
+            TransactionType::Withdrawal => self.keep_withdrawals,
+            _ => false,
+        }
+    }
+
+    /// Stores transactions, transaction IDs and dispute records while holding every lock of
+    /// the store.
+    async fn store_locked(
+        &self,
+        transactions: Vec<Transaction>,
+        transaction_ids: Vec<u32>,
+        dispute_records: Vec<DisputeRecord>,
+        seen_ids: &mut IdBitmap,
+    ) {
+        for tx_id in transaction_ids {
+            seen_ids.insert(tx_id);
+        }
+        let mut records = self.records.write().await;
+        let mut disputes = self.disputes.write().await;
+        for tx in transactions {
+            let tx_id = tx.tx;
+            seen_ids.insert(tx_id);
+
+            if self.is_recorded(tx.r#type)
+                && let Some(amount) = tx.amount
+            {
+                let lean_tx = LeanTransaction {
+                    r#type: tx.r#type,
+                    client_id: tx.client,
+                    amount,
+                    currency: tx.currency,
+                };
+                records.insert(tx_id, lean_tx);
+            }
+        }
+        for record in dispute_records {
+            disputes.insert(record.tx, record);
+        }
+    }
 }
 
 #[async_trait]
 impl TransactionStore for InMemoryTransactionStore {
     async fn store(&self, tx: Transaction) -> Result<()> {
-        let tx_id = tx.tx;
         let mut seen_ids = self.seen_ids.write().await;
-        seen_ids.insert(tx_id);
-
-        if tx.r#type == TransactionType::Deposit
-            && let Some(amount) = tx.amount
-        {
-            let lean_tx = LeanTransaction {
-                client_id: tx.client,
-                amount,
-                dispute_status: tx.dispute_status,
-            };
-            let mut records = self.records.write().await;
-            records.insert(tx_id, lean_tx);
-        }
+        self.store_locked(vec![tx], Vec::new(), Vec::new(), &mut seen_ids)
+            .await;
+        Ok(())
+    }
+
+    async fn store_id(&self, tx_id: u32) -> Result<()> {
+        self.seen_ids.write().await.insert(tx_id);
         Ok(())
     }
 
     async fn get(&self, tx_id: u32) -> Result<Option<Transaction>> {
         let records = self.records.read().await;
-        if let Some(lean) = records.get(&tx_id) {
+        if let Some(lean) = records.get(tx_id)? {
             Ok(Some(Transaction {
-                r#type: TransactionType::Deposit,
+                r#type: lean.r#type,
                 client: lean.client_id,
                 tx: tx_id,
                 amount: Some(lean.amount),
-                dispute_status: lean.dispute_status,
+                currency: lean.currency,
+                destination: None,
             }))
         } else {
             Ok(None)
@@ -110,14 +267,336 @@ impl TransactionStore for InMemoryTransactionStore {
 
     async fn exists(&self, tx_id: u32) -> Result<bool> {
         let seen_ids = self.seen_ids.read().await;
-        Ok(seen_ids.contains(&tx_id))
+        Ok(seen_ids.contains(tx_id))
+    }
+
+    async fn get_all(&self) -> Result<Vec<Transaction>> {
+        let tx_ids = self.records.read().await.ids();
+        let mut transactions = Vec::with_capacity(tx_ids.len());
+        for tx_id in tx_ids {
+            if let Some(tx) = self.get(tx_id).await? {
+                transactions.push(tx);
+            }
+        }
+        Ok(transactions)
+    }
+
+    async fn get_all_ids(&self) -> Result<IdBitmap> {
+        Ok(self.seen_ids.read().await.clone())
+    }
+
+    async fn store_disputes(&self, record: DisputeRecord) -> Result<()> {
+        self.disputes.write().await.insert(record.tx, record);
+        Ok(())
+    }
+
+    async fn get_disputes(&self, tx_id: u32) -> Result<Option<DisputeRecord>> {
+        Ok(self.disputes.read().await.get(&tx_id).cloned())
+    }
+
+    async fn get_all_disputes(&self) -> Result<Vec<DisputeRecord>> {
+        Ok(self.disputes.read().await.values().cloned().collect())
+    }
+}
+
+/// Commits units of work to in-memory account, transaction, fee, risk, journal and event stores.
+///
+/// Every lock of the stores is taken before anything is written, so readers see either all
+/// the writes of a unit of work or none of them. Also acts as the checkpoint store the
+/// checkpoints of the units of work are committed to.
+#[derive(Clone)]
+pub struct InMemoryUnitOfWork {
+    accounts: InMemoryAccountStore,
+    transactions: InMemoryTransactionStore,
+    fees: Option<InMemoryFeeStore>,
+    risk: Option<InMemoryRiskStore>,
+    journal: Option<InMemoryJournalStore>,
+    events: Option<InMemoryEventStore>,
+    checkpoint: Arc<RwLock<Option<Checkpoint>>>,
+}
+
+impl InMemoryUnitOfWork {
+    /// Creates a unit of work store writing to the given stores, which share its data.
+    pub fn new(accounts: InMemoryAccountStore, transactions: InMemoryTransactionStore) -> Self {
+        Self {
+            accounts,
+            transactions,
+            fees: None,
+            risk: None,
+            journal: None,
+            events: None,
+            checkpoint: Arc::default(),
+        }
+    }
+
+    /// Also writes the fee account of the units of work to `fees`, which shares its data.
+    ///
+    /// Without it, committing a unit of work that carries a fee account fails.
+    pub fn with_fees(mut self, fees: InMemoryFeeStore) -> Self {
+        self.fees = Some(fees);
+        self
+    }
+
+    /// Also writes the risk state of the units of work to `risk`, which shares its data.
+    ///
+    /// Without it, committing a unit of work that carries a risk state fails.
+    pub fn with_risk(mut self, risk: InMemoryRiskStore) -> Self {
+        self.risk = Some(risk);
+        self
+    }
+
+    /// Also appends the journal entries of the units of work to `journal`.
+    ///
+    /// Without it, committing a unit of work that carries journal entries fails.
+    pub fn with_journal(mut self, journal: InMemoryJournalStore) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Also appends the events of the units of work to `events`.
+    ///
+    /// Without it, committing a unit of work that carries events fails.
+    pub fn with_event_log(mut self, events: InMemoryEventStore) -> Self {
+        self.events = Some(events);
+        self
+    }
+}
+
+/// Takes the write lock of a store the unit of work may write to, failing if the unit of work
+/// has writes for it but the store is not set.
+async fn lock_for<'a, T>(
+    store: Option<&'a Arc<RwLock<T>>>,
+    has_writes: bool,
+    name: &str,
+) -> Result<Option<RwLockWriteGuard<'a, T>>> {
+    match store {
+        Some(store) => Ok(Some(store.write().await)),
+        None if has_writes => Err(PaymentError::InternalError(Box::new(
+            std::io::Error::other(format!("{} store not set for the unit of work", name)),
+        ))),
+        None => Ok(None),
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkStore for InMemoryUnitOfWork {
+    async fn commit(&self, work: UnitOfWork) -> Result<()> {
+        let mut fee_account = lock_for(
+            self.fees.as_ref().map(|fees| &fees.account),
+            work.fees.is_some(),
+            "Fee",
+        )
+        .await?;
+        let mut risk_states = lock_for(
+            self.risk.as_ref().map(|risk| &risk.states),
+            work.risk.is_some(),
+            "Risk",
+        )
+        .await?;
+        let mut journal = lock_for(
+            self.journal.as_ref().map(|journal| &journal.entries),
+            !work.journal.is_empty(),
+            "Journal",
+        )
+        .await?;
+        let mut events = lock_for(
+            self.events.as_ref().map(|events| &events.events),
+            !work.events.is_empty(),
+            "Event",
+        )
+        .await?;
+        let mut accounts = self.accounts.accounts.write().await;
+        let mut seen_ids = self.transactions.seen_ids.write().await;
+        let mut checkpoint = self.checkpoint.write().await;
+        self.transactions
+            .store_locked(
+                work.transactions,
+                work.transaction_ids,
+                work.disputes,
+                &mut seen_ids,
+            )
+            .await;
+        for account in work.accounts {
+            accounts.insert(account.client, account);
+        }
+        if let (Some(fee_account), Some(fees)) = (fee_account.as_deref_mut(), work.fees) {
+            *fee_account = fees;
+        }
+        if let (Some(risk_states), Some(state)) = (risk_states.as_deref_mut(), work.risk) {
+            risk_states.insert(state.client, state);
+        }
+        if let Some(journal) = journal.as_deref_mut() {
+            InMemoryJournalStore::append_locked(journal, work.journal);
+        }
+        if let Some(events) = events.as_deref_mut() {
+            InMemoryEventStore::append_locked(events, work.events);
+        }
+        if work.checkpoint.is_some() {
+            *checkpoint = work.checkpoint;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for InMemoryUnitOfWork {
+    async fn save(&self, checkpoint: Checkpoint) -> Result<()> {
+        *self.checkpoint.write().await = Some(checkpoint);
+        Ok(())
+    }
+
+    async fn latest(&self) -> Result<Option<Checkpoint>> {
+        Ok(*self.checkpoint.read().await)
+    }
+}
+
+/// A thread-safe in-memory journal of double-entry postings.
+///
+/// Entries are kept in posting order, their sequence number being their position.
+#[derive(Default, Clone)]
+pub struct InMemoryJournalStore {
+    entries: Arc<RwLock<Vec<JournalEntry>>>,
+}
+
+impl InMemoryJournalStore {
+    /// Creates a new, empty in-memory journal.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends entries to the journal while its lock is held, numbering them by position.
+    fn append_locked(entries: &mut Vec<JournalEntry>, new_entries: Vec<JournalEntry>) {
+        for mut entry in new_entries {
+            entry.seq = entries.len() as u64;
+            entries.push(entry);
+        }
+    }
+}
+
+#[async_trait]
+impl JournalStore for InMemoryJournalStore {
+    async fn append(&self, new_entries: Vec<JournalEntry>) -> Result<()> {
+        Self::append_locked(&mut *self.entries.write().await, new_entries);
+        Ok(())
+    }
+
+    async fn get_for_client(&self, client_id: u16) -> Result<Vec<JournalEntry>> {
+        let entries = self.entries.read().await;
+        Ok(entries
+            .iter()
+            .filter(|entry| entry.involves_client(client_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_all(&self) -> Result<Vec<JournalEntry>> {
+        let entries = self.entries.read().await;
+        Ok(entries.clone())
+    }
+}
+
+/// A thread-safe in-memory log of domain events.
+///
+/// Events are kept in log order, their sequence number being their position.
+#[derive(Default, Clone)]
+pub struct InMemoryEventStore {
+    events: Arc<RwLock<Vec<EventRecord>>>,
+}
+
+impl InMemoryEventStore {
+    /// Creates a new, empty in-memory event log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends events to the log while its lock is held, numbering them by position.
+    fn append_locked(events: &mut Vec<EventRecord>, new_events: Vec<EventRecord>) {
+        for mut event in new_events {
+            event.seq = events.len() as u64;
+            events.push(event);
+        }
+    }
+}
+
+#[async_trait]
+impl EventStore for InMemoryEventStore {
+    async fn append(&self, new_events: Vec<EventRecord>) -> Result<()> {
+        Self::append_locked(&mut *self.events.write().await, new_events);
+        Ok(())
+    }
+
+    async fn get_for_client(&self, client_id: u16) -> Result<Vec<EventRecord>> {
+        let events = self.events.read().await;
+        Ok(events
+            .iter()
+            .filter(|event| event.client == client_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_all(&self) -> Result<Vec<EventRecord>> {
+        let events = self.events.read().await;
+        Ok(events.clone())
+    }
+}
+
+/// A thread-safe in-memory store for the per-client state of the risk rules.
+#[derive(Default, Clone)]
+pub struct InMemoryRiskStore {
+    states: Arc<RwLock<HashMap<u16, RiskState>>>,
+}
+
+impl InMemoryRiskStore {
+    /// Creates a new, empty in-memory risk store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RiskStore for InMemoryRiskStore {
+    async fn store(&self, state: RiskState) -> Result<()> {
+        let mut states = self.states.write().await;
+        states.insert(state.client, state);
+        Ok(())
+    }
+
+    async fn get(&self, client_id: u16) -> Result<Option<RiskState>> {
+        let states = self.states.read().await;
+        Ok(states.get(&client_id).cloned())
+    }
+}
+
+/// A thread-safe in-memory store for the system fee account.
+#[derive(Default, Clone)]
+pub struct InMemoryFeeStore {
+    account: Arc<RwLock<FeeAccount>>,
+}
+
+impl InMemoryFeeStore {
+    /// Creates a new in-memory fee store, with no fee collected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl FeeStore for InMemoryFeeStore {
+    async fn store(&self, account: FeeAccount) -> Result<()> {
+        *self.account.write().await = account;
+        Ok(())
+    }
+
+    async fn get(&self) -> Result<FeeAccount> {
+        Ok(self.account.read().await.clone())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::account::Balance;
+    use crate::domain::account::{Balance, LockCause, LockReason};
+    use crate::domain::currency::Currency;
     use crate::domain::transaction::TransactionType;
     use rust_decimal_macros::dec;
 
@@ -125,7 +604,7 @@ mod tests {
     async fn test_in_memory_account_store() {
         let store = InMemoryAccountStore::new();
         let mut account = ClientAccount::new(1);
-        account.available = Balance::new(dec!(100.0));
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(100.0));
 
         store.store(account.clone()).await.unwrap();
         let retrieved = store.get(1).await.unwrap().unwrap();
@@ -148,6 +627,20 @@ mod tests {
         assert!(all.contains(&account2));
     }
 
+    #[tokio::test]
+    async fn test_in_memory_account_store_all() {
+        let store = InMemoryAccountStore::new();
+        let account1 = ClientAccount::new(1);
+        let account2 = ClientAccount::new(2);
+        store
+            .store_all(vec![account1.clone(), account2.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(store.get(1).await.unwrap(), Some(account1));
+        assert_eq!(store.get(2).await.unwrap(), Some(account2));
+    }
+
     #[tokio::test]
     async fn test_in_memory_transaction_store() {
         let store = InMemoryTransactionStore::new();
@@ -156,7 +649,8 @@ mod tests {
             client: 1,
             tx: 1,
             amount: Some(dec!(100.0).try_into().unwrap()),
-            dispute_status: Default::default(),
+            currency: Currency::default(),
+            destination: None,
         };
 
         store.store(tx.clone()).await.unwrap();
@@ -173,14 +667,16 @@ mod tests {
             client: 1,
             tx: 1,
             amount: Some(dec!(100.0).try_into().unwrap()),
-            dispute_status: Default::default(),
+            currency: Currency::default(),
+            destination: None,
         };
         let withdrawal = Transaction {
             r#type: TransactionType::Withdrawal,
             client: 1,
             tx: 2,
             amount: Some(dec!(50.0).try_into().unwrap()),
-            dispute_status: Default::default(),
+            currency: Currency::default(),
+            destination: None,
         };
 
         store.store(deposit.clone()).await.unwrap();
@@ -200,4 +696,250 @@ mod tests {
         assert_eq!(retrieved_deposit.tx, 1);
         assert_eq!(retrieved_deposit.amount, deposit.amount);
     }
+
+    #[tokio::test]
+    async fn test_keeps_withdrawals_when_enabled() {
+        let store = InMemoryTransactionStore::with_withdrawals();
+        let withdrawal = Transaction {
+            r#type: TransactionType::Withdrawal,
+            client: 1,
+            tx: 2,
+            amount: Some(dec!(50.0).try_into().unwrap()),
+            currency: Currency::default(),
+            destination: None,
+        };
+
+        store.store(withdrawal.clone()).await.unwrap();
+        let retrieved = store.get(2).await.unwrap().unwrap();
+        assert_eq!(retrieved, withdrawal);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_journal_store() {
+        use crate::domain::ledger::LedgerAccount;
+
+        let store = InMemoryJournalStore::new();
+        let entry = |client| {
+            JournalEntry::new(
+                1,
+                Currency::default(),
+                LedgerAccount::Cash,
+                LedgerAccount::Available(client),
+                Balance::new(dec!(1.0)),
+            )
+        };
+        store.append(vec![entry(1), entry(2)]).await.unwrap();
+        store.append(vec![entry(1)]).await.unwrap();
+
+        let all = store.get_all().await.unwrap();
+        assert_eq!(all.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        let client1 = store.get_for_client(1).await.unwrap();
+        assert_eq!(
+            client1.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_event_store() {
+        use crate::domain::event::DomainEvent;
+
+        let store = InMemoryEventStore::new();
+        store
+            .append(vec![
+                EventRecord::new(1, 1, DomainEvent::AccountOpened),
+                EventRecord::new(2, 2, DomainEvent::AccountOpened),
+            ])
+            .await
+            .unwrap();
+        store
+            .append(vec![EventRecord::new(1, 3, DomainEvent::AccountFrozen)])
+            .await
+            .unwrap();
+
+        let all = store.get_all().await.unwrap();
+        assert_eq!(all.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        let client1 = store.get_for_client(1).await.unwrap();
+        assert_eq!(client1.iter().map(|e| e.tx).collect::<Vec<_>>(), vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_risk_store() {
+        let store = InMemoryRiskStore::new();
+        let state = RiskState::new(1);
+
+        RiskStore::store(&store, state.clone()).await.unwrap();
+        assert_eq!(RiskStore::get(&store, 1).await.unwrap(), Some(state));
+        assert!(RiskStore::get(&store, 2).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_fee_store() {
+        let store = InMemoryFeeStore::new();
+        assert_eq!(FeeStore::get(&store).await.unwrap(), FeeAccount::default());
+
+        let mut account = FeeAccount::default();
+        account
+            .credit(Currency::default(), Balance::new(dec!(1.5)))
+            .unwrap();
+        FeeStore::store(&store, account.clone()).await.unwrap();
+        assert_eq!(FeeStore::get(&store).await.unwrap(), account);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_account_lock_reason() {
+        let store = InMemoryAccountStore::new();
+        let mut account = ClientAccount::new(1);
+        account.lock(LockReason {
+            tx: 3,
+            r#type: TransactionType::Chargeback,
+            cause: LockCause::Chargeback,
+            position: 5,
+            timestamp: Some(1700000000),
+        });
+
+        AccountStore::store(&store, account.clone()).await.unwrap();
+        assert_eq!(AccountStore::get(&store, 1).await.unwrap(), Some(account));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_unit_of_work() {
+        use crate::domain::event::DomainEvent;
+        use crate::domain::ledger::LedgerAccount;
+
+        let accounts = InMemoryAccountStore::new();
+        let transactions = InMemoryTransactionStore::new();
+        let fees = InMemoryFeeStore::new();
+        let risk = InMemoryRiskStore::new();
+        let journal = InMemoryJournalStore::new();
+        let events = InMemoryEventStore::new();
+        let unit_of_work = InMemoryUnitOfWork::new(accounts.clone(), transactions.clone())
+            .with_fees(fees.clone())
+            .with_risk(risk.clone())
+            .with_journal(journal.clone())
+            .with_event_log(events.clone());
+        events
+            .append(vec![EventRecord::new(2, 1, DomainEvent::AccountOpened)])
+            .await
+            .unwrap();
+
+        let account = ClientAccount::new(1);
+        let mut fee_account = FeeAccount::default();
+        fee_account
+            .credit(Currency::default(), Balance::new(dec!(0.5)))
+            .unwrap();
+        let tx = Transaction {
+            r#type: TransactionType::Deposit,
+            client: 1,
+            tx: 7,
+            amount: Some(dec!(10.0).try_into().unwrap()),
+            currency: Currency::default(),
+            destination: None,
+        };
+        unit_of_work
+            .commit(UnitOfWork {
+                accounts: vec![account.clone()],
+                transactions: vec![tx.clone()],
+                transaction_ids: vec![8],
+                disputes: vec![DisputeRecord::new(7)],
+                fees: Some(fee_account.clone()),
+                risk: Some(RiskState::new(1)),
+                journal: vec![JournalEntry::new(
+                    7,
+                    Currency::default(),
+                    LedgerAccount::Cash,
+                    LedgerAccount::Available(1),
+                    Balance::new(dec!(10.0)),
+                )],
+                events: vec![EventRecord::new(1, 7, DomainEvent::AccountOpened)],
+                checkpoint: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(accounts.get(1).await.unwrap(), Some(account));
+        assert_eq!(fees.get().await.unwrap(), fee_account);
+        assert_eq!(risk.get(1).await.unwrap(), Some(RiskState::new(1)));
+        // The entries are numbered after those appended before
+        assert_eq!(journal.get_all().await.unwrap()[0].seq, 0);
+        assert_eq!(events.get_for_client(1).await.unwrap()[0].seq, 1);
+        assert_eq!(transactions.get(7).await.unwrap(), Some(tx));
+        assert!(transactions.exists(7).await.unwrap());
+        // An ID recorded alone is a duplicate without a record
+        assert!(transactions.exists(8).await.unwrap());
+        assert!(transactions.get(8).await.unwrap().is_none());
+        assert_eq!(
+            transactions
+                .get_all_ids()
+                .await
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![7, 8]
+        );
+        assert_eq!(
+            transactions.get_disputes(7).await.unwrap(),
+            Some(DisputeRecord::new(7))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_unit_of_work_without_journal() {
+        use crate::domain::ledger::LedgerAccount;
+
+        let accounts = InMemoryAccountStore::new();
+        let unit_of_work =
+            InMemoryUnitOfWork::new(accounts.clone(), InMemoryTransactionStore::new());
+        let result = unit_of_work
+            .commit(UnitOfWork {
+                accounts: vec![ClientAccount::new(1)],
+                journal: vec![JournalEntry::new(
+                    7,
+                    Currency::default(),
+                    LedgerAccount::Cash,
+                    LedgerAccount::Available(1),
+                    Balance::new(dec!(10.0)),
+                )],
+                ..UnitOfWork::default()
+            })
+            .await;
+
+        // Nothing is written when the journal entries have nowhere to go
+        assert!(result.is_err());
+        assert!(accounts.get(1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_packed_and_unpacked_records() {
+        assert_eq!(std::mem::size_of::<PackedRecord>(), 16);
+
+        let store = InMemoryTransactionStore::with_withdrawals();
+        let deposit = Transaction {
+            r#type: TransactionType::Deposit,
+            client: 3,
+            tx: 1,
+            amount: Some(dec!(12.3400).try_into().unwrap()),
+            currency: Currency::new("EUR").unwrap(),
+            destination: None,
+        };
+        // Too wide a mantissa for a packed record
+        let huge = Transaction {
+            r#type: TransactionType::Withdrawal,
+            tx: 2,
+            amount: Some(dec!(79228162514264.337593543950335).try_into().unwrap()),
+            ..deposit.clone()
+        };
+        store.store(deposit.clone()).await.unwrap();
+        store.store(huge.clone()).await.unwrap();
+
+        let retrieved = store.get(1).await.unwrap().unwrap();
+        assert_eq!(retrieved, deposit);
+        // The scale of the amount is kept
+        assert_eq!(retrieved.amount.unwrap().value().to_string(), "12.3400");
+        assert_eq!(store.get(2).await.unwrap(), Some(huge));
+        assert_eq!(store.get_all().await.unwrap().len(), 2);
+        assert!(store.get(3).await.unwrap().is_none());
+    }
 }
@@ -1,3 +1,7 @@
+pub mod codec;
+pub mod compact;
+pub mod file;
 pub mod in_memory;
 #[cfg(feature = "storage-rocksdb")]
 pub mod rocksdb;
+pub mod schema;
@@ -1,119 +1,438 @@
 #![cfg(feature = "storage-rocksdb")]
 use crate::domain::account::ClientAccount;
-use crate::domain::ports::{AccountStore, TransactionStore};
+use crate::domain::checkpoint::Checkpoint;
+use crate::domain::dispute::DisputeRecord;
+use crate::domain::event::EventRecord;
+use crate::domain::fee::FeeAccount;
+use crate::domain::ids::IdBitmap;
+use crate::domain::ledger::JournalEntry;
+use crate::domain::ports::{
+    AccountStore, CheckpointStore, EventStore, FeeStore, JournalStore, RiskStore, SnapshotStore,
+    TransactionStore, UnitOfWork, UnitOfWorkStore,
+};
+use crate::domain::risk::RiskState;
+use crate::domain::snapshot::Snapshot;
 use crate::domain::transaction::Transaction;
 use crate::error::{PaymentError, Result};
+use crate::infrastructure::codec;
+use crate::infrastructure::schema::{self, LEGACY_SCHEMA_VERSION, Migration, SCHEMA_VERSION};
 use async_trait::async_trait;
-use rocksdb::{ColumnFamilyDescriptor, DB, Options};
+use rocksdb::{ColumnFamilyDescriptor, DB, Options, WriteBatch};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 
 /// Column Family for storing account states.
 pub const CF_ACCOUNTS: &str = "accounts";
 /// Column Family for storing transaction history.
 pub const CF_TRANSACTIONS: &str = "transactions";
+/// Column Family for storing the IDs of transactions that had no effect, which are kept for
+/// duplicate detection only.
+pub const CF_TRANSACTION_IDS: &str = "transaction_ids";
+/// Column Family for storing the dispute records of the disputed transactions.
+pub const CF_DISPUTES: &str = "disputes";
+/// Column Family for storing the double-entry journal.
+pub const CF_JOURNAL: &str = "journal";
+/// Column Family for storing the domain event log.
+pub const CF_EVENTS: &str = "events";
+/// Column Family for storing the latest engine snapshot.
+pub const CF_SNAPSHOTS: &str = "snapshots";
+/// Column Family for storing the per-client state of the risk rules.
+pub const CF_RISK: &str = "risk";
+/// Column Family for storing the system fee account.
+pub const CF_FEES: &str = "fees";
+/// Column Family for storing the checkpoint of the input being processed.
+pub const CF_CHECKPOINTS: &str = "checkpoints";
+/// Column Family for storing metadata about the database itself, such as its schema version.
+pub const CF_METADATA: &str = "metadata";
+
+/// The Column Families holding data, as opposed to metadata.
+const DATA_COLUMN_FAMILIES: [&str; 10] = [
+    CF_ACCOUNTS,
+    CF_TRANSACTIONS,
+    CF_TRANSACTION_IDS,
+    CF_DISPUTES,
+    CF_JOURNAL,
+    CF_EVENTS,
+    CF_SNAPSHOTS,
+    CF_RISK,
+    CF_FEES,
+    CF_CHECKPOINTS,
+];
+
+/// Key of the latest snapshot in the snapshots Column Family.
+const LATEST_SNAPSHOT_KEY: &[u8] = b"latest";
+/// The prefix of the keys of the incremental snapshots taken after the latest full one,
+/// followed by their big-endian position.
+const SNAPSHOT_INCREMENT_PREFIX: &[u8] = b"increment/";
+/// Key of the fee account in the fees Column Family.
+const FEE_ACCOUNT_KEY: &[u8] = b"fees";
+/// Key of the latest checkpoint in the checkpoints Column Family.
+const LATEST_CHECKPOINT_KEY: &[u8] = b"latest";
+/// Key of the schema version in the metadata Column Family.
+const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+/// Number of values rewritten per `WriteBatch` by a migration.
+const MIGRATION_BATCH_SIZE: usize = 10_000;
 
 /// A persistent store implementation using RocksDB.
 ///
-/// Handles storage for both `ClientAccount` and `Transaction` entities using
-/// separate Column Families. This ensures data separation and efficient retrieval.
+/// Handles storage for `ClientAccount`, `Transaction`, `DisputeRecord`, `JournalEntry`, `EventRecord`, `Snapshot`,
+/// `RiskState`, `FeeAccount` and `Checkpoint` entities using separate Column Families. This ensures data separation and efficient retrieval.
+///
+/// Accounts, transactions and dispute records, written once per input transaction, are stored in the compact
+/// binary encoding of [`codec`]. The "metadata" Column Family records the [`schema`] version
+/// of the database; databases at an older version are upgraded by [`RocksDBStore::migrate`].
 ///
 /// This struct is thread-safe (`Clone` shares the underlying `Arc<DB>`).
 #[derive(Clone)]
 pub struct RocksDBStore {
     db: Arc<DB>,
+    /// The next sequence numbers of the journal and the event log, only advanced once the
+    /// entries they were assigned to are written.
+    sequences: Arc<Mutex<Sequences>>,
+}
+
+/// The next sequence numbers of the append-only column families.
+struct Sequences {
+    journal: u64,
+    events: u64,
 }
 
 impl RocksDBStore {
+    /// Returns a column family of the database.
+    fn column_family(&self, cf_name: &str) -> Result<&rocksdb::ColumnFamily> {
+        column_family(&self.db, cf_name)
+    }
+
     /// Opens or creates a RocksDB instance at the specified path.
     ///
-    /// Ensures that the required column families ("accounts" and "transactions") exist.
+    /// Ensures that the required column families ("accounts", "transactions",
+    /// "transaction_ids", "disputes", "journal", "events", "snapshots", "risk", "fees",
+    /// "checkpoints" and "metadata") exist, and refuses
+    /// a database at another schema version than [`SCHEMA_VERSION`].
     ///
     /// # Arguments
     ///
     /// * `path` - The filesystem path where the database will be stored.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let mut opts = Options::default();
-        opts.create_if_missing(true);
-        opts.create_missing_column_families(true);
+        let db = open_db(path)?;
+        schema::check(schema_version(&db)?)?;
+
+        // Resume the append-only sequences after their last entry
+        let sequences = Sequences {
+            journal: next_seq(&db, CF_JOURNAL)?,
+            events: next_seq(&db, CF_EVENTS)?,
+        };
+
+        Ok(Self {
+            db: Arc::new(db),
+            sequences: Arc::new(Mutex::new(sequences)),
+        })
+    }
+
+    /// Upgrades the database at the specified path in place to [`SCHEMA_VERSION`].
+    ///
+    /// Each step records its version once its values are rewritten, so an interrupted migration
+    /// can simply be run again.
+    pub fn migrate<P: AsRef<Path>>(path: P) -> Result<Migration> {
+        let db = open_db(path)?;
+        let from = schema_version(&db)?;
+        if from > SCHEMA_VERSION {
+            schema::check(from)?;
+        }
+
+        let mut version = from;
+        let mut rewritten = 0;
+        while version < SCHEMA_VERSION {
+            rewritten += upgrade(&db, version)?;
+            version += 1;
+            set_schema_version(&db, version)?;
+        }
+
+        Ok(Migration {
+            from,
+            to: version,
+            rewritten,
+        })
+    }
+}
+
+/// Opens or creates the database and its column families, whatever its schema version.
+fn open_db<P: AsRef<Path>>(path: P) -> Result<DB> {
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+
+    let cf_accounts = ColumnFamilyDescriptor::new(CF_ACCOUNTS, Options::default());
+    let cf_transactions = ColumnFamilyDescriptor::new(CF_TRANSACTIONS, Options::default());
+    let cf_transaction_ids = ColumnFamilyDescriptor::new(CF_TRANSACTION_IDS, Options::default());
+    let cf_disputes = ColumnFamilyDescriptor::new(CF_DISPUTES, Options::default());
+    let cf_journal = ColumnFamilyDescriptor::new(CF_JOURNAL, Options::default());
+    let cf_events = ColumnFamilyDescriptor::new(CF_EVENTS, Options::default());
+    let cf_snapshots = ColumnFamilyDescriptor::new(CF_SNAPSHOTS, Options::default());
+    let cf_risk = ColumnFamilyDescriptor::new(CF_RISK, Options::default());
+    let cf_fees = ColumnFamilyDescriptor::new(CF_FEES, Options::default());
+    let cf_checkpoints = ColumnFamilyDescriptor::new(CF_CHECKPOINTS, Options::default());
+    let cf_metadata = ColumnFamilyDescriptor::new(CF_METADATA, Options::default());
 
-        let cf_accounts = ColumnFamilyDescriptor::new(CF_ACCOUNTS, Options::default());
-        let cf_transactions = ColumnFamilyDescriptor::new(CF_TRANSACTIONS, Options::default());
+    let db = DB::open_cf_descriptors(
+        &opts,
+        path,
+        vec![
+            cf_accounts,
+            cf_transactions,
+            cf_transaction_ids,
+            cf_disputes,
+            cf_journal,
+            cf_events,
+            cf_snapshots,
+            cf_risk,
+            cf_fees,
+            cf_checkpoints,
+            cf_metadata,
+        ],
+    )?;
 
-        let db = DB::open_cf_descriptors(&opts, path, vec![cf_accounts, cf_transactions])?;
+    Ok(db)
+}
+
+/// Returns a column family of the database.
+fn column_family<'a>(db: &'a DB, cf_name: &str) -> Result<&'a rocksdb::ColumnFamily> {
+    db.cf_handle(cf_name).ok_or_else(|| {
+        PaymentError::InternalError(Box::new(std::io::Error::other(format!(
+            "Column family {} not found",
+            cf_name
+        ))))
+    })
+}
 
-        Ok(Self { db: Arc::new(db) })
+/// Serializes a value stored as JSON.
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| {
+        PaymentError::InternalError(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Serialization error: {}", e),
+        )))
+    })
+}
+
+/// Deserializes a value stored as JSON.
+fn from_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| {
+        PaymentError::InternalError(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Deserialization error: {}", e),
+        )))
+    })
+}
+
+/// Returns the schema version of the database, recording the current one in a new database.
+fn schema_version(db: &DB) -> Result<u32> {
+    let cf = column_family(db, CF_METADATA)?;
+    if let Some(bytes) = db.get_cf(cf, SCHEMA_VERSION_KEY)? {
+        return schema::decode_version(&bytes);
+    }
+    // Databases created before schema versioning hold data but no version
+    for cf_name in DATA_COLUMN_FAMILIES {
+        let cf = column_family(db, cf_name)?;
+        if db
+            .iterator_cf(cf, rocksdb::IteratorMode::Start)
+            .next()
+            .is_some()
+        {
+            return Ok(LEGACY_SCHEMA_VERSION);
+        }
+    }
+    set_schema_version(db, SCHEMA_VERSION)?;
+    Ok(SCHEMA_VERSION)
+}
+
+fn set_schema_version(db: &DB, version: u32) -> Result<()> {
+    let cf = column_family(db, CF_METADATA)?;
+    db.put_cf(cf, SCHEMA_VERSION_KEY, schema::encode_version(version))?;
+    Ok(())
+}
+
+/// Rewrites the values of a database at schema version `from` for the next version, returning
+/// the number of values rewritten.
+fn upgrade(db: &DB, from: u32) -> Result<u64> {
+    match from {
+        // JSON values to the binary encoding, the transactions being rewritten by the next step
+        1 => reencode(
+            db,
+            CF_ACCOUNTS,
+            codec::decode_account,
+            codec::encode_account,
+        ),
+        // Dispute state of the transactions to dispute records
+        2 => split_disputes(db),
+        _ => Err(PaymentError::InternalError(Box::new(
+            std::io::Error::other(format!("No migration from schema version {}", from)),
+        ))),
+    }
+}
+
+/// Rewrites the values of a column family not yet in the binary encoding.
+fn reencode<T>(
+    db: &DB,
+    cf_name: &str,
+    decode: fn(&[u8]) -> Result<T>,
+    encode: fn(&T) -> Vec<u8>,
+) -> Result<u64> {
+    let cf = column_family(db, cf_name)?;
+    let mut batch = WriteBatch::default();
+    let mut rewritten = 0;
+    for item in db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+        let (key, value) = item?;
+        if value.first() == Some(&codec::FORMAT_MAGIC) {
+            continue;
+        }
+        batch.put_cf(cf, key, encode(&decode(&value)?));
+        rewritten += 1;
+        if batch.len() >= MIGRATION_BATCH_SIZE {
+            db.write(std::mem::take(&mut batch))?;
+        }
+    }
+    db.write(batch)?;
+    Ok(rewritten)
+}
+
+/// Moves the dispute state kept in the transaction records (and in the latest snapshot) to
+/// dispute records, rewriting every transaction not yet in the current binary encoding.
+fn split_disputes(db: &DB) -> Result<u64> {
+    let cf_transactions = column_family(db, CF_TRANSACTIONS)?;
+    let cf_disputes = column_family(db, CF_DISPUTES)?;
+    let mut batch = WriteBatch::default();
+    let mut rewritten = 0;
+    for item in db.iterator_cf(cf_transactions, rocksdb::IteratorMode::Start) {
+        let (key, value) = item?;
+        if value.starts_with(&[codec::FORMAT_MAGIC, codec::FORMAT_VERSION]) {
+            continue;
+        }
+        let (tx, disputes) = codec::decode_legacy_transaction(&value)?;
+        batch.put_cf(cf_transactions, key, codec::encode_transaction(&tx));
+        if let Some(disputes) = disputes {
+            batch.put_cf(
+                cf_disputes,
+                disputes.tx.to_be_bytes(),
+                codec::encode_disputes(&disputes),
+            );
+        }
+        rewritten += 1;
+        if batch.len() >= MIGRATION_BATCH_SIZE {
+            db.write(std::mem::take(&mut batch))?;
+        }
+    }
+
+    let cf_snapshots = column_family(db, CF_SNAPSHOTS)?;
+    if let Some(bytes) = db.get_cf(cf_snapshots, LATEST_SNAPSHOT_KEY)? {
+        let legacy: LegacySnapshot = from_json(&bytes)?;
+        let mut snapshot = Snapshot {
+            position: legacy.position,
+            incremental: false,
+            accounts: legacy.accounts,
+            transactions: Vec::with_capacity(legacy.transactions.len()),
+            disputes: legacy.disputes,
+            transaction_ids: IdBitmap::new(),
+            fees: legacy.fees,
+        };
+        for tx in legacy.transactions {
+            let (tx, disputes) = tx.split()?;
+            snapshot.transaction_ids.insert(tx.tx);
+            snapshot.transactions.push(tx);
+            snapshot.disputes.extend(disputes);
+        }
+        let value = to_json(&snapshot)?;
+        batch.put_cf(cf_snapshots, LATEST_SNAPSHOT_KEY, value);
+        rewritten += 1;
+    }
+    db.write(batch)?;
+    Ok(rewritten)
+}
+
+/// A snapshot whose transactions may still hold their dispute state.
+#[derive(Deserialize)]
+struct LegacySnapshot {
+    position: u64,
+    accounts: Vec<ClientAccount>,
+    transactions: Vec<codec::LegacyTransaction>,
+    #[serde(default)]
+    disputes: Vec<DisputeRecord>,
+    #[serde(default)]
+    fees: FeeAccount,
+}
+
+/// Returns the sequence number following the last key of an append-only column family.
+///
+/// Keys are big-endian `u64` sequence numbers, so the last key is the highest one.
+fn next_seq(db: &DB, cf_name: &str) -> Result<u64> {
+    let cf = column_family(db, cf_name)?;
+    match db.iterator_cf(cf, rocksdb::IteratorMode::End).next() {
+        Some(item) => {
+            let (key, _value) = item?;
+            let seq: [u8; 8] = key.as_ref().try_into().map_err(|_| {
+                PaymentError::InternalError(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Invalid key in column family {}", cf_name),
+                )))
+            })?;
+            Ok(u64::from_be_bytes(seq) + 1)
+        }
+        None => Ok(0),
     }
 }
 
 #[async_trait]
 impl AccountStore for RocksDBStore {
     async fn store(&self, account: ClientAccount) -> Result<()> {
-        let cf = self.db.cf_handle(CF_ACCOUNTS).ok_or_else(|| {
-            PaymentError::InternalError(Box::new(std::io::Error::other(
-                "Accounts column family not found",
-            )))
-        })?;
+        let cf = self.column_family(CF_ACCOUNTS)?;
 
         let key = account.client.to_be_bytes();
-        let value = serde_json::to_vec(&account).map_err(|e| {
-            PaymentError::InternalError(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Serialization error: {}", e),
-            )))
-        })?;
+        let value = codec::encode_account(&account);
 
-        self.db.put_cf(&cf, key, value)?;
+        self.db.put_cf(cf, key, value)?;
+
+        Ok(())
+    }
+
+    async fn store_all(&self, accounts: Vec<ClientAccount>) -> Result<()> {
+        let cf = self.column_family(CF_ACCOUNTS)?;
+
+        let mut batch = WriteBatch::default();
+        for account in accounts {
+            let key = account.client.to_be_bytes();
+            let value = codec::encode_account(&account);
+            batch.put_cf(cf, key, value);
+        }
+
+        self.db.write(batch)?;
 
         Ok(())
     }
 
     async fn get(&self, client_id: u16) -> Result<Option<ClientAccount>> {
-        let cf = self.db.cf_handle(CF_ACCOUNTS).ok_or_else(|| {
-            PaymentError::InternalError(Box::new(std::io::Error::other(
-                "Accounts column family not found",
-            )))
-        })?;
+        let cf = self.column_family(CF_ACCOUNTS)?;
 
         let key = client_id.to_be_bytes();
-        let result = self.db.get_cf(&cf, key)?;
+        let result = self.db.get_cf(cf, key)?;
 
         if let Some(bytes) = result {
-            let account = serde_json::from_slice(&bytes).map_err(|e| {
-                PaymentError::InternalError(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    format!("Deserialization error: {}", e),
-                )))
-            })?;
-            Ok(Some(account))
+            Ok(Some(codec::decode_account(&bytes)?))
         } else {
             Ok(None)
         }
     }
 
     async fn get_all(&self) -> Result<Vec<ClientAccount>> {
-        let handle = self.db.cf_handle("accounts").ok_or_else(|| {
-            PaymentError::InternalError(Box::new(std::io::Error::other(
-                "Accounts column family not found",
-            )))
-        })?;
+        let handle = self.column_family(CF_ACCOUNTS)?;
 
         let mut accounts = Vec::new();
         let iter = self.db.iterator_cf(handle, rocksdb::IteratorMode::Start);
 
         for item in iter {
-            let (_key, value) = item.map_err(|e| {
-                PaymentError::InternalError(Box::new(std::io::Error::other(format!(
-                    "RocksDB iteration error: {}",
-                    e
-                ))))
-            })?;
-            let account: ClientAccount = serde_json::from_slice(&value).map_err(|e| {
-                PaymentError::InternalError(Box::new(std::io::Error::other(format!(
-                    "Failed to deserialize account: {}",
-                    e
-                ))))
-            })?;
+            let (_key, value) = item?;
+            let account = codec::decode_account(&value)?;
             accounts.push(account);
         }
 
@@ -124,67 +443,398 @@ impl AccountStore for RocksDBStore {
 #[async_trait]
 impl TransactionStore for RocksDBStore {
     async fn store(&self, tx: Transaction) -> Result<()> {
-        let cf = self.db.cf_handle(CF_TRANSACTIONS).ok_or_else(|| {
-            PaymentError::InternalError(Box::new(std::io::Error::other(
-                "Transactions column family not found",
-            )))
-        })?;
+        let cf = self.column_family(CF_TRANSACTIONS)?;
 
         let key = tx.tx.to_be_bytes();
-        let value = serde_json::to_vec(&tx).map_err(|e| {
-            PaymentError::InternalError(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Serialization error: {}", e),
-            )))
-        })?;
+        let value = codec::encode_transaction(&tx);
+
+        self.db.put_cf(cf, key, value)?;
+
+        Ok(())
+    }
 
-        self.db.put_cf(&cf, key, value)?;
+    async fn store_id(&self, tx_id: u32) -> Result<()> {
+        let cf = self.column_family(CF_TRANSACTION_IDS)?;
+
+        self.db.put_cf(cf, tx_id.to_be_bytes(), b"")?;
 
         Ok(())
     }
 
     async fn get(&self, tx_id: u32) -> Result<Option<Transaction>> {
-        let cf = self.db.cf_handle(CF_TRANSACTIONS).ok_or_else(|| {
-            PaymentError::InternalError(Box::new(std::io::Error::other(
-                "Transactions column family not found",
-            )))
-        })?;
+        let cf = self.column_family(CF_TRANSACTIONS)?;
 
         let key = tx_id.to_be_bytes();
-        let result = self.db.get_cf(&cf, key)?;
+        let result = self.db.get_cf(cf, key)?;
 
         if let Some(bytes) = result {
-            let tx = serde_json::from_slice(&bytes).map_err(|e| {
-                PaymentError::InternalError(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    format!("Deserialization error: {}", e),
-                )))
-            })?;
-            Ok(Some(tx))
+            Ok(Some(codec::decode_transaction(&bytes)?))
         } else {
             Ok(None)
         }
     }
 
     async fn exists(&self, tx_id: u32) -> Result<bool> {
-        let cf = self.db.cf_handle(CF_TRANSACTIONS).ok_or_else(|| {
-            PaymentError::InternalError(Box::new(std::io::Error::other(
-                "Transactions column family not found",
-            )))
-        })?;
+        let cf = self.column_family(CF_TRANSACTIONS)?;
+
+        let cf_ids = self.column_family(CF_TRANSACTION_IDS)?;
 
         let key = tx_id.to_be_bytes();
         // Just check if the key exists without retrieving the value
-        let result = self.db.get_pinned_cf(&cf, key)?;
-        Ok(result.is_some())
+        if self.db.get_pinned_cf(cf, key)?.is_some() {
+            return Ok(true);
+        }
+        Ok(self.db.get_pinned_cf(cf_ids, key)?.is_some())
+    }
+
+    async fn get_all(&self) -> Result<Vec<Transaction>> {
+        let handle = self.column_family(CF_TRANSACTIONS)?;
+
+        let mut transactions = Vec::new();
+        let iter = self.db.iterator_cf(handle, rocksdb::IteratorMode::Start);
+
+        for item in iter {
+            let (_key, value) = item?;
+            let tx = codec::decode_transaction(&value)?;
+            transactions.push(tx);
+        }
+
+        Ok(transactions)
+    }
+
+    async fn get_all_ids(&self) -> Result<IdBitmap> {
+        let mut ids = IdBitmap::new();
+        for cf_name in [CF_TRANSACTIONS, CF_TRANSACTION_IDS] {
+            let cf = self.column_family(cf_name)?;
+            for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+                let (key, _value) = item?;
+                let tx_id: [u8; 4] = key.as_ref().try_into().map_err(|_| {
+                    PaymentError::InternalError(Box::new(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid key in column family {}", cf_name),
+                    )))
+                })?;
+                ids.insert(u32::from_be_bytes(tx_id));
+            }
+        }
+        Ok(ids)
+    }
+
+    async fn store_disputes(&self, record: DisputeRecord) -> Result<()> {
+        let cf = self.column_family(CF_DISPUTES)?;
+
+        let key = record.tx.to_be_bytes();
+        let value = codec::encode_disputes(&record);
+
+        self.db.put_cf(cf, key, value)?;
+
+        Ok(())
+    }
+
+    async fn get_disputes(&self, tx_id: u32) -> Result<Option<DisputeRecord>> {
+        let cf = self.column_family(CF_DISPUTES)?;
+
+        match self.db.get_cf(cf, tx_id.to_be_bytes())? {
+            Some(bytes) => Ok(Some(codec::decode_disputes(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_all_disputes(&self) -> Result<Vec<DisputeRecord>> {
+        let handle = self.column_family(CF_DISPUTES)?;
+
+        let mut records = Vec::new();
+        for item in self.db.iterator_cf(handle, rocksdb::IteratorMode::Start) {
+            let (_key, value) = item?;
+            records.push(codec::decode_disputes(&value)?);
+        }
+
+        Ok(records)
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkStore for RocksDBStore {
+    /// Writes the accounts, transactions, transaction IDs, dispute records, fee account, risk
+    /// state and checkpoint in a single `WriteBatch` across their column families, which
+    /// RocksDB applies atomically.
+    async fn commit(&self, work: UnitOfWork) -> Result<()> {
+        let cf_accounts = self.column_family(CF_ACCOUNTS)?;
+        let cf_transactions = self.column_family(CF_TRANSACTIONS)?;
+
+        let mut batch = WriteBatch::default();
+        for tx in work.transactions {
+            let key = tx.tx.to_be_bytes();
+            let value = codec::encode_transaction(&tx);
+            batch.put_cf(cf_transactions, key, value);
+        }
+        if !work.transaction_ids.is_empty() {
+            let cf_transaction_ids = self.column_family(CF_TRANSACTION_IDS)?;
+            for tx_id in work.transaction_ids {
+                batch.put_cf(cf_transaction_ids, tx_id.to_be_bytes(), b"");
+            }
+        }
+        if !work.disputes.is_empty() {
+            let cf_disputes = self.column_family(CF_DISPUTES)?;
+            for record in work.disputes {
+                let key = record.tx.to_be_bytes();
+                let value = codec::encode_disputes(&record);
+                batch.put_cf(cf_disputes, key, value);
+            }
+        }
+        for account in work.accounts {
+            let key = account.client.to_be_bytes();
+            let value = codec::encode_account(&account);
+            batch.put_cf(cf_accounts, key, value);
+        }
+        if let Some(fees) = work.fees {
+            let cf_fees = self.column_family(CF_FEES)?;
+            let value = to_json(&fees)?;
+            batch.put_cf(cf_fees, FEE_ACCOUNT_KEY, value);
+        }
+        if let Some(state) = work.risk {
+            let cf_risk = self.column_family(CF_RISK)?;
+            let value = to_json(&state)?;
+            batch.put_cf(cf_risk, state.client.to_be_bytes(), value);
+        }
+        if let Some(checkpoint) = work.checkpoint {
+            let cf_checkpoints = self.column_family(CF_CHECKPOINTS)?;
+            let value = to_json(&checkpoint)?;
+            batch.put_cf(cf_checkpoints, LATEST_CHECKPOINT_KEY, value);
+        }
+
+        self.write_sequenced(batch, work.journal, work.events)
+    }
+}
+
+impl RocksDBStore {
+    /// Writes a batch along with journal entries and events, assigning their sequence numbers.
+    ///
+    /// The sequences are locked until the batch is written, so entries are numbered in the
+    /// order they are committed, and a failed write leaves no gap.
+    fn write_sequenced(
+        &self,
+        mut batch: WriteBatch,
+        journal: Vec<JournalEntry>,
+        events: Vec<EventRecord>,
+    ) -> Result<()> {
+        let mut sequences = self
+            .sequences
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let (mut next_journal_seq, mut next_event_seq) = (sequences.journal, sequences.events);
+        if !journal.is_empty() {
+            let cf_journal = self.column_family(CF_JOURNAL)?;
+            for mut entry in journal {
+                entry.seq = next_journal_seq;
+                next_journal_seq += 1;
+                let value = to_json(&entry)?;
+                batch.put_cf(cf_journal, entry.seq.to_be_bytes(), value);
+            }
+        }
+        if !events.is_empty() {
+            let cf_events = self.column_family(CF_EVENTS)?;
+            for mut event in events {
+                event.seq = next_event_seq;
+                next_event_seq += 1;
+                let value = to_json(&event)?;
+                batch.put_cf(cf_events, event.seq.to_be_bytes(), value);
+            }
+        }
+
+        self.db.write(batch)?;
+        sequences.journal = next_journal_seq;
+        sequences.events = next_event_seq;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JournalStore for RocksDBStore {
+    async fn append(&self, entries: Vec<JournalEntry>) -> Result<()> {
+        self.write_sequenced(WriteBatch::default(), entries, Vec::new())
+    }
+
+    async fn get_for_client(&self, client_id: u16) -> Result<Vec<JournalEntry>> {
+        let entries = JournalStore::get_all(self).await?;
+        Ok(entries
+            .into_iter()
+            .filter(|entry| entry.involves_client(client_id))
+            .collect())
+    }
+
+    async fn get_all(&self) -> Result<Vec<JournalEntry>> {
+        let handle = self.column_family(CF_JOURNAL)?;
+
+        let mut entries = Vec::new();
+        let iter = self.db.iterator_cf(handle, rocksdb::IteratorMode::Start);
+
+        for item in iter {
+            let (_key, value) = item?;
+            let entry: JournalEntry = from_json(&value)?;
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl EventStore for RocksDBStore {
+    async fn append(&self, events: Vec<EventRecord>) -> Result<()> {
+        self.write_sequenced(WriteBatch::default(), Vec::new(), events)
+    }
+
+    async fn get_for_client(&self, client_id: u16) -> Result<Vec<EventRecord>> {
+        let events = EventStore::get_all(self).await?;
+        Ok(events
+            .into_iter()
+            .filter(|event| event.client == client_id)
+            .collect())
+    }
+
+    async fn get_all(&self) -> Result<Vec<EventRecord>> {
+        let handle = self.column_family(CF_EVENTS)?;
+
+        let mut events = Vec::new();
+        let iter = self.db.iterator_cf(handle, rocksdb::IteratorMode::Start);
+
+        for item in iter {
+            let (_key, value) = item?;
+            let event: EventRecord = from_json(&value)?;
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for RocksDBStore {
+    async fn save(&self, snapshot: &Snapshot) -> Result<()> {
+        let cf = self.column_family(CF_SNAPSHOTS)?;
+
+        let value = to_json(snapshot)?;
+
+        let mut batch = WriteBatch::default();
+        if snapshot.incremental {
+            let key = [SNAPSHOT_INCREMENT_PREFIX, &snapshot.position.to_be_bytes()].concat();
+            batch.put_cf(cf, key, value);
+        } else {
+            // A full snapshot supersedes the increments taken before it
+            for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+                let (key, _value) = item?;
+                if key.starts_with(SNAPSHOT_INCREMENT_PREFIX) {
+                    batch.delete_cf(cf, key);
+                }
+            }
+            batch.put_cf(cf, LATEST_SNAPSHOT_KEY, value);
+        }
+        self.db.write(batch)?;
+
+        Ok(())
+    }
+
+    async fn latest(&self) -> Result<Option<Snapshot>> {
+        let cf = self.column_family(CF_SNAPSHOTS)?;
+
+        let Some(bytes) = self.db.get_cf(cf, LATEST_SNAPSHOT_KEY)? else {
+            return Ok(None);
+        };
+        let mut snapshot: Snapshot = from_json(&bytes)?;
+        // The increments are keyed by position, so they are iterated in the order taken
+        for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+            let (key, value) = item?;
+            if key.starts_with(SNAPSHOT_INCREMENT_PREFIX) {
+                snapshot.extend(from_json(&value)?);
+            }
+        }
+        Ok(Some(snapshot))
+    }
+}
+
+#[async_trait]
+impl RiskStore for RocksDBStore {
+    async fn store(&self, state: RiskState) -> Result<()> {
+        let cf = self.column_family(CF_RISK)?;
+
+        let key = state.client.to_be_bytes();
+        let value = to_json(&state)?;
+
+        self.db.put_cf(cf, key, value)?;
+
+        Ok(())
+    }
+
+    async fn get(&self, client_id: u16) -> Result<Option<RiskState>> {
+        let cf = self.column_family(CF_RISK)?;
+
+        let key = client_id.to_be_bytes();
+        let result = self.db.get_cf(cf, key)?;
+
+        if let Some(bytes) = result {
+            let state = from_json(&bytes)?;
+            Ok(Some(state))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[async_trait]
+impl FeeStore for RocksDBStore {
+    async fn store(&self, account: FeeAccount) -> Result<()> {
+        let cf = self.column_family(CF_FEES)?;
+
+        let value = to_json(&account)?;
+
+        self.db.put_cf(cf, FEE_ACCOUNT_KEY, value)?;
+
+        Ok(())
+    }
+
+    async fn get(&self) -> Result<FeeAccount> {
+        let cf = self.column_family(CF_FEES)?;
+
+        match self.db.get_cf(cf, FEE_ACCOUNT_KEY)? {
+            Some(bytes) => from_json(&bytes),
+            None => Ok(FeeAccount::default()),
+        }
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for RocksDBStore {
+    async fn save(&self, checkpoint: Checkpoint) -> Result<()> {
+        let cf = self.column_family(CF_CHECKPOINTS)?;
+
+        let value = to_json(&checkpoint)?;
+
+        self.db.put_cf(cf, LATEST_CHECKPOINT_KEY, value)?;
+
+        Ok(())
+    }
+
+    async fn latest(&self) -> Result<Option<Checkpoint>> {
+        let cf = self.column_family(CF_CHECKPOINTS)?;
+
+        match self.db.get_cf(cf, LATEST_CHECKPOINT_KEY)? {
+            Some(bytes) => from_json(&bytes).map(Some),
+            None => Ok(None),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::account::Balance;
-    use crate::domain::transaction::{DisputeStatus, TransactionType};
+    use crate::domain::account::{AccountStatus, Balance, CurrencyBalance, LockCause, LockReason};
+    use crate::domain::checkpoint::{Fingerprint, InputPosition};
+    use crate::domain::currency::Currency;
+    use crate::domain::dispute::{Dispute, DisputeStatus};
+    use crate::domain::transaction::TransactionType;
+    use rust_decimal::Decimal;
     use rust_decimal_macros::dec;
     use tempfile::tempdir;
 
@@ -196,6 +846,15 @@ mod tests {
         // Verify CFs exist
         assert!(store.db.cf_handle(CF_ACCOUNTS).is_some());
         assert!(store.db.cf_handle(CF_TRANSACTIONS).is_some());
+        assert!(store.db.cf_handle(CF_TRANSACTION_IDS).is_some());
+        assert!(store.db.cf_handle(CF_DISPUTES).is_some());
+        assert!(store.db.cf_handle(CF_JOURNAL).is_some());
+        assert!(store.db.cf_handle(CF_EVENTS).is_some());
+        assert!(store.db.cf_handle(CF_SNAPSHOTS).is_some());
+        assert!(store.db.cf_handle(CF_RISK).is_some());
+        assert!(store.db.cf_handle(CF_FEES).is_some());
+        assert!(store.db.cf_handle(CF_CHECKPOINTS).is_some());
+        assert!(store.db.cf_handle(CF_METADATA).is_some());
     }
 
     #[tokio::test]
@@ -204,7 +863,7 @@ mod tests {
         let store = RocksDBStore::open(dir.path()).unwrap();
 
         let mut account = ClientAccount::new(1);
-        account.available = Balance::new(dec!(100.0));
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(100.0));
 
         AccountStore::store(&store, account.clone()).await.unwrap();
 
@@ -218,6 +877,21 @@ mod tests {
         assert!(AccountStore::get(&store, 2).await.unwrap().is_none());
     }
 
+    #[tokio::test]
+    async fn test_rocksdb_account_store_all() {
+        let dir = tempdir().unwrap();
+        let store = RocksDBStore::open(dir.path()).unwrap();
+
+        let account1 = ClientAccount::new(1);
+        let account2 = ClientAccount::new(2);
+        AccountStore::store_all(&store, vec![account1.clone(), account2.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(AccountStore::get(&store, 1).await.unwrap(), Some(account1));
+        assert_eq!(AccountStore::get(&store, 2).await.unwrap(), Some(account2));
+    }
+
     #[tokio::test]
     async fn test_rocksdb_transaction_store() {
         let dir = tempdir().unwrap();
@@ -228,7 +902,8 @@ mod tests {
             client: 1,
             tx: 1,
             amount: Some(dec!(100.0).try_into().unwrap()),
-            dispute_status: DisputeStatus::None,
+            currency: Currency::default(),
+            destination: None,
         };
 
         TransactionStore::store(&store, tx.clone()).await.unwrap();
@@ -237,5 +912,475 @@ mod tests {
         assert_eq!(retrieved, tx);
 
         assert!(TransactionStore::get(&store, 2).await.unwrap().is_none());
+
+        // The disputes are kept apart from the transaction
+        assert!(store.get_disputes(1).await.unwrap().is_none());
+        let record = DisputeRecord {
+            tx: 1,
+            disputes: vec![Dispute::open(Balance::new(dec!(40)), Balance::ZERO)],
+        };
+        store.store_disputes(record.clone()).await.unwrap();
+        assert_eq!(store.get_disputes(1).await.unwrap(), Some(record.clone()));
+        assert_eq!(store.get_all_disputes().await.unwrap(), vec![record]);
+        assert_eq!(TransactionStore::get(&store, 1).await.unwrap(), Some(tx));
+    }
+
+    /// An account as serialized by releases before the binary encoding.
+    const BASELINE_ACCOUNT: &str =
+        r#"{"client":1,"available":"7.5","held":"0","total":"7.5","locked":false}"#;
+
+    #[tokio::test]
+    async fn test_rocksdb_reads_json_values() {
+        let dir = tempdir().unwrap();
+        let store = RocksDBStore::open(dir.path()).unwrap();
+
+        let mut account = ClientAccount::new(1);
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(7.5));
+        account.balance_mut(Currency::default()).total = Balance::new(dec!(7.5));
+        let tx = Transaction {
+            r#type: TransactionType::Deposit,
+            client: 1,
+            tx: 1,
+            amount: Some(dec!(7.5).try_into().unwrap()),
+            currency: Currency::default(),
+            destination: None,
+        };
+
+        // Values written by earlier versions, before the binary encoding
+        let accounts = store.column_family(CF_ACCOUNTS).unwrap();
+        store
+            .db
+            .put_cf(accounts, 1u16.to_be_bytes(), BASELINE_ACCOUNT)
+            .unwrap();
+        let transactions = store.column_family(CF_TRANSACTIONS).unwrap();
+        let value =
+            r#"{"type":"deposit","client":1,"tx":1,"amount":"7.5","dispute_status":"None"}"#;
+        store
+            .db
+            .put_cf(transactions, 1u32.to_be_bytes(), value)
+            .unwrap();
+
+        assert_eq!(
+            AccountStore::get(&store, 1).await.unwrap(),
+            Some(account.clone())
+        );
+        assert_eq!(
+            TransactionStore::get(&store, 1).await.unwrap(),
+            Some(tx.clone())
+        );
+        assert_eq!(
+            AccountStore::get_all(&store).await.unwrap(),
+            vec![account.clone()]
+        );
+
+        // Rewritten values use the binary encoding
+        AccountStore::store(&store, account.clone()).await.unwrap();
+        let bytes = store
+            .db
+            .get_cf(accounts, 1u16.to_be_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(bytes[0], codec::FORMAT_MAGIC);
+        assert_eq!(AccountStore::get(&store, 1).await.unwrap(), Some(account));
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_journal_store_resumes_sequence() {
+        use crate::domain::ledger::LedgerAccount;
+
+        let dir = tempdir().unwrap();
+        let entry = |client| {
+            JournalEntry::new(
+                1,
+                Currency::default(),
+                LedgerAccount::Cash,
+                LedgerAccount::Available(client),
+                Balance::new(dec!(1.0)),
+            )
+        };
+
+        {
+            let store = RocksDBStore::open(dir.path()).unwrap();
+            JournalStore::append(&store, vec![entry(1), entry(2)])
+                .await
+                .unwrap();
+        }
+
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        JournalStore::append(&store, vec![entry(1)]).await.unwrap();
+
+        let all = JournalStore::get_all(&store).await.unwrap();
+        assert_eq!(all.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![0, 1, 2]);
+        let client1 = JournalStore::get_for_client(&store, 1).await.unwrap();
+        assert_eq!(
+            client1.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_event_store() {
+        use crate::domain::event::DomainEvent;
+
+        let dir = tempdir().unwrap();
+        {
+            let store = RocksDBStore::open(dir.path()).unwrap();
+            EventStore::append(
+                &store,
+                vec![
+                    EventRecord::new(1, 1, DomainEvent::AccountOpened),
+                    EventRecord::new(2, 2, DomainEvent::AccountOpened),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        EventStore::append(
+            &store,
+            vec![EventRecord::new(1, 3, DomainEvent::AccountFrozen)],
+        )
+        .await
+        .unwrap();
+
+        let all = EventStore::get_all(&store).await.unwrap();
+        assert_eq!(all.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![0, 1, 2]);
+        let client1 = EventStore::get_for_client(&store, 1).await.unwrap();
+        assert_eq!(client1.iter().map(|e| e.tx).collect::<Vec<_>>(), vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_snapshot_store() {
+        let dir = tempdir().unwrap();
+        let snapshot = Snapshot {
+            position: 42,
+            incremental: false,
+            accounts: vec![ClientAccount::new(1)],
+            transactions: Vec::new(),
+            disputes: vec![DisputeRecord::new(3)],
+            transaction_ids: [3].into_iter().collect(),
+            fees: FeeAccount::default(),
+        };
+        let increment = Snapshot {
+            position: 50,
+            incremental: true,
+            accounts: vec![ClientAccount::new(2)],
+            ..Snapshot::default()
+        };
+
+        {
+            let store = RocksDBStore::open(dir.path()).unwrap();
+            assert!(SnapshotStore::latest(&store).await.unwrap().is_none());
+            SnapshotStore::save(&store, &Snapshot::default())
+                .await
+                .unwrap();
+            SnapshotStore::save(&store, &increment).await.unwrap();
+            SnapshotStore::save(&store, &snapshot).await.unwrap();
+        }
+
+        // The full snapshot dropped the increment taken before it
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        assert_eq!(
+            SnapshotStore::latest(&store).await.unwrap(),
+            Some(snapshot.clone())
+        );
+
+        SnapshotStore::save(&store, &increment).await.unwrap();
+        let mut extended = snapshot;
+        extended.extend(increment);
+        assert_eq!(SnapshotStore::latest(&store).await.unwrap(), Some(extended));
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_risk_store() {
+        let dir = tempdir().unwrap();
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        let state = RiskState::new(1);
+
+        RiskStore::store(&store, state.clone()).await.unwrap();
+        assert_eq!(RiskStore::get(&store, 1).await.unwrap(), Some(state));
+        assert!(RiskStore::get(&store, 2).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_fee_store() {
+        let dir = tempdir().unwrap();
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        assert_eq!(FeeStore::get(&store).await.unwrap(), FeeAccount::default());
+
+        let mut account = FeeAccount::default();
+        account
+            .credit(Currency::default(), Balance::new(dec!(2.5)))
+            .unwrap();
+        FeeStore::store(&store, account.clone()).await.unwrap();
+        assert_eq!(FeeStore::get(&store).await.unwrap(), account);
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_account_lock_reason() {
+        let dir = tempdir().unwrap();
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        let mut account = ClientAccount::new(1);
+        account.lock(LockReason {
+            tx: 3,
+            r#type: TransactionType::Chargeback,
+            cause: LockCause::Chargeback,
+            position: 5,
+            timestamp: Some(1700000000),
+        });
+
+        AccountStore::store(&store, account.clone()).await.unwrap();
+        assert_eq!(AccountStore::get(&store, 1).await.unwrap(), Some(account));
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_unit_of_work() {
+        use crate::domain::event::DomainEvent;
+        use crate::domain::ledger::LedgerAccount;
+
+        let dir = tempdir().unwrap();
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        EventStore::append(
+            &store,
+            vec![EventRecord::new(2, 1, DomainEvent::AccountOpened)],
+        )
+        .await
+        .unwrap();
+
+        let mut account = ClientAccount::new(1);
+        account.balance_mut(Currency::default()).available = Balance::new(dec!(10.0));
+        let tx = Transaction {
+            r#type: TransactionType::Deposit,
+            client: 1,
+            tx: 7,
+            amount: Some(dec!(10.0).try_into().unwrap()),
+            currency: Currency::default(),
+            destination: None,
+        };
+        let checkpoint = Checkpoint {
+            fingerprint: Fingerprint::new(100, b"type,client,tx,amount"),
+            input: InputPosition {
+                byte: 42,
+                line: 2,
+                record: 1,
+            },
+            position: 1,
+        };
+        let mut fee_account = FeeAccount::default();
+        fee_account
+            .credit(Currency::default(), Balance::new(dec!(0.5)))
+            .unwrap();
+        assert!(CheckpointStore::latest(&store).await.unwrap().is_none());
+        store
+            .commit(UnitOfWork {
+                accounts: vec![account.clone()],
+                transactions: vec![tx.clone()],
+                transaction_ids: vec![8],
+                disputes: vec![DisputeRecord::new(7)],
+                fees: Some(fee_account.clone()),
+                risk: Some(RiskState::new(1)),
+                journal: vec![JournalEntry::new(
+                    7,
+                    Currency::default(),
+                    LedgerAccount::Cash,
+                    LedgerAccount::Available(1),
+                    Balance::new(dec!(10.0)),
+                )],
+                events: vec![EventRecord::new(1, 7, DomainEvent::AccountOpened)],
+                checkpoint: Some(checkpoint),
+            })
+            .await
+            .unwrap();
+        drop(store);
+
+        // Every write is found after a reopen
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        assert_eq!(AccountStore::get(&store, 1).await.unwrap(), Some(account));
+        assert_eq!(TransactionStore::get(&store, 7).await.unwrap(), Some(tx));
+        assert!(TransactionStore::exists(&store, 8).await.unwrap());
+        assert!(TransactionStore::get(&store, 8).await.unwrap().is_none());
+        assert_eq!(
+            store
+                .get_all_ids()
+                .await
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![7, 8]
+        );
+        assert_eq!(
+            store.get_disputes(7).await.unwrap(),
+            Some(DisputeRecord::new(7))
+        );
+        assert_eq!(FeeStore::get(&store).await.unwrap(), fee_account);
+        assert_eq!(
+            RiskStore::get(&store, 1).await.unwrap(),
+            Some(RiskState::new(1))
+        );
+        // The entries are numbered after those appended before
+        assert_eq!(JournalStore::get_all(&store).await.unwrap()[0].seq, 0);
+        assert_eq!(
+            EventStore::get_for_client(&store, 1).await.unwrap()[0].seq,
+            1
+        );
+        assert_eq!(
+            CheckpointStore::latest(&store).await.unwrap(),
+            Some(checkpoint)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_refuses_other_schema_versions() {
+        let dir = tempdir().unwrap();
+
+        // A new database is created at the current version
+        drop(RocksDBStore::open(dir.path()).unwrap());
+        let db = open_db(dir.path()).unwrap();
+        assert_eq!(schema_version(&db).unwrap(), SCHEMA_VERSION);
+
+        set_schema_version(&db, SCHEMA_VERSION + 1).unwrap();
+        drop(db);
+        assert!(matches!(
+            RocksDBStore::open(dir.path()),
+            Err(PaymentError::IncompatibleSchema { found, .. }) if found == SCHEMA_VERSION + 1
+        ));
+        assert!(RocksDBStore::migrate(dir.path()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_migrates_legacy_database() {
+        let dir = tempdir().unwrap();
+        let deposit = |client, tx, amount: Decimal| Transaction {
+            r#type: TransactionType::Deposit,
+            client,
+            tx,
+            amount: Some(amount.try_into().unwrap()),
+            currency: Currency::default(),
+            destination: None,
+        };
+        let balance = |available, held, total| CurrencyBalance {
+            available: Balance::new(available),
+            held: Balance::new(held),
+            total: Balance::new(total),
+        };
+
+        // A database written by the first release: JSON values, with the dispute state in the
+        // transactions, and no metadata. The deposit of client 1 is under dispute, the one of
+        // client 2 was charged back
+        let baseline = [
+            (
+                CF_ACCOUNTS,
+                1u16.to_be_bytes().to_vec(),
+                r#"{"client":1,"available":"0","held":"10.0","total":"10.0","locked":false}"#,
+            ),
+            (
+                CF_ACCOUNTS,
+                2u16.to_be_bytes().to_vec(),
+                r#"{"client":2,"available":"0","held":"0","total":"0","locked":true}"#,
+            ),
+            (
+                CF_TRANSACTIONS,
+                1u32.to_be_bytes().to_vec(),
+                r#"{"type":"deposit","client":1,"tx":1,"amount":"10.0","dispute_status":"Disputed"}"#,
+            ),
+            (
+                CF_TRANSACTIONS,
+                2u32.to_be_bytes().to_vec(),
+                r#"{"type":"deposit","client":2,"tx":2,"amount":"5","dispute_status":"Chargebacked"}"#,
+            ),
+            // Snapshots were added with partial disputes, before schema versioning
+            (
+                CF_SNAPSHOTS,
+                LATEST_SNAPSHOT_KEY.to_vec(),
+                r#"{"position":1,"accounts":[{"client":1,"balances":{"":{"available":"6.0","held":"4.0","total":"10.0"}},"status":"active"}],"transactions":[{"type":"deposit","client":1,"tx":1,"amount":"10.0","currency":"","destination":null,"dispute_status":"Disputed","open_disputed":"4.0","total_disputed":"4.0"}]}"#,
+            ),
+        ];
+        {
+            let db = open_db(dir.path()).unwrap();
+            for (cf_name, key, value) in &baseline {
+                db.put_cf(column_family(&db, cf_name).unwrap(), key, value)
+                    .unwrap();
+            }
+        }
+        let err = RocksDBStore::open(dir.path()).err().unwrap();
+        assert!(err.to_string().contains("migrate"));
+
+        let migration = RocksDBStore::migrate(dir.path()).unwrap();
+        assert_eq!(
+            migration,
+            Migration {
+                from: LEGACY_SCHEMA_VERSION,
+                to: SCHEMA_VERSION,
+                rewritten: 5,
+            }
+        );
+        // Migrating again has nothing left to do
+        let again = RocksDBStore::migrate(dir.path()).unwrap();
+        assert_eq!((again.from, again.rewritten), (SCHEMA_VERSION, 0));
+
+        let store = RocksDBStore::open(dir.path()).unwrap();
+        let accounts = store.column_family(CF_ACCOUNTS).unwrap();
+        let bytes = store
+            .db
+            .get_cf(accounts, 1u16.to_be_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(bytes[0], codec::FORMAT_MAGIC);
+        let mut client1 = ClientAccount::new(1);
+        client1.balances.insert(
+            Currency::default(),
+            balance(dec!(0), dec!(10.0), dec!(10.0)),
+        );
+        let mut client2 = ClientAccount::new(2);
+        client2
+            .balances
+            .insert(Currency::default(), balance(dec!(0), dec!(0), dec!(0)));
+        client2.status = AccountStatus::Locked;
+        assert_eq!(
+            AccountStore::get_all(&store).await.unwrap(),
+            vec![client1, client2]
+        );
+        assert_eq!(
+            TransactionStore::get(&store, 1).await.unwrap(),
+            Some(deposit(1, 1, dec!(10.0)))
+        );
+        assert_eq!(
+            TransactionStore::get(&store, 2).await.unwrap(),
+            Some(deposit(2, 2, dec!(5)))
+        );
+
+        // The dispute state is moved to dispute records covering the whole deposits, so the
+        // open dispute can still be settled
+        assert_eq!(
+            store.get_disputes(1).await.unwrap(),
+            Some(DisputeRecord {
+                tx: 1,
+                disputes: vec![Dispute::open(Balance::new(dec!(10.0)), Balance::ZERO)],
+            })
+        );
+        assert_eq!(
+            store.get_disputes(2).await.unwrap(),
+            Some(DisputeRecord {
+                tx: 2,
+                disputes: vec![Dispute {
+                    amount: Balance::new(dec!(5)),
+                    shortfall: Balance::ZERO,
+                    status: DisputeStatus::Chargebacked,
+                }],
+            })
+        );
+
+        // And in the snapshot
+        let snapshot = SnapshotStore::latest(&store).await.unwrap().unwrap();
+        assert_eq!(snapshot.transactions, vec![deposit(1, 1, dec!(10.0))]);
+        assert_eq!(snapshot.transaction_ids.iter().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(
+            snapshot.disputes,
+            vec![DisputeRecord {
+                tx: 1,
+                disputes: vec![Dispute::open(Balance::new(dec!(4.0)), Balance::ZERO)],
+            }]
+        );
     }
 }
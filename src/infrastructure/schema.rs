@@ -0,0 +1,89 @@
+//! Versioning of the layout and encoding of persistent databases.
+//!
+//! | Version | Layout                                                                  |
+//! |---------|-------------------------------------------------------------------------|
+//! | 1       | JSON values, no metadata (databases created before schema versioning)   |
+//! | 2       | Accounts and transactions in the binary encoding of [`super::codec`]    |
+//! | 3       | Dispute state in dispute records, apart from the transactions           |
+
+use crate::error::{PaymentError, Result};
+use std::fmt;
+
+/// The schema version written and read by this build.
+pub const SCHEMA_VERSION: u32 = 3;
+/// The version of databases holding data but no schema version.
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+/// Fails unless a database at schema version `found` can be opened by this build.
+pub fn check(found: u32) -> Result<()> {
+    if found == SCHEMA_VERSION {
+        Ok(())
+    } else {
+        Err(PaymentError::IncompatibleSchema {
+            found,
+            supported: SCHEMA_VERSION,
+        })
+    }
+}
+
+/// Encodes a schema version as stored in the metadata of a database.
+pub fn encode_version(version: u32) -> [u8; 4] {
+    version.to_be_bytes()
+}
+
+/// Decodes a stored schema version.
+pub fn decode_version(bytes: &[u8]) -> Result<u32> {
+    let bytes: [u8; 4] = bytes.try_into().map_err(|_| {
+        PaymentError::InternalError(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid schema version of {} byte(s)", bytes.len()),
+        )))
+    })?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+/// The outcome of upgrading a database to [`SCHEMA_VERSION`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Migration {
+    /// The schema version the database was at.
+    pub from: u32,
+    /// The schema version the database is now at.
+    pub to: u32,
+    /// The number of stored values rewritten.
+    pub rewritten: u64,
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.from == self.to {
+            write!(f, "already at schema version {}", self.to)
+        } else {
+            write!(
+                f,
+                "upgraded from schema version {} to {} ({} value(s) rewritten)",
+                self.from, self.to, self.rewritten
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_schema_version() {
+        assert!(check(SCHEMA_VERSION).is_ok());
+
+        let older = check(LEGACY_SCHEMA_VERSION).unwrap_err();
+        assert!(older.to_string().contains("migrate"));
+        let newer = check(SCHEMA_VERSION + 1).unwrap_err();
+        assert!(newer.to_string().contains("newer"));
+    }
+
+    #[test]
+    fn test_version_encoding() {
+        assert_eq!(decode_version(&encode_version(7)).unwrap(), 7);
+        assert!(decode_version(b"v2").is_err());
+    }
+}
@@ -1,15 +1,84 @@
-use crate::domain::account::ClientAccount;
+use crate::domain::account::{
+    AccountStatus, Balance, ClientAccount, CurrencyBalance, LockCause, LockReason,
+};
+use crate::domain::currency::Currency;
+use crate::domain::fee::FeeAccount;
+use crate::domain::transaction::TransactionType;
 use crate::error::Result;
+use serde::Serialize;
 use std::io::{BufWriter, Write};
 
 const OUTPUT_BUFFER_SIZE: usize = 8192;
 
+/// The `client` column value of the system fee account rows.
+pub const FEE_ACCOUNT_LABEL: &str = "fees";
+
+/// The holder of the balances of an output row.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Holder {
+    Client(u16),
+    System(&'static str),
+}
+
+/// A single output row: the balances of one client in one currency.
+#[derive(Serialize)]
+struct AccountRow {
+    client: Holder,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    currency: Option<Currency>,
+    available: Balance,
+    held: Balance,
+    total: Balance,
+    locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<AccountStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overdraft: Option<Balance>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reserve: Option<Balance>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    receivable: Option<Balance>,
+    // The lock columns are empty (inner `None`) for accounts that are not locked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lock_tx: Option<Option<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lock_type: Option<Option<TransactionType>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lock_cause: Option<Option<LockCause>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lock_position: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locked_at: Option<Option<u64>>,
+}
+
+impl AccountRow {
+    /// Fills the lock columns (if `lock` is set) from the lock reason of the account.
+    fn with_lock(self, lock: Option<Option<LockReason>>) -> Self {
+        Self {
+            lock_tx: lock.map(|reason| reason.map(|reason| reason.tx)),
+            lock_type: lock.map(|reason| reason.map(|reason| reason.r#type)),
+            lock_cause: lock.map(|reason| reason.map(|reason| reason.cause)),
+            lock_position: lock.map(|reason| reason.map(|reason| reason.position)),
+            locked_at: lock.map(|reason| reason.and_then(|reason| reason.timestamp)),
+            ..self
+        }
+    }
+}
+
 /// Writes client account states to a CSV sink.
 ///
 /// Wraps `csv::Writer` with a `BufWriter` to ensure efficient I/O operations,
 /// especially when writing to stdout.
 pub struct AccountWriter<W: Write> {
     writer: csv::Writer<BufWriter<W>>,
+    with_status: bool,
+    with_lock_details: bool,
+    scale: Option<u32>,
+    /// Optional columns of the rows written so far, reused by the fee account rows.
+    with_currency: bool,
+    with_limits: bool,
+    with_receivables: bool,
 }
 
 impl<W: Write> AccountWriter<W> {
@@ -19,18 +88,153 @@ impl<W: Write> AccountWriter<W> {
     pub fn new(sink: W) -> Self {
         Self {
             writer: csv::Writer::from_writer(BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, sink)),
+            with_status: false,
+            with_lock_details: false,
+            scale: None,
+            with_currency: false,
+            with_limits: false,
+            with_receivables: false,
+        }
+    }
+
+    /// Adds a trailing `status` column with the full account status.
+    ///
+    /// The `locked` column is kept for backwards compatibility.
+    pub fn with_status_column(mut self) -> Self {
+        self.with_status = true;
+        self
+    }
+
+    /// Adds trailing `lock_tx`, `lock_type`, `lock_cause`, `lock_position` and `locked_at`
+    /// columns, telling which transaction locked an account, at which input position and when.
+    ///
+    /// The columns are empty for accounts that are not locked, and `locked_at` for locks
+    /// recorded without a timestamp.
+    pub fn with_lock_details(mut self) -> Self {
+        self.with_lock_details = true;
+        self
+    }
+
+    /// Prints every balance with exactly `scale` decimal places, so the output does not
+    /// depend on the precision of the input amounts.
+    ///
+    /// Balances with more decimal places are rounded half to even.
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    fn format(&self, balance: Balance) -> Balance {
+        match self.scale {
+            Some(scale) => {
+                let mut value = balance.0.round_dp(scale);
+                value.rescale(scale);
+                Balance(value)
+            }
+            None => balance,
         }
     }
 
     /// Serializes and writes a collection of accounts to the underlying sink.
     ///
+    /// Emits one row per client-currency pair. The `locked` column is `true` for any status other
+    /// than `Active`. The `currency` column is only included when
+    /// some balance is in an explicit currency, the `overdraft` and `reserve` columns when
+    /// some account has credit limits, and the `receivable` column when some client owes
+    /// disputed funds, so the output keeps its original layout otherwise.
     /// Flushes the writer after processing all accounts.
     pub fn write_accounts(
         &mut self,
         accounts: impl IntoIterator<Item = ClientAccount>,
     ) -> Result<()> {
+        let accounts: Vec<ClientAccount> = accounts.into_iter().collect();
+        let with_currency = accounts
+            .iter()
+            .flat_map(|account| account.balances.keys())
+            .any(|currency| !currency.is_default());
+        let with_limits = accounts.iter().any(|account| !account.limits.is_default());
+        let with_receivables = accounts
+            .iter()
+            .any(|account| !account.receivables.is_empty());
+        self.with_currency = with_currency;
+        self.with_limits = with_limits;
+        self.with_receivables = with_receivables;
+
         for account in accounts {
-            self.writer.serialize(account)?;
+            // Any status restricting the account is reported as locked
+            let locked = account.status != AccountStatus::Active;
+            let mut balances: Vec<(Currency, CurrencyBalance)> =
+                account.balances.into_iter().collect();
+            // Clients without any balance still get a (zero) row
+            if balances.is_empty() {
+                balances.push((Currency::default(), CurrencyBalance::default()));
+            }
+            for (currency, balance) in balances {
+                self.writer.serialize(
+                    AccountRow {
+                        client: Holder::Client(account.client),
+                        currency: with_currency.then_some(currency),
+                        available: self.format(balance.available),
+                        held: self.format(balance.held),
+                        total: self.format(balance.total),
+                        locked,
+                        status: self.with_status.then_some(account.status),
+                        overdraft: with_limits.then(|| self.format(account.limits.overdraft)),
+                        reserve: with_limits.then(|| self.format(account.limits.reserve)),
+                        receivable: with_receivables.then(|| {
+                            self.format(
+                                account
+                                    .receivables
+                                    .get(&currency)
+                                    .copied()
+                                    .unwrap_or(Balance::ZERO),
+                            )
+                        }),
+                        lock_tx: None,
+                        lock_type: None,
+                        lock_cause: None,
+                        lock_position: None,
+                        locked_at: None,
+                    }
+                    .with_lock(self.with_lock_details.then_some(account.lock_reason)),
+                )?;
+            }
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the system fee account after the client rows, with `fees` in the `client`
+    /// column so it cannot be mistaken for a client.
+    ///
+    /// Emits one row per currency (a zero row if no fee was collected), in the same layout as
+    /// the preceding client rows. Fees are never held, so `available` and `total` are equal.
+    pub fn write_fee_account(&mut self, fees: FeeAccount) -> Result<()> {
+        let mut balances: Vec<(Currency, Balance)> = fees.balances.into_iter().collect();
+        if balances.is_empty() {
+            balances.push((Currency::default(), Balance::ZERO));
+        }
+        for (currency, balance) in balances {
+            self.writer.serialize(
+                AccountRow {
+                    client: Holder::System(FEE_ACCOUNT_LABEL),
+                    currency: self.with_currency.then_some(currency),
+                    available: self.format(balance),
+                    held: self.format(Balance::ZERO),
+                    total: self.format(balance),
+                    locked: false,
+                    status: self.with_status.then_some(AccountStatus::Active),
+                    overdraft: self.with_limits.then(|| self.format(Balance::ZERO)),
+                    reserve: self.with_limits.then(|| self.format(Balance::ZERO)),
+                    receivable: self.with_receivables.then(|| self.format(Balance::ZERO)),
+                    lock_tx: None,
+                    lock_type: None,
+                    lock_cause: None,
+                    lock_position: None,
+                    locked_at: None,
+                }
+                .with_lock(self.with_lock_details.then_some(None)),
+            )?;
         }
         self.writer.flush()?;
         Ok(())
@@ -94,13 +298,10 @@ mod tests {
         // Clone for the writer to keep a reference to the counter
         let mut writer = AccountWriter::new(cw.clone());
 
-        let account = ClientAccount {
-            client: 1,
-            available: crate::domain::account::Balance(dec!(1.0)),
-            held: crate::domain::account::Balance(dec!(0.0)),
-            total: crate::domain::account::Balance(dec!(1.0)),
-            status: crate::domain::account::AccountStatus::Active,
-        };
+        let mut account = ClientAccount::new(1);
+        account
+            .deposit(Currency::default(), Balance(dec!(1.0)))
+            .unwrap();
 
         let records_count = 100;
         let records: Vec<_> = (0..records_count).map(|_| account.clone()).collect();
@@ -122,12 +323,11 @@ mod tests {
         let mut buf = Vec::new();
         {
             let mut writer = AccountWriter::new(&mut buf);
-            let account = ClientAccount {
-                client: 1,
-                available: crate::domain::account::Balance(dec!(1.5000)),
-                held: crate::domain::account::Balance(dec!(0.0000)),
-                total: crate::domain::account::Balance(dec!(1.5000)),
-                status: crate::domain::account::AccountStatus::Active,
+            let mut account = ClientAccount::new(1);
+            *account.balance_mut(Currency::default()) = CurrencyBalance {
+                available: Balance(dec!(1.5000)),
+                held: Balance(dec!(0.0000)),
+                total: Balance(dec!(1.5000)),
             };
 
             writer.write_accounts(vec![account]).unwrap();
@@ -142,4 +342,160 @@ mod tests {
                 || output.contains("1,1.5000,0.0000,1.5000,false")
         );
     }
+
+    #[test]
+    fn test_writer_output_per_currency() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = AccountWriter::new(&mut buf);
+            let mut account = ClientAccount::new(1);
+            account
+                .deposit(Currency::new("EUR").unwrap(), Balance(dec!(1.5)))
+                .unwrap();
+            account
+                .deposit(Currency::new("USD").unwrap(), Balance(dec!(2)))
+                .unwrap();
+
+            writer.write_accounts(vec![account]).unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            output,
+            "client,currency,available,held,total,locked\n\
+             1,EUR,1.5,0,1.5,false\n\
+             1,USD,2,0,2,false\n"
+        );
+    }
+
+    #[test]
+    fn test_writer_output_with_status() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = AccountWriter::new(&mut buf).with_status_column();
+            let mut account = ClientAccount::new(1);
+            account.status = AccountStatus::Frozen;
+
+            writer.write_accounts(vec![account]).unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            output,
+            "client,available,held,total,locked,status\n1,0,0,0,true,frozen\n"
+        );
+    }
+
+    #[test]
+    fn test_writer_output_fixed_scale() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = AccountWriter::new(&mut buf).with_scale(4);
+            let mut account = ClientAccount::new(1);
+            account
+                .deposit(Currency::default(), Balance(dec!(1.5)))
+                .unwrap();
+            account
+                .deposit(Currency::default(), Balance(dec!(0.000025)))
+                .unwrap();
+
+            writer.write_accounts(vec![account]).unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert!(output.contains("1,1.5000,0.0000,1.5000,false"));
+    }
+
+    #[test]
+    fn test_writer_output_with_limits() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = AccountWriter::new(&mut buf);
+            let mut account = ClientAccount::new(1);
+            account.limits.overdraft = Balance(dec!(100));
+
+            writer
+                .write_accounts(vec![account, ClientAccount::new(2)])
+                .unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert!(output.contains("client,available,held,total,locked,overdraft,reserve"));
+        assert!(output.contains("1,0,0,0,false,100,0"));
+        assert!(output.contains("2,0,0,0,false,0,0"));
+    }
+
+    #[test]
+    fn test_writer_output_with_fee_account() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = AccountWriter::new(&mut buf);
+            let mut account = ClientAccount::new(1);
+            account
+                .deposit(Currency::default(), Balance(dec!(9)))
+                .unwrap();
+            let mut fees = FeeAccount::default();
+            fees.credit(Currency::default(), Balance(dec!(1))).unwrap();
+
+            writer.write_accounts(vec![account]).unwrap();
+            writer.write_fee_account(fees).unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            output,
+            "client,available,held,total,locked\n\
+             1,9,0,9,false\n\
+             fees,1,0,1,false\n"
+        );
+    }
+
+    #[test]
+    fn test_writer_output_with_receivables() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = AccountWriter::new(&mut buf);
+            let mut account = ClientAccount::new(1);
+            account
+                .add_receivable(Currency::default(), Balance(dec!(2.5)))
+                .unwrap();
+
+            writer
+                .write_accounts(vec![account, ClientAccount::new(2)])
+                .unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert!(output.contains("client,available,held,total,locked,receivable"));
+        assert!(output.contains("1,0,0,0,false,2.5"));
+        assert!(output.contains("2,0,0,0,false,0"));
+    }
+
+    #[test]
+    fn test_writer_output_with_lock_details() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = AccountWriter::new(&mut buf).with_lock_details();
+            let mut account = ClientAccount::new(1);
+            account.lock(LockReason {
+                tx: 4,
+                r#type: TransactionType::Chargeback,
+                cause: LockCause::Chargeback,
+                position: 9,
+                timestamp: Some(1700000000),
+            });
+
+            writer
+                .write_accounts(vec![account, ClientAccount::new(2)])
+                .unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            output,
+            "client,available,held,total,locked,lock_tx,lock_type,lock_cause,lock_position,locked_at\n\
+             1,0,0,0,true,4,chargeback,chargeback,9,1700000000\n\
+             2,0,0,0,false,,,,,\n"
+        );
+    }
 }
@@ -0,0 +1,67 @@
+use crate::domain::ledger::JournalEntry;
+use crate::error::Result;
+use std::io::{BufWriter, Write};
+
+const OUTPUT_BUFFER_SIZE: usize = 8192;
+
+/// Writes double-entry journal entries to a CSV sink.
+pub struct JournalWriter<W: Write> {
+    writer: csv::Writer<BufWriter<W>>,
+}
+
+impl<W: Write> JournalWriter<W> {
+    /// Creates a new `JournalWriter` from any `Write` sink and writes the header row.
+    pub fn new(sink: W) -> Result<Self> {
+        let mut writer =
+            csv::Writer::from_writer(BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, sink));
+        writer.write_record(["seq", "tx", "currency", "debit", "credit", "amount"])?;
+        Ok(Self { writer })
+    }
+
+    /// Writes all entries and flushes the underlying sink.
+    pub fn write_entries(&mut self, entries: &[JournalEntry]) -> Result<()> {
+        for entry in entries {
+            self.writer.write_record([
+                entry.seq.to_string(),
+                entry.tx.to_string(),
+                entry.currency.to_string(),
+                entry.debit.to_string(),
+                entry.credit.to_string(),
+                entry.amount.0.normalize().to_string(),
+            ])?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::account::Balance;
+    use crate::domain::currency::Currency;
+    use crate::domain::ledger::LedgerAccount;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_journal_writer_output() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = JournalWriter::new(&mut buf).unwrap();
+            let entry = JournalEntry::new(
+                7,
+                Currency::new("EUR").unwrap(),
+                LedgerAccount::Cash,
+                LedgerAccount::Available(1),
+                Balance::new(dec!(1.50)),
+            );
+            writer.write_entries(&[entry]).unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            output,
+            "seq,tx,currency,debit,credit,amount\n0,7,EUR,system:cash,client:1:available,1.5\n"
+        );
+    }
+}
@@ -1,2 +1,4 @@
 pub mod account_writer;
+pub mod journal_writer;
+pub mod reject_writer;
 pub mod transaction_reader;
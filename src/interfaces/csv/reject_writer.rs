@@ -0,0 +1,92 @@
+use crate::error::{PaymentError, Result};
+use std::io::{BufWriter, Write};
+
+const OUTPUT_BUFFER_SIZE: usize = 8192;
+
+/// Reason code for input rows that could not be parsed into a transaction.
+pub const MALFORMED_RECORD: &str = "malformed_record";
+
+/// Reason code for input rows whose amount exceeds the maximum transaction amount.
+pub const AMOUNT_LIMIT_EXCEEDED: &str = "amount_limit_exceeded";
+
+/// Reason code for transactions failed by strict mode because they would break an account
+/// invariant.
+pub const INVARIANT_VIOLATION: &str = "invariant_violation";
+
+/// Reason code for transactions the engine failed to process for any other reason.
+pub const PROCESSING_ERROR: &str = "processing_error";
+
+/// Returns the reason code of a transaction the engine failed to process.
+pub fn engine_error_code(error: &PaymentError) -> &'static str {
+    match error {
+        PaymentError::InvariantViolation(_) => INVARIANT_VIOLATION,
+        _ => PROCESSING_ERROR,
+    }
+}
+
+/// Writes rejected input rows to a CSV sink.
+///
+/// Each row records the input line number, a machine-readable reason code and the
+/// original text of the input record in a single column, so the report can be reconciled
+/// against the input file.
+pub struct RejectWriter<W: Write> {
+    writer: csv::Writer<BufWriter<W>>,
+}
+
+impl<W: Write> RejectWriter<W> {
+    /// Creates a new `RejectWriter` from any `Write` sink and writes the header row.
+    pub fn new(sink: W) -> Result<Self> {
+        let mut writer =
+            csv::Writer::from_writer(BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, sink));
+        writer.write_record(["line", "reason", "record"])?;
+        Ok(Self { writer })
+    }
+
+    /// Writes a single rejected row, given the original text of its input record.
+    pub fn write_reject(&mut self, line: u64, reason: &str, record: &str) -> Result<()> {
+        self.writer
+            .write_record([line.to_string().as_str(), reason, record])?;
+        Ok(())
+    }
+
+    /// Flushes any buffered rows to the underlying sink.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject_writer_output() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = RejectWriter::new(&mut buf).unwrap();
+            writer
+                .write_reject(3, "insufficient_funds", "withdrawal, 1, \"2\", 5.00")
+                .unwrap();
+            writer.flush().unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            output,
+            "line,reason,record\n3,insufficient_funds,\"withdrawal, 1, \"\"2\"\", 5.00\"\n"
+        );
+    }
+
+    #[test]
+    fn test_engine_error_code() {
+        assert_eq!(
+            engine_error_code(&PaymentError::InvariantViolation(Vec::new())),
+            INVARIANT_VIOLATION
+        );
+        assert_eq!(
+            engine_error_code(&PaymentError::ValidationError("closed".to_string())),
+            PROCESSING_ERROR
+        );
+    }
+}
@@ -1,23 +1,44 @@
+use crate::domain::checkpoint::InputPosition;
 use crate::domain::transaction::Transaction;
 use crate::error::{PaymentError, Result};
-use std::io::Read;
+use rust_decimal::Decimal;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex, PoisonError};
 
 /// Reads transactions from a CSV source.
 ///
 /// This reader wraps `csv::Reader` and provides an iterator over `Result<Transaction>`.
 /// It handles whitespace trimming and flexible record lengths automatically.
 pub struct TransactionReader<R: Read> {
-    reader: csv::Reader<R>,
+    reader: csv::Reader<RecordingReader<R>>,
+    raw: Arc<Mutex<RawInput>>,
+    max_amount: Option<Decimal>,
 }
 
 impl<R: Read> TransactionReader<R> {
     /// Creates a new `TransactionReader` from any `Read` source (e.g., File, Stdin).
     pub fn new(source: R) -> Self {
+        let raw = Arc::new(Mutex::new(RawInput::default()));
         let reader = csv::ReaderBuilder::new()
             .trim(csv::Trim::All)
             .flexible(true)
-            .from_reader(source);
-        Self { reader }
+            .from_reader(RecordingReader {
+                inner: source,
+                raw: Arc::clone(&raw),
+            });
+        Self {
+            reader,
+            raw,
+            max_amount: None,
+        }
+    }
+
+    /// Rejects transactions whose amount exceeds `limit` with `PaymentError::AmountLimitExceeded`.
+    ///
+    /// Bounding the amounts keeps hostile input from overflowing the balances it is applied to.
+    pub fn with_max_amount(mut self, limit: Decimal) -> Self {
+        self.max_amount = Some(limit);
+        self
     }
 
     /// Returns an iterator that lazily reads and deserializes transactions.
@@ -25,12 +46,182 @@ impl<R: Read> TransactionReader<R> {
     /// This allows for processing large files in a streaming fashion without loading
     /// the entire dataset into memory.
     pub fn transactions(self) -> impl Iterator<Item = Result<Transaction>> {
-        self.reader
-            .into_deserialize()
-            .map(|result| result.map_err(PaymentError::from))
+        let max_amount = self.max_amount;
+        self.reader.into_deserialize().map(move |result| {
+            result
+                .map_err(PaymentError::from)
+                .and_then(|tx| check_amount(tx, max_amount))
+        })
+    }
+
+    /// Returns an iterator that lazily reads transactions along with their source record.
+    ///
+    /// Unlike [`Self::transactions`], each item keeps the input line number and the original
+    /// text of the record, so rows that fail to parse or have no effect can be reported back to
+    /// the user, and the position right after the record, to resume the input from.
+    pub fn records(mut self) -> impl Iterator<Item = TransactionRecord> {
+        self.raw
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .recording = true;
+        let headers = self.reader.headers().cloned();
+        let max_amount = self.max_amount;
+        let mut record = csv::StringRecord::new();
+        std::iter::from_fn(move || {
+            let result = self.reader.read_record(&mut record);
+            let end = input_position(self.reader.position());
+            let start = match &result {
+                Ok(_) => record.position(),
+                Err(e) => e.position(),
+            };
+            let raw = self
+                .raw
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take(start.map_or(end.byte, |p| p.byte()), end.byte);
+            match result {
+                Ok(false) => return None,
+                Ok(true) => {}
+                Err(e) => {
+                    return Some(TransactionRecord {
+                        line: e.position().map_or(0, |p| p.line()),
+                        raw,
+                        transaction: Err(e.into()),
+                        end,
+                    });
+                }
+            }
+            let transaction = match &headers {
+                Ok(headers) => record
+                    .deserialize(Some(headers))
+                    .map_err(PaymentError::from)
+                    .and_then(|tx| check_amount(tx, max_amount)),
+                Err(e) => Err(PaymentError::ValidationError(format!(
+                    "Invalid CSV headers: {}",
+                    e
+                ))),
+            };
+            Some(TransactionRecord {
+                line: record.position().map_or(0, |p| p.line()),
+                raw,
+                transaction,
+                end,
+            })
+        })
+    }
+}
+
+impl<R: Read + Seek> TransactionReader<R> {
+    /// Moves the reader to a position returned by a previous read of the same input, such as
+    /// the end of a [`TransactionRecord`], so the records before it are skipped without being
+    /// parsed.
+    ///
+    /// The headers are read first, so the records after the position are still mapped to
+    /// their columns.
+    pub fn seek(&mut self, position: InputPosition) -> Result<()> {
+        self.reader.headers()?;
+        let mut target = csv::Position::new();
+        target
+            .set_byte(position.byte)
+            .set_line(position.line)
+            .set_record(position.record);
+        self.reader.seek(target)?;
+        Ok(())
+    }
+}
+
+/// Reads the input while keeping a copy of the bytes read, once recording, so the original
+/// text of each record can be recovered after the CSV reader parsed it.
+struct RecordingReader<R> {
+    inner: R,
+    raw: Arc<Mutex<RawInput>>,
+}
+
+/// The bytes of the input read but not yet taken as part of a record.
+#[derive(Default)]
+struct RawInput {
+    recording: bool,
+    /// The position in the input of the first byte of `bytes`.
+    offset: u64,
+    bytes: Vec<u8>,
+}
+
+impl RawInput {
+    /// Returns the text of the input between two positions, without the line terminators around
+    /// it, and drops the bytes before `end`.
+    fn take(&mut self, start: u64, end: u64) -> String {
+        let index = |position: u64| {
+            usize::try_from(position.saturating_sub(self.offset))
+                .map_or(self.bytes.len(), |index| index.min(self.bytes.len()))
+        };
+        let (from, to) = (index(start), index(end));
+        let text = String::from_utf8_lossy(&self.bytes[from.min(to)..to])
+            .trim_matches(['\r', '\n'])
+            .to_string();
+        self.bytes.drain(..to);
+        self.offset += to as u64;
+        text
+    }
+}
+
+impl<R: Read> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let mut raw = self.raw.lock().unwrap_or_else(PoisonError::into_inner);
+        if raw.recording {
+            raw.bytes.extend_from_slice(&buf[..read]);
+        } else {
+            raw.offset += read as u64;
+        }
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for RecordingReader<R> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let offset = self.inner.seek(position)?;
+        let mut raw = self.raw.lock().unwrap_or_else(PoisonError::into_inner);
+        raw.offset = offset;
+        raw.bytes.clear();
+        Ok(offset)
+    }
+}
+
+/// Converts a position of the CSV reader.
+fn input_position(position: &csv::Position) -> InputPosition {
+    InputPosition {
+        byte: position.byte(),
+        line: position.line(),
+        record: position.record(),
+    }
+}
+
+/// Fails if the amount of the transaction exceeds the limit, if any.
+fn check_amount(tx: Transaction, limit: Option<Decimal>) -> Result<Transaction> {
+    match (tx.amount, limit) {
+        (Some(amount), Some(limit)) if amount.value() > limit => {
+            Err(PaymentError::AmountLimitExceeded {
+                amount: amount.value(),
+                limit,
+            })
+        }
+        _ => Ok(tx),
     }
 }
 
+/// A transaction read from the input, along with the record it was parsed from.
+#[derive(Debug)]
+pub struct TransactionRecord {
+    /// The 1-based line number of the record in the input.
+    pub line: u64,
+    /// The original text of the record, as found in the input, without its line terminator.
+    pub raw: String,
+    /// The parsed transaction, or the reason it could not be parsed.
+    pub transaction: Result<Transaction>,
+    /// The position in the input right after the record.
+    pub end: InputPosition,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -56,4 +247,63 @@ mod tests {
 
         assert!(results[0].is_err());
     }
+
+    #[test]
+    fn test_reader_records_keep_source() {
+        let data = "type, client, tx, amount\ndeposit, 1, 1, 1.0\ninvalid, 1, 2, 1.0";
+        let reader = TransactionReader::new(data.as_bytes());
+        let records: Vec<TransactionRecord> = reader.records().collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].line, 2);
+        assert_eq!(records[0].raw, "deposit, 1, 1, 1.0");
+        assert_eq!(records[0].transaction.as_ref().unwrap().tx, 1);
+
+        assert_eq!(records[1].line, 3);
+        assert_eq!(records[1].raw, "invalid, 1, 2, 1.0");
+        assert!(records[1].transaction.is_err());
+    }
+
+    #[test]
+    fn test_reader_records_keep_raw_text() {
+        let data =
+            "type,client,tx,amount\r\n\"deposit\", 1 ,1,1.5000\r\n\r\nwithdrawal,1,2,\"0.10\"";
+        let reader = TransactionReader::new(data.as_bytes());
+        let records: Vec<TransactionRecord> = reader.records().collect();
+
+        // Quoting, spacing and the scale of the amount are kept as they were in the input
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].raw, "\"deposit\", 1 ,1,1.5000");
+        assert_eq!(records[1].raw, "withdrawal,1,2,\"0.10\"");
+    }
+
+    #[test]
+    fn test_reader_max_amount() {
+        let data = "type, client, tx, amount\ndeposit, 1, 1, 100\ndeposit, 1, 2, 100.0001";
+        let reader = TransactionReader::new(data.as_bytes()).with_max_amount(dec!(100));
+        let records: Vec<TransactionRecord> = reader.records().collect();
+
+        assert!(records[0].transaction.is_ok());
+        assert!(matches!(
+            records[1].transaction,
+            Err(PaymentError::AmountLimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reader_seek_resumes_after_record() {
+        let data = "type, client, tx, amount\ndeposit, 1, 1, 1.0\ndeposit, 1, 2, 2.0\n";
+        let records: Vec<TransactionRecord> =
+            TransactionReader::new(data.as_bytes()).records().collect();
+        assert_eq!(records[1].end.byte, data.len() as u64);
+
+        let mut reader = TransactionReader::new(std::io::Cursor::new(data));
+        reader.seek(records[0].end).unwrap();
+        let resumed: Vec<TransactionRecord> = reader.records().collect();
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].line, 3);
+        assert_eq!(resumed[0].transaction.as_ref().unwrap().tx, 2);
+        assert_eq!(resumed[0].raw, "deposit, 1, 2, 2.0");
+        assert_eq!(resumed[0].end, records[1].end);
+    }
 }
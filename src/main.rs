@@ -1,21 +1,48 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use hc190aop::application::config::{
+    AutoLockPolicy, DisputeEligibility, DisputeFundsPolicy, EngineConfig, LockedDisputePolicy,
+    PrecisionMode, PrecisionPolicy,
+};
 use hc190aop::application::engine::PaymentEngine;
-use hc190aop::domain::ports::{AccountStoreBox, TransactionStoreBox};
-use hc190aop::infrastructure::in_memory::{InMemoryAccountStore, InMemoryTransactionStore};
+use hc190aop::domain::checkpoint::Checkpoint;
+use hc190aop::domain::outcome::TransactionOutcome;
+use hc190aop::domain::ports::{
+    AccountStoreBox, CheckpointStoreBox, EventStoreBox, FeeStoreBox, JournalStoreBox, RiskStoreBox,
+    SnapshotStoreBox, TransactionStoreBox, UnitOfWorkStoreBox,
+};
+use hc190aop::error::PaymentError;
+use hc190aop::infrastructure::file::{
+    FileSnapshotStore, fingerprint_file, load_fee_schedule, load_limits, load_risk_rules,
+};
+use hc190aop::infrastructure::in_memory::{
+    InMemoryAccountStore, InMemoryEventStore, InMemoryFeeStore, InMemoryJournalStore,
+    InMemoryRiskStore, InMemoryTransactionStore, InMemoryUnitOfWork,
+};
 #[cfg(feature = "storage-rocksdb")]
 use hc190aop::infrastructure::rocksdb::RocksDBStore;
 use hc190aop::interfaces::csv::account_writer::AccountWriter;
+use hc190aop::interfaces::csv::journal_writer::JournalWriter;
+use hc190aop::interfaces::csv::reject_writer::{
+    AMOUNT_LIMIT_EXCEEDED, MALFORMED_RECORD, RejectWriter, engine_error_code,
+};
 use hc190aop::interfaces::csv::transaction_reader::TransactionReader;
 use miette::{IntoDiagnostic, Result};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Input transactions CSV file
-    input: PathBuf,
+    #[arg(required_unless_present_any = ["replay", "verify"])]
+    input: Option<PathBuf>,
 
     /// Path to persistent database (optional). If provided, uses RocksDB.
     #[arg(long, conflicts_with = "in_memory")]
@@ -24,26 +51,251 @@ struct Cli {
     /// Force in-memory storage, even for large files.
     #[arg(long, conflicts_with = "db_path")]
     in_memory: bool,
+
+    /// Which transaction types can be disputed.
+    #[arg(long, value_enum, default_value_t = DisputeEligibility::Deposits)]
+    dispute_eligibility: DisputeEligibility,
+
+    /// What to do with a dispute when the client no longer has the funds to cover the hold.
+    #[arg(long, value_enum, default_value_t = DisputeFundsPolicy::Reject)]
+    dispute_funds: DisputeFundsPolicy,
+
+    /// Which dispute operations still apply to locked accounts (deposits, withdrawals and
+    /// transfers are always refused).
+    #[arg(long, value_enum, default_value_t = LockedDisputePolicy::Block)]
+    locked_disputes: LockedDisputePolicy,
+
+    /// Lock the account of a client with more than N transactions under an open dispute.
+    #[arg(long, value_name = "N")]
+    lock_after_disputes: Option<u32>,
+
+    /// Lock the account of a client having disputed more than AMOUNT in a currency.
+    #[arg(long, value_name = "AMOUNT")]
+    lock_after_disputed_value: Option<Decimal>,
+
+    /// Add a `status` column (active, frozen, locked, closed) to the output.
+    #[arg(long)]
+    with_status: bool,
+
+    /// Add columns telling which transaction locked an account, at which input position and
+    /// (with `--lock-timestamps`) when, to the output.
+    #[arg(long)]
+    with_lock_details: bool,
+
+    /// Record the time at which accounts are locked.
+    #[arg(long)]
+    lock_timestamps: bool,
+
+    /// Write rows that failed to parse or had no effect to this CSV file.
+    #[arg(long)]
+    rejects: Option<PathBuf>,
+
+    /// Record every balance movement in a double-entry journal, write it to this CSV file and
+    /// fail if the trial balance does not hold.
+    #[arg(long)]
+    journal: Option<PathBuf>,
+
+    /// Record every applied transaction as domain events and derive the accounts from them.
+    #[arg(long)]
+    event_sourced: bool,
+
+    /// Rebuild the accounts from the event log of the database before processing the input.
+    /// Implies `--event-sourced`.
+    #[arg(long, requires = "db_path")]
+    replay: bool,
+
+    /// Save a snapshot of the state every N transactions, and at the end of the input.
+    #[arg(long, value_name = "N")]
+    snapshot_every: Option<u64>,
+
+    /// Keep snapshots in this file instead of the database (required for in-memory storage).
+    #[arg(long)]
+    snapshot_file: Option<PathBuf>,
+
+    /// Restore the latest snapshot and only process the input transactions after it. Without a
+    /// database, the journal, the event log and the risk rules cannot be enabled.
+    #[arg(long)]
+    restore: bool,
+
+    /// Resume an interrupted run on the same input and database, skipping the records it
+    /// already processed.
+    #[arg(long, requires = "db_path", conflicts_with = "restore")]
+    resume: bool,
+
+    /// Fail transactions that would leave an account violating its invariants, instead of
+    /// only reporting them.
+    #[arg(long)]
+    strict: bool,
+
+    /// Scan all accounts of the store (after processing the input, if any) and fail if any
+    /// of them violates its invariants.
+    #[arg(long)]
+    verify: bool,
+
+    /// Maximum number of decimal places of input amounts (any precision is accepted when
+    /// unset).
+    #[arg(long, value_name = "N")]
+    precision: Option<u32>,
+
+    /// How amounts with more decimal places than `--precision` are handled.
+    #[arg(long, value_enum, default_value_t = PrecisionMode::Reject, requires = "precision")]
+    precision_mode: PrecisionMode,
+
+    /// Print balances with exactly N decimal places.
+    #[arg(long, value_name = "N")]
+    output_scale: Option<u32>,
+
+    /// Reject input rows with an amount above this limit.
+    #[arg(long, value_name = "AMOUNT")]
+    max_amount: Option<Decimal>,
+
+    /// Evaluate the risk rules configured in this TOML file before applying transactions.
+    #[arg(long)]
+    risk_rules: Option<PathBuf>,
+
+    /// Give the accounts of the clients listed in this CSV or TOML file an overdraft ceiling
+    /// or a minimum reserve balance when they are opened.
+    #[arg(long)]
+    limits: Option<PathBuf>,
+
+    /// Charge the fees configured in this TOML file on withdrawals, transfers and chargebacks,
+    /// and report the collected fees in a `fees` row after the clients.
+    #[arg(long)]
+    fees: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Upgrade a database in place to the schema version of this release.
+    Migrate {
+        /// Path to the database.
+        #[arg(long)]
+        db_path: PathBuf,
+    },
+}
+
+/// Input size above which RocksDB is used.
+///
+/// `test_compact_store_memory_at_scale` measured the in-memory transaction store at 30.6 bytes
+/// per deposit in its worst case (50M ids strided across the whole `u32` range; 18.1 with
+/// sequential ids), and a deposit row takes at least 14 bytes (`deposit,1,1,1` and a newline).
+/// The store thus takes at most about 2.2 times the size of the input, 2.2 GiB at the threshold.
+const ROCKSDB_THRESHOLD_BYTES: u64 = 1024 * 1024 * 1024; // 1 GiB
+
+/// The storage backends used by the engine.
+struct Stores {
+    accounts: AccountStoreBox,
+    transactions: TransactionStoreBox,
+    journal: JournalStoreBox,
+    events: EventStoreBox,
+    risk: RiskStoreBox,
+    fees: FeeStoreBox,
+    /// Commits the account, transaction, fee, journal and event writes of a transaction together.
+    unit_of_work: UnitOfWorkStoreBox,
+    /// Only set for a database that keeps its state across runs.
+    snapshots: Option<SnapshotStoreBox>,
+    /// Only set for a database that keeps its state across runs.
+    checkpoints: Option<CheckpointStoreBox>,
+}
+
+fn in_memory_stores(config: &EngineConfig) -> Stores {
+    let ts_store = if config.dispute_eligibility.includes_withdrawals() {
+        InMemoryTransactionStore::with_withdrawals()
+    } else {
+        InMemoryTransactionStore::new()
+    };
+    let account_store = InMemoryAccountStore::new();
+    let fee_store = InMemoryFeeStore::new();
+    let journal_store = InMemoryJournalStore::new();
+    let event_store = InMemoryEventStore::new();
+    let risk_store = InMemoryRiskStore::new();
+    Stores {
+        accounts: Box::new(account_store.clone()),
+        transactions: Box::new(ts_store.clone()),
+        unit_of_work: Box::new(
+            InMemoryUnitOfWork::new(account_store, ts_store)
+                .with_fees(fee_store.clone())
+                .with_risk(risk_store.clone())
+                .with_journal(journal_store.clone())
+                .with_event_log(event_store.clone()),
+        ),
+        journal: Box::new(journal_store),
+        events: Box::new(event_store),
+        risk: Box::new(risk_store),
+        fees: Box::new(fee_store),
+        snapshots: None,
+        checkpoints: None,
+    }
+}
+
+#[cfg(feature = "storage-rocksdb")]
+fn rocksdb_stores(store: RocksDBStore) -> Stores {
+    Stores {
+        accounts: Box::new(store.clone()),
+        transactions: Box::new(store.clone()),
+        unit_of_work: Box::new(store.clone()),
+        journal: Box::new(store.clone()),
+        events: Box::new(store.clone()),
+        risk: Box::new(store.clone()),
+        fees: Box::new(store),
+        snapshots: None,
+        checkpoints: None,
+    }
 }
 
-const ROCKSDB_THRESHOLD_BYTES: u64 = 50 * 1024 * 1024; // 100 MB
+/// Runs the `migrate` command.
+fn migrate(db_path: &Path) -> Result<()> {
+    #[cfg(feature = "storage-rocksdb")]
+    {
+        let migration = RocksDBStore::migrate(db_path).into_diagnostic()?;
+        eprintln!("Database {}: {}.", db_path.display(), migration);
+        Ok(())
+    }
+    #[cfg(not(feature = "storage-rocksdb"))]
+    {
+        let _ = db_path; // avoid unused variable warning
+        miette::bail!("Migrating a database requires the storage-rocksdb feature")
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    if let Some(Command::Migrate { db_path }) = &cli.command {
+        return migrate(db_path);
+    }
+    let config = EngineConfig {
+        dispute_eligibility: cli.dispute_eligibility,
+        dispute_funds: cli.dispute_funds,
+        auto_lock: AutoLockPolicy {
+            max_open_disputes: cli.lock_after_disputes,
+            max_disputed_value: cli.lock_after_disputed_value,
+        },
+        locked_disputes: cli.locked_disputes,
+        lock_timestamps: cli.lock_timestamps,
+        strict_invariants: cli.strict,
+        precision: cli
+            .precision
+            .map(|scale| PrecisionPolicy::new(scale, cli.precision_mode)),
+        limits: match &cli.limits {
+            Some(path) => load_limits(path)?,
+            None => BTreeMap::new(),
+        },
+    };
 
     // Determine storage type and handle temporary directory if needed
     let mut _temp_dir_handle: Option<tempfile::TempDir> = None;
 
-    let (as_store, ts_store) = if let Some(db_path) = cli.db_path {
+    let stores = if let Some(db_path) = cli.db_path {
         // Explicit RocksDB
         #[cfg(feature = "storage-rocksdb")]
         {
             let store = RocksDBStore::open(db_path).into_diagnostic()?;
-            (
-                Box::new(store.clone()) as AccountStoreBox,
-                Box::new(store) as TransactionStoreBox,
-            )
+            Stores {
+                snapshots: Some(Box::new(store.clone())),
+                checkpoints: Some(Box::new(store.clone())),
+                ..rocksdb_stores(store)
+            }
         }
         #[cfg(not(feature = "storage-rocksdb"))]
         {
@@ -51,20 +303,15 @@ async fn main() -> Result<()> {
             eprintln!(
                 "WARNING: Persistent storage requested via --db-path, but 'storage-rocksdb' feature is not enabled. Falling back to In-Memory storage."
             );
-            (
-                Box::new(InMemoryAccountStore::new()) as AccountStoreBox,
-                Box::new(InMemoryTransactionStore::new()) as TransactionStoreBox,
-            )
+            in_memory_stores(&config)
         }
     } else if cli.in_memory {
         // Explicit In-Memory
-        (
-            Box::new(InMemoryAccountStore::new()) as AccountStoreBox,
-            Box::new(InMemoryTransactionStore::new()) as TransactionStoreBox,
-        )
+        in_memory_stores(&config)
     } else {
         // Auto-selection based on file size
-        let use_rocksdb = if let Ok(metadata) = std::fs::metadata(&cli.input) {
+        let metadata = cli.input.as_ref().map(std::fs::metadata);
+        let use_rocksdb = if let Some(Ok(metadata)) = metadata {
             if metadata.len() >= ROCKSDB_THRESHOLD_BYTES {
                 #[cfg(feature = "storage-rocksdb")]
                 {
@@ -95,51 +342,226 @@ async fn main() -> Result<()> {
                 let temp = tempfile::tempdir().into_diagnostic()?;
                 let store = RocksDBStore::open(temp.path()).into_diagnostic()?;
                 _temp_dir_handle = Some(temp);
-                (
-                    Box::new(store.clone()) as AccountStoreBox,
-                    Box::new(store) as TransactionStoreBox,
-                )
+                rocksdb_stores(store)
             }
             #[cfg(not(feature = "storage-rocksdb"))]
             {
-                (
-                    Box::new(InMemoryAccountStore::new()) as AccountStoreBox,
-                    Box::new(InMemoryTransactionStore::new()) as TransactionStoreBox,
-                )
+                in_memory_stores(&config)
             }
         } else {
-            (
-                Box::new(InMemoryAccountStore::new()) as AccountStoreBox,
-                Box::new(InMemoryTransactionStore::new()) as TransactionStoreBox,
-            )
+            in_memory_stores(&config)
         }
     };
 
-    let engine = PaymentEngine::new(as_store, ts_store);
+    let mut engine = PaymentEngine::with_config(stores.accounts, stores.transactions, config)
+        .with_unit_of_work(stores.unit_of_work);
+    if cli.journal.is_some() {
+        engine = engine.with_journal(stores.journal);
+    }
+    if cli.event_sourced || cli.replay {
+        engine = engine.with_event_log(stores.events);
+    }
+    if let Some(path) = &cli.risk_rules {
+        let rules = load_risk_rules(path)?;
+        engine = engine.with_risk_rules(rules, stores.risk);
+    }
+    if let Some(path) = &cli.fees {
+        engine = engine.with_fees(load_fee_schedule(path)?, stores.fees);
+    }
+    // A persistent database records how far the input went with every transaction
+    let checkpoints = stores.checkpoints.is_some();
+    if let Some(checkpoint_store) = stores.checkpoints {
+        engine = engine.with_checkpoints(checkpoint_store);
+    }
+    if cli.resume && !checkpoints {
+        miette::bail!("Resuming requires a database (--db-path with the storage-rocksdb feature)");
+    }
+    if cli.replay {
+        let count = engine.replay().await?;
+        eprintln!("Rebuilt {} account(s) from the event log.", count);
+    }
+
+    // A persistent database is already up to date, other stores are rebuilt from the snapshot
+    let persistent = stores.snapshots.is_some();
+    let snapshot_store = match &cli.snapshot_file {
+        Some(path) => Some(Box::new(FileSnapshotStore::new(path)) as SnapshotStoreBox),
+        None => stores.snapshots,
+    };
+    if cli.snapshot_every.is_some() || cli.restore {
+        let Some(snapshot_store) = snapshot_store else {
+            miette::bail!("Snapshots require --snapshot-file or --db-path");
+        };
+        engine = engine.with_snapshots(snapshot_store, cli.snapshot_every.unwrap_or(0));
+    }
+    let mut restored_position = 0;
+    if cli.restore {
+        let position = if persistent {
+            engine.resume().await?
+        } else {
+            engine.restore().await?
+        };
+        match position {
+            Some(position) if persistent => {
+                eprintln!("Resuming the database after {} transaction(s).", position);
+                restored_position = position;
+            }
+            Some(position) => {
+                eprintln!("Restored snapshot after {} transaction(s).", position);
+                restored_position = position;
+            }
+            None => eprintln!("No snapshot found, processing the whole input."),
+        }
+    }
+
+    let mut rejects = match &cli.rejects {
+        Some(path) => Some(RejectWriter::new(File::create(path).into_diagnostic()?)?),
+        None => None,
+    };
 
-    // Process transactions
-    let file = File::open(cli.input).into_diagnostic()?;
-    let reader = TransactionReader::new(file);
-    for tx_result in reader.transactions() {
-        match tx_result {
-            Ok(tx) => {
-                if let Err(e) = engine.process_transaction(tx).await {
-                    eprintln!("Error processing transaction: {}", e);
+    // Process transactions (none when only replaying the event log)
+    if let Some(input) = &cli.input {
+        let file = File::open(input).into_diagnostic()?;
+        let mut reader = TransactionReader::new(file);
+        if let Some(limit) = cli.max_amount {
+            reader = reader.with_max_amount(limit);
+        }
+        let fingerprint = if checkpoints {
+            Some(fingerprint_file(input)?)
+        } else {
+            None
+        };
+        if let (true, Some(fingerprint)) = (cli.resume, &fingerprint) {
+            match engine.resume_input(fingerprint).await? {
+                Some(checkpoint) => {
+                    reader.seek(checkpoint.input)?;
+                    eprintln!(
+                        "Resuming after {} transaction(s), from line {}.",
+                        checkpoint.position, checkpoint.input.line
+                    );
+                }
+                None => eprintln!("No checkpoint found, processing the whole input."),
+            }
+        }
+        let mut end = None;
+        let mut skipped = 0;
+        for record in reader.records() {
+            end = Some(record.end);
+            // Skip the rows covered by the restored snapshot
+            if skipped < restored_position {
+                if record.transaction.is_ok() {
+                    skipped += 1;
                 }
+                continue;
             }
-            Err(e) => {
-                eprintln!("Error reading transaction: {}", e);
+            let reason = match record.transaction {
+                Ok(tx) => {
+                    let outcome = match fingerprint {
+                        Some(fingerprint) => {
+                            engine
+                                .process_checkpointed(tx, fingerprint, record.end)
+                                .await
+                        }
+                        None => engine.process_transaction(tx).await,
+                    };
+                    match outcome {
+                        Ok(TransactionOutcome::Applied) => None,
+                        Ok(TransactionOutcome::Rejected { reason }) => Some(reason.code()),
+                        Err(e) => {
+                            eprintln!("Error processing transaction: {}", e);
+                            Some(engine_error_code(&e))
+                        }
+                    }
+                }
+                Err(e @ PaymentError::AmountLimitExceeded { .. }) => {
+                    eprintln!("Error reading transaction: {}", e);
+                    Some(AMOUNT_LIMIT_EXCEEDED)
+                }
+                Err(e) => {
+                    eprintln!("Error reading transaction: {}", e);
+                    Some(MALFORMED_RECORD)
+                }
+            };
+            if let (Some(rejects), Some(reason)) = (rejects.as_mut(), reason) {
+                rejects.write_reject(record.line, reason, &record.raw)?;
             }
         }
+        // Let a resume also skip the trailing rows that failed to parse
+        if let (Some(fingerprint), Some(end)) = (fingerprint, end) {
+            engine
+                .save_checkpoint(Checkpoint {
+                    fingerprint,
+                    input: end,
+                    position: engine.position(),
+                })
+                .await?;
+        }
+    }
+    if let Some(rejects) = rejects.as_mut() {
+        rejects.flush()?;
+    }
+    if cli.snapshot_every.is_some() {
+        engine.save_snapshot().await?;
+    }
+    for violation in engine.violations() {
+        eprintln!("WARNING: Invariant violation: {}", violation);
+    }
+    for flag in engine.risk_flags() {
+        eprintln!("Flagged for review: {}", flag);
+    }
+
+    if cli.verify {
+        let violations = engine.verify().await?;
+        for violation in &violations {
+            eprintln!("Invariant violation: {}", violation);
+        }
+        if !violations.is_empty() {
+            miette::bail!(
+                "Verification failed with {} invariant violation(s)",
+                violations.len()
+            );
+        }
+        eprintln!("Verification passed: no invariant violations.");
+    }
+
+    if let Some(path) = &cli.journal {
+        let entries = engine.journal_entries().await?;
+        JournalWriter::new(File::create(path).into_diagnostic()?)?.write_entries(&entries)?;
+
+        let mismatches = engine.trial_balance().await?;
+        for mismatch in &mismatches {
+            eprintln!("Trial balance mismatch: {}", mismatch);
+        }
+        if !mismatches.is_empty() {
+            miette::bail!(
+                "Trial balance failed with {} mismatch(es)",
+                mismatches.len()
+            );
+        }
     }
 
     // Collect final state from engine
+    let fees = match cli.fees {
+        Some(_) => Some(engine.fee_account().await?),
+        None => None,
+    };
     let accounts = engine.into_results().await?;
 
     // Output final state
     let stdout = io::stdout();
     let mut writer = AccountWriter::new(stdout.lock());
+    if cli.with_status {
+        writer = writer.with_status_column();
+    }
+    if cli.with_lock_details {
+        writer = writer.with_lock_details();
+    }
+    if let Some(scale) = cli.output_scale {
+        writer = writer.with_scale(scale);
+    }
     writer.write_accounts(accounts).into_diagnostic()?;
+    if let Some(fees) = fees {
+        writer.write_fee_account(fees).into_diagnostic()?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,59 @@
+use assert_cmd::cargo_bin;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::io::Write;
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+#[test]
+fn test_freeze_blocks_withdrawals_until_unfrozen() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount").unwrap();
+    writeln!(file, "deposit, 1, 1, 100.0").unwrap();
+    writeln!(file, "freeze, 1, 2, ").unwrap();
+    writeln!(file, "withdrawal, 1, 3, 10.0").unwrap(); // Blocked
+    writeln!(file, "deposit, 1, 4, 5.0").unwrap(); // Accepted
+    writeln!(file, "unfreeze, 1, 5, ").unwrap();
+    writeln!(file, "withdrawal, 1, 6, 20.0").unwrap();
+    writeln!(file, "deposit, 2, 7, 1.0").unwrap();
+    writeln!(file, "freeze, 2, 8, ").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path()).arg("--with-status");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "client,available,held,total,locked,status",
+        ))
+        .stdout(predicate::str::contains("1,85,0,85,false,active"))
+        .stdout(predicate::str::contains("2,1,0,1,true,frozen"));
+}
+
+#[test]
+fn test_unlock_after_chargeback_and_close() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount").unwrap();
+    writeln!(file, "deposit, 1, 1, 100.0").unwrap();
+    writeln!(file, "deposit, 1, 2, 50.0").unwrap();
+    writeln!(file, "dispute, 1, 2, ").unwrap();
+    writeln!(file, "chargeback, 1, 2, ").unwrap();
+    writeln!(file, "deposit, 1, 3, 10.0").unwrap(); // Ignored, account locked
+    writeln!(file, "unlock, 1, 4, ").unwrap();
+    writeln!(file, "deposit, 1, 5, 10.0").unwrap();
+    writeln!(file, "deposit, 2, 6, 10.0").unwrap();
+    writeln!(file, "close, 2, 7, ").unwrap();
+    writeln!(file, "deposit, 2, 8, 10.0").unwrap(); // Ignored, account closed
+    writeln!(file, "unlock, 2, 9, ").unwrap(); // Ignored, closing is final
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "client,available,held,total,locked\n",
+        ))
+        .stdout(predicate::str::contains("1,110,0,110,false"))
+        .stdout(predicate::str::contains("2,10,0,10,true"));
+}
@@ -1,6 +1,7 @@
 use assert_cmd::cargo_bin;
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
+use std::io::Write;
 use std::process::Command;
 
 #[test]
@@ -33,3 +34,489 @@ fn test_cli_end_to_end() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_rejects_report() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(input, "withdrawal, 1, 2, 50.0").unwrap();
+    writeln!(input, "invalid, 1, 3, 1.0").unwrap();
+    writeln!(input, "dispute, 1, 999, ").unwrap();
+    writeln!(input, "deposit, 1, 4, 5.0").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let rejects_path = dir.path().join("rejects.csv");
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path()).arg("--rejects").arg(&rejects_path);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,15,0,15,false"));
+
+    let rejects = std::fs::read_to_string(&rejects_path).unwrap();
+    assert_eq!(
+        rejects,
+        "line,reason,record\n\
+         3,insufficient_funds,\"withdrawal, 1, 2, 50.0\"\n\
+         4,malformed_record,\"invalid, 1, 3, 1.0\"\n\
+         5,unknown_transaction,\"dispute, 1, 999, \"\n"
+    );
+}
+
+#[test]
+fn test_journal_export() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(input, "dispute, 1, 1, ").unwrap();
+    writeln!(input, "resolve, 1, 1, ").unwrap();
+    writeln!(input, "withdrawal, 1, 2, 4.0").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let journal_path = dir.path().join("journal.csv");
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path()).arg("--journal").arg(&journal_path);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,6,0,6,false"))
+        .stderr(predicate::str::contains("Trial balance").not());
+
+    let journal = std::fs::read_to_string(&journal_path).unwrap();
+    assert_eq!(
+        journal,
+        "seq,tx,currency,debit,credit,amount\n\
+         0,1,,system:cash,client:1:available,10\n\
+         1,1,,client:1:available,client:1:held,10\n\
+         2,1,,client:1:held,client:1:available,10\n\
+         3,2,,client:1:available,system:cash,4\n"
+    );
+}
+
+#[test]
+fn test_event_sourced_mode() {
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg("tests/fixtures/test.csv").arg("--event-sourced");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,1.5,0,1.5,false"))
+        .stdout(predicate::str::contains("2,2,0,2,false"));
+}
+
+#[test]
+fn test_replay_requires_db_path() {
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg("--replay");
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "the following required arguments were not provided",
+    ));
+}
+
+#[test]
+fn test_snapshot_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot_path = dir.path().join("snapshot.json");
+
+    // 1. First run: Process the first part of the input
+    let mut input1 = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input1, "type, client, tx, amount").unwrap();
+    writeln!(input1, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(input1, "deposit, 2, 2, 5.0").unwrap();
+    writeln!(input1, "dispute, 1, 1, 4.0").unwrap();
+
+    let mut cmd1 = Command::new(cargo_bin!("hc190aop"));
+    cmd1.arg(input1.path())
+        .arg("--snapshot-every")
+        .arg("2")
+        .arg("--snapshot-file")
+        .arg(&snapshot_path);
+    cmd1.assert()
+        .success()
+        .stdout(predicate::str::contains("1,6,4,10,false"));
+
+    // 2. Second run: The grown input is only processed after the snapshot
+    let mut input2 = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input2, "type, client, tx, amount").unwrap();
+    writeln!(input2, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(input2, "deposit, 2, 2, 5.0").unwrap();
+    writeln!(input2, "dispute, 1, 1, 4.0").unwrap();
+    writeln!(input2, "chargeback, 1, 1, ").unwrap();
+    writeln!(input2, "deposit, 2, 3, 1.0").unwrap();
+
+    let mut cmd2 = Command::new(cargo_bin!("hc190aop"));
+    cmd2.arg(input2.path())
+        .arg("--restore")
+        .arg("--snapshot-file")
+        .arg(&snapshot_path);
+    cmd2.assert()
+        .success()
+        .stdout(predicate::str::contains("1,6,0,6,true"))
+        .stdout(predicate::str::contains("2,6,0,6,false"))
+        .stderr(predicate::str::contains(
+            "Restored snapshot after 3 transaction(s).",
+        ));
+}
+
+#[test]
+fn test_restore_rejects_repeated_withdrawal() {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot_path = dir.path().join("snapshot.json");
+
+    let mut input1 = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input1, "type, client, tx, amount").unwrap();
+    writeln!(input1, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(input1, "withdrawal, 1, 2, 5.0").unwrap();
+    Command::new(cargo_bin!("hc190aop"))
+        .arg(input1.path())
+        .arg("--snapshot-every")
+        .arg("2")
+        .arg("--snapshot-file")
+        .arg(&snapshot_path)
+        .assert()
+        .success();
+
+    // The withdrawal is a duplicate after the restore, though its record was not kept
+    let mut input2 = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input2, "type, client, tx, amount").unwrap();
+    writeln!(input2, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(input2, "withdrawal, 1, 2, 5.0").unwrap();
+    writeln!(input2, "withdrawal, 1, 2, 5.0").unwrap();
+    Command::new(cargo_bin!("hc190aop"))
+        .arg(input2.path())
+        .arg("--restore")
+        .arg("--snapshot-file")
+        .arg(&snapshot_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1,5,0,5,false"));
+}
+
+#[test]
+fn test_restore_refuses_journal() {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot_path = dir.path().join("snapshot.json");
+    let journal_path = dir.path().join("journal.csv");
+
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 1.0").unwrap();
+    writeln!(input, "deposit, 1, 2, 5.0").unwrap();
+    Command::new(cargo_bin!("hc190aop"))
+        .arg(input.path())
+        .arg("--snapshot-every")
+        .arg("2")
+        .arg("--snapshot-file")
+        .arg(&snapshot_path)
+        .arg("--journal")
+        .arg(&journal_path)
+        .assert()
+        .success();
+
+    // The journal of the restored run would miss the postings of the snapshot
+    Command::new(cargo_bin!("hc190aop"))
+        .arg(input.path())
+        .arg("--restore")
+        .arg("--snapshot-file")
+        .arg(&snapshot_path)
+        .arg("--journal")
+        .arg(&journal_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Snapshots cannot be restored"));
+}
+
+#[test]
+fn test_snapshots_require_a_store() {
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg("tests/fixtures/test.csv")
+        .arg("--snapshot-every")
+        .arg("10");
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Snapshots require --snapshot-file or --db-path",
+    ));
+}
+
+#[test]
+fn test_verify_mode() {
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg("tests/fixtures/test.csv")
+        .arg("--verify")
+        .arg("--strict");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,1.5,0,1.5,false"))
+        .stderr(predicate::str::contains(
+            "Verification passed: no invariant violations.",
+        ));
+}
+
+#[test]
+fn test_precision_policy() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 1.5").unwrap();
+    writeln!(input, "deposit, 1, 2, 0.00015").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let rejects_path = dir.path().join("rejects.csv");
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path())
+        .arg("--precision")
+        .arg("4")
+        .arg("--output-scale")
+        .arg("4")
+        .arg("--rejects")
+        .arg(&rejects_path);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,1.5000,0.0000,1.5000,false"));
+    let rejects = std::fs::read_to_string(&rejects_path).unwrap();
+    assert!(rejects.contains("3,excess_precision,\"deposit, 1, 2, 0.00015\""));
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path())
+        .arg("--precision")
+        .arg("4")
+        .arg("--precision-mode")
+        .arg("bankers")
+        .arg("--output-scale")
+        .arg("4");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,1.5002,0.0000,1.5002,false"));
+}
+
+#[test]
+fn test_amount_limit_and_overflow() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 40000000000000000000000000000.0").unwrap();
+    writeln!(input, "deposit, 1, 2, 40000000000000000000000000000.0").unwrap();
+    writeln!(input, "deposit, 1, 3, 1").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let rejects_path = dir.path().join("rejects.csv");
+
+    // Without a limit, the overflowing deposit is rejected and the batch goes on
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path()).arg("--rejects").arg(&rejects_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "1,40000000000000000000000000001,0,40000000000000000000000000001,false",
+    ));
+    let rejects = std::fs::read_to_string(&rejects_path).unwrap();
+    assert!(rejects.contains("3,arithmetic_overflow,"));
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path())
+        .arg("--max-amount")
+        .arg("1000000")
+        .arg("--rejects")
+        .arg(&rejects_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,1,0,1,false"));
+    let rejects = std::fs::read_to_string(&rejects_path).unwrap();
+    assert!(rejects.contains("2,amount_limit_exceeded,"));
+    assert!(rejects.contains("3,amount_limit_exceeded,"));
+}
+
+#[test]
+fn test_risk_rules() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 500").unwrap();
+    writeln!(input, "withdrawal, 1, 2, 200").unwrap();
+    writeln!(input, "withdrawal, 1, 3, 50").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let rules_path = dir.path().join("rules.toml");
+    std::fs::write(
+        &rules_path,
+        "[[rules]]\n\
+         type = \"max_withdrawal_amount\"\n\
+         limit = 100\n\
+         \n\
+         [[rules]]\n\
+         type = \"deposit_then_withdrawal\"\n\
+         window = 5\n\
+         action = \"flag\"\n",
+    )
+    .unwrap();
+    let rejects_path = dir.path().join("rejects.csv");
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path())
+        .arg("--risk-rules")
+        .arg(&rules_path)
+        .arg("--rejects")
+        .arg(&rejects_path);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,450,0,450,false"))
+        .stderr(predicate::str::contains(
+            "Flagged for review: transaction 3 of client 1: deposit_then_withdrawal",
+        ));
+    let rejects = std::fs::read_to_string(&rejects_path).unwrap();
+    assert!(rejects.contains("3,risk_rejected,\"withdrawal, 1, 2, 200\""));
+}
+
+#[test]
+fn test_account_limits() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 10").unwrap();
+    writeln!(input, "withdrawal, 1, 2, 30").unwrap();
+    writeln!(input, "deposit, 2, 3, 10").unwrap();
+    writeln!(input, "set_overdraft, 2, 4, 5").unwrap();
+    writeln!(input, "withdrawal, 2, 5, 15").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let limits_path = dir.path().join("limits.csv");
+    std::fs::write(&limits_path, "client,overdraft,reserve\n1,25,\n").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path()).arg("--limits").arg(&limits_path);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "client,available,held,total,locked,overdraft,reserve",
+        ))
+        .stdout(predicate::str::contains("1,-20,0,-20,false,25,0"))
+        .stdout(predicate::str::contains("2,-5,0,-5,false,5,0"))
+        .stderr(predicate::str::contains("Invariant violation").not());
+}
+
+#[test]
+fn test_fee_schedule() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 100").unwrap();
+    writeln!(input, "withdrawal, 1, 2, 10").unwrap();
+    writeln!(input, "withdrawal, 1, 3, 89.5").unwrap();
+    writeln!(input, "deposit, 2, 4, 20").unwrap();
+    writeln!(input, "deposit, 2, 5, 5").unwrap();
+    writeln!(input, "dispute, 2, 5,").unwrap();
+    writeln!(input, "chargeback, 2, 5,").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let fees_path = dir.path().join("fees.toml");
+    std::fs::write(
+        &fees_path,
+        "[withdrawal]\ntype = \"percentage\"\npercent = \"1\"\n\n\
+         [chargeback]\ntype = \"flat\"\namount = \"15\"\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path()).arg("--fees").arg(&fees_path);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,89.9,0,89.9,false"))
+        .stdout(predicate::str::contains("2,5,0,5,true"))
+        .stdout(predicate::str::ends_with("fees,15.1,0,15.1,false\n"));
+}
+
+#[test]
+fn test_dispute_funds_policy() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 100").unwrap();
+    writeln!(input, "withdrawal, 1, 2, 70").unwrap();
+    writeln!(input, "dispute, 1, 1,").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,30,0,30,false"));
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path())
+        .arg("--dispute-funds")
+        .arg("allow-negative");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,-70,100,30,false"));
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path())
+        .arg("--dispute-funds")
+        .arg("hold-available");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "client,available,held,total,locked,receivable",
+        ))
+        .stdout(predicate::str::contains("1,0,30,30,false,70"));
+}
+
+#[test]
+fn test_auto_lock_thresholds() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 10").unwrap();
+    writeln!(input, "deposit, 1, 2, 10").unwrap();
+    writeln!(input, "dispute, 1, 1,").unwrap();
+    writeln!(input, "dispute, 1, 2,").unwrap();
+    writeln!(input, "deposit, 2, 3, 100").unwrap();
+    writeln!(input, "dispute, 2, 3,").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path()).arg("--lock-after-disputes").arg("1");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,0,20,20,true"))
+        .stdout(predicate::str::contains("2,0,100,100,false"));
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path())
+        .arg("--lock-after-disputed-value")
+        .arg("50");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,0,20,20,false"))
+        .stdout(predicate::str::contains("2,0,100,100,true"));
+}
+
+#[test]
+fn test_lock_details_output() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    writeln!(input, "type, client, tx, amount").unwrap();
+    writeln!(input, "deposit, 1, 1, 10").unwrap();
+    writeln!(input, "deposit, 2, 2, 10").unwrap();
+    writeln!(input, "dispute, 1, 1,").unwrap();
+    writeln!(input, "chargeback, 1, 1,").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path()).arg("--with-lock-details");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "client,available,held,total,locked,lock_tx,lock_type,lock_cause,lock_position,locked_at",
+        ))
+        .stdout(predicate::str::contains(
+            "1,0,0,0,true,1,chargeback,chargeback,3,\n",
+        ))
+        .stdout(predicate::str::contains("2,10,0,10,false,,,,,\n"));
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(input.path())
+        .arg("--with-lock-details")
+        .arg("--lock-timestamps");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"1,0,0,0,true,1,chargeback,chargeback,3,\d+\n").unwrap());
+}
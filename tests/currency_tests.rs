@@ -0,0 +1,48 @@
+use assert_cmd::cargo_bin;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::io::Write;
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+#[test]
+fn test_multi_currency_balances() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount, currency").unwrap();
+    writeln!(file, "deposit, 1, 1, 10.0, EUR").unwrap();
+    writeln!(file, "deposit, 1, 2, 20.0, USD").unwrap();
+    writeln!(file, "withdrawal, 1, 3, 15.0, EUR").unwrap(); // Insufficient EUR funds
+    writeln!(file, "withdrawal, 1, 4, 5.0, usd").unwrap();
+    writeln!(file, "deposit, 2, 5, 7.0, GBP").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "client,currency,available,held,total,locked",
+        ))
+        .stdout(predicate::str::contains("1,EUR,10,0,10,false"))
+        .stdout(predicate::str::contains("1,USD,15,0,15,false"))
+        .stdout(predicate::str::contains("2,GBP,7,0,7,false"));
+}
+
+#[test]
+fn test_dispute_currency_must_match() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount, currency").unwrap();
+    writeln!(file, "deposit, 1, 1, 10.0, EUR").unwrap();
+    writeln!(file, "dispute, 1, 1, , USD").unwrap(); // Wrong currency
+    writeln!(file, "dispute, 1, 1, , ").unwrap(); // Unspecified currency
+    writeln!(file, "deposit, 2, 2, 10.0, EUR").unwrap();
+    writeln!(file, "dispute, 2, 2, , EUR").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,EUR,10,0,10,false"))
+        .stdout(predicate::str::contains("2,EUR,0,10,10,false"));
+}
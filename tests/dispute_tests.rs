@@ -117,3 +117,145 @@ fn test_dispute_with_insufficient_funds_ignored() {
         .success()
         .stdout(predicate::str::contains("1,40,0,40,false"));
 }
+
+#[test]
+fn test_withdrawal_dispute_chargeback_when_enabled() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount").unwrap();
+    writeln!(file, "deposit, 1, 1, 100.0").unwrap();
+    writeln!(file, "withdrawal, 1, 2, 40.0").unwrap();
+    writeln!(file, "dispute, 1, 2, ").unwrap();
+    writeln!(file, "chargeback, 1, 2, ").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path())
+        .arg("--dispute-eligibility")
+        .arg("both");
+
+    // Expected: the withdrawn 40 are credited back to the client and the account is locked.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,100,0,100,true"));
+}
+
+#[test]
+fn test_bounced_withdrawal_not_disputable() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount").unwrap();
+    writeln!(file, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(file, "withdrawal, 1, 2, 500.0").unwrap(); // Bounces
+    writeln!(file, "dispute, 1, 2, ").unwrap();
+    writeln!(file, "chargeback, 1, 2, ").unwrap();
+    writeln!(file, "withdrawal, 1, 2, 5.0").unwrap(); // Duplicate of the bounced one
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path())
+        .arg("--dispute-eligibility")
+        .arg("both");
+
+    // Expected: nothing was withdrawn, so the dispute and chargeback are ignored.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,10,0,10,false"));
+}
+
+#[test]
+fn test_withdrawal_dispute_resolve_when_enabled() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount").unwrap();
+    writeln!(file, "deposit, 1, 1, 100.0").unwrap();
+    writeln!(file, "withdrawal, 1, 2, 40.0").unwrap();
+    writeln!(file, "dispute, 1, 2, ").unwrap();
+    writeln!(file, "dispute, 1, 1, ").unwrap(); // Deposits are not disputable
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path())
+        .arg("--dispute-eligibility")
+        .arg("withdrawals");
+
+    // Expected: 40 held pending the dispute, the deposit dispute is ignored.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,60,40,100,false"));
+
+    writeln!(file, "resolve, 1, 2, ").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path())
+        .arg("--dispute-eligibility")
+        .arg("withdrawals");
+
+    // Expected: the withdrawal stands.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,60,0,60,false"));
+}
+
+#[test]
+fn test_partial_dispute_resolve_and_redispute() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount").unwrap();
+    writeln!(file, "deposit, 1, 1, 100.0").unwrap();
+    writeln!(file, "dispute, 1, 1, 40.0").unwrap();
+    writeln!(file, "resolve, 1, 1, ").unwrap();
+    writeln!(file, "dispute, 1, 1, ").unwrap(); // Disputes the remaining 60.0
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,40,60,100,false"));
+}
+
+#[test]
+fn test_locked_account_settles_open_disputes() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount").unwrap();
+    writeln!(file, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(file, "deposit, 1, 2, 20.0").unwrap();
+    writeln!(file, "dispute, 1, 1, ").unwrap();
+    writeln!(file, "dispute, 1, 2, ").unwrap();
+    writeln!(file, "chargeback, 1, 1, ").unwrap(); // Locks the account
+    writeln!(file, "resolve, 1, 2, ").unwrap();
+    writeln!(file, "deposit, 1, 3, 5.0").unwrap();
+
+    // By default, the funds of tx 2 stay held.
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,0,20,20,true"));
+
+    // Expected: the resolve releases them, but the deposit is still ignored.
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path()).arg("--locked-disputes").arg("settle");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,20,0,20,true"));
+}
+
+#[test]
+fn test_locked_account_accepts_new_disputes() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount").unwrap();
+    writeln!(file, "deposit, 1, 1, 10.0").unwrap();
+    writeln!(file, "deposit, 1, 2, 20.0").unwrap();
+    writeln!(file, "dispute, 1, 1, ").unwrap();
+    writeln!(file, "chargeback, 1, 1, ").unwrap(); // Locks the account
+    writeln!(file, "dispute, 1, 2, ").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path()).arg("--locked-disputes").arg("settle");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,20,0,20,true"));
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path())
+        .arg("--locked-disputes")
+        .arg("settle-and-dispute");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,0,20,20,true"));
+}
@@ -36,3 +36,32 @@ fn test_rocksdb_no_fallback_warning() {
         .success()
         .stderr(predicate::str::contains("WARNING").not());
 }
+
+#[cfg(not(feature = "storage-rocksdb"))]
+#[test]
+fn test_resume_requires_rocksdb() {
+    let mut csv = tempfile::NamedTempFile::new().unwrap();
+    writeln!(csv, "type, client, tx, amount").unwrap();
+    writeln!(csv, "deposit, 1, 1, 100.0").unwrap();
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(csv.path())
+        .arg("--db-path")
+        .arg("some_db")
+        .arg("--resume");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Resuming requires a database"));
+}
+
+#[cfg(not(feature = "storage-rocksdb"))]
+#[test]
+fn test_migrate_requires_rocksdb() {
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg("migrate").arg("--db-path").arg("some_db");
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "requires the storage-rocksdb feature",
+    ));
+}
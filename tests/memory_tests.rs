@@ -0,0 +1,182 @@
+//! Memory usage of `InMemoryTransactionStore`, against the `HashSet` of seen ids and `HashMap`
+//! of records it used to be built on.
+//!
+//! The full-scale benchmark is triggered manually:
+//! `cargo test --release --test memory_tests -- --ignored --nocapture`
+use hc190aop::domain::currency::Currency;
+use hc190aop::domain::ports::TransactionStore;
+use hc190aop::domain::transaction::{Transaction, TransactionType};
+use hc190aop::infrastructure::in_memory::{InMemoryTransactionStore, LeanTransaction};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Mutex;
+
+/// Keeps track of the bytes allocated by the test binary.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Measurements share the allocation counter, so they must not run concurrently.
+static MEASURING: Mutex<()> = Mutex::const_new(());
+
+/// The ids of `count` transactions: increasing, spread by a fixed stride, or random.
+#[derive(Clone, Copy, Debug)]
+enum Ids {
+    Sequential,
+    /// Spread evenly over the whole id space, at least 4096 apart (the range of a page of the
+    /// compact store) below a million transactions.
+    Strided,
+    Random,
+}
+
+impl Ids {
+    fn generate(self, count: u32) -> Vec<u32> {
+        match self {
+            Ids::Sequential => (1..=count).collect(),
+            Ids::Strided => {
+                let stride = u32::MAX / count;
+                (1..=count).map(|n| n * stride).collect()
+            }
+            Ids::Random => {
+                let mut rng = StdRng::seed_from_u64(7);
+                let mut ids: Vec<u32> = (0..count).map(|_| rng.r#gen()).collect();
+                ids.sort_unstable();
+                ids.dedup();
+                ids.shuffle(&mut rng);
+                ids
+            }
+        }
+    }
+}
+
+/// Deposits with the given ids, every tenth transaction being a withdrawal.
+fn transactions(ids: &[u32]) -> impl Iterator<Item = Transaction> + '_ {
+    ids.iter().enumerate().map(|(n, &tx)| Transaction {
+        r#type: if n % 10 == 9 {
+            TransactionType::Withdrawal
+        } else {
+            TransactionType::Deposit
+        },
+        client: (tx % 1000) as u16,
+        tx,
+        amount: Some(
+            Decimal::new(i64::from(tx % 100_000) + 1, 2)
+                .try_into()
+                .unwrap(),
+        ),
+        currency: Currency::default(),
+        destination: None,
+    })
+}
+
+/// Returns the bytes held by the previous layout of the store after the transactions `ids`.
+fn hashed_layout_bytes(ids: &[u32]) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let mut seen_ids = HashSet::new();
+    let mut records = HashMap::new();
+    for tx in transactions(ids) {
+        seen_ids.insert(tx.tx);
+        if let (TransactionType::Deposit, Some(amount)) = (tx.r#type, tx.amount) {
+            let record = LeanTransaction {
+                r#type: tx.r#type,
+                client_id: tx.client,
+                amount,
+                currency: tx.currency,
+            };
+            records.insert(tx.tx, record);
+        }
+    }
+    let bytes = ALLOCATED.load(Ordering::Relaxed) - before;
+    drop((seen_ids, records));
+    bytes
+}
+
+/// Returns the bytes held by `InMemoryTransactionStore` after the transactions `ids`.
+async fn store_bytes(ids: &[u32]) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let store = InMemoryTransactionStore::new();
+    for tx in transactions(ids) {
+        store.store(tx).await.unwrap();
+    }
+    let bytes = ALLOCATED.load(Ordering::Relaxed) - before;
+    drop(store);
+    bytes
+}
+
+/// Measures both layouts, printing their cost per deposit.
+async fn compare(count: u32, ids: Ids) -> (usize, usize) {
+    let ids = ids.generate(count);
+    let _guard = MEASURING.lock().await;
+    let deposits = (ids.len() - ids.len() / 10) as f64;
+    let hashed = hashed_layout_bytes(&ids);
+    let compact = store_bytes(&ids).await;
+    for (label, bytes) in [("HashSet + HashMap", hashed), ("compact", compact)] {
+        println!(
+            "{:>17}: {:>8.1} MiB for {} transactions, {:.1} bytes per deposit",
+            label,
+            bytes as f64 / (1024.0 * 1024.0),
+            ids.len(),
+            bytes as f64 / deposits
+        );
+    }
+    (hashed, compact)
+}
+
+#[tokio::test]
+async fn test_compact_store_memory() {
+    let (hashed, compact) = compare(200_000, Ids::Sequential).await;
+    assert!(
+        compact * 2 < hashed,
+        "compact store uses {} bytes, against {} bytes before",
+        compact,
+        hashed
+    );
+}
+
+#[tokio::test]
+async fn test_compact_store_memory_sparse_ids() {
+    // Isolated ids are kept in sparse pages, at a small multiple of the hash maps rather than
+    // a whole page each
+    for ids in [Ids::Strided, Ids::Random] {
+        let (hashed, compact) = compare(200_000, ids).await;
+        assert!(
+            compact < hashed * 3,
+            "compact store uses {} bytes for {:?} ids, against {} bytes before",
+            compact,
+            ids,
+            hashed
+        );
+    }
+}
+
+#[tokio::test]
+#[ignore = "Triggered manually to check RAM usage"]
+async fn test_compact_store_memory_at_scale() {
+    for ids in [Ids::Sequential, Ids::Strided, Ids::Random] {
+        println!("{:?} ids:", ids);
+        compare(50_000_000, ids).await;
+    }
+}
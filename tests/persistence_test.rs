@@ -41,3 +41,172 @@ fn test_rocksdb_persistence_recovery() {
     // Should have recovered 100.0 and added 50.0 = 150.0
     assert!(stdout2.contains("1,150,0,150,false"));
 }
+
+#[cfg(feature = "storage-rocksdb")]
+#[test]
+fn test_rocksdb_event_log_replay() {
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("test_db");
+
+    // 1. First run: Record the events of a dispute lifecycle
+    let mut csv = tempfile::NamedTempFile::new().unwrap();
+    writeln!(csv, "type, client, tx, amount").unwrap();
+    writeln!(csv, "deposit, 1, 1, 100.0").unwrap();
+    writeln!(csv, "deposit, 2, 2, 20.0").unwrap();
+    writeln!(csv, "withdrawal, 1, 3, 30.0").unwrap();
+    writeln!(csv, "dispute, 2, 2, ").unwrap();
+    writeln!(csv, "chargeback, 2, 2, ").unwrap();
+
+    let mut cmd1 = Command::new(cargo_bin!("hc190aop"));
+    cmd1.arg(csv.path())
+        .arg("--db-path")
+        .arg(&db_path)
+        .arg("--event-sourced");
+
+    let output1 = cmd1.output().expect("Failed to execute command");
+    assert!(output1.status.success());
+
+    // 2. Second run: Rebuild the accounts from the event log only
+    let mut cmd2 = Command::new(cargo_bin!("hc190aop"));
+    cmd2.arg("--db-path").arg(&db_path).arg("--replay");
+
+    let output2 = cmd2.output().expect("Failed to execute command");
+    assert!(output2.status.success());
+    let stdout2 = String::from_utf8_lossy(&output2.stdout);
+    assert!(stdout2.contains("1,70,0,70,false"));
+    assert!(stdout2.contains("2,0,0,0,true"));
+}
+
+#[cfg(feature = "storage-rocksdb")]
+#[test]
+fn test_rocksdb_resume_skips_processed_records() {
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("test_db");
+
+    let mut csv = tempfile::NamedTempFile::new().unwrap();
+    writeln!(csv, "type, client, tx, amount").unwrap();
+    writeln!(csv, "deposit, 1, 1, 100.0").unwrap();
+    writeln!(csv, "dispute, 1, 1, 40.0").unwrap();
+
+    let mut cmd1 = Command::new(cargo_bin!("hc190aop"));
+    cmd1.arg(csv.path()).arg("--db-path").arg(&db_path);
+    let output1 = cmd1.output().expect("Failed to execute command");
+    assert!(output1.status.success());
+    assert!(String::from_utf8_lossy(&output1.stdout).contains("1,60,40,100,false"));
+
+    // Resuming the same input applies nothing twice, not even the partial dispute
+    let mut cmd2 = Command::new(cargo_bin!("hc190aop"));
+    cmd2.arg(csv.path())
+        .arg("--db-path")
+        .arg(&db_path)
+        .arg("--resume");
+    let output2 = cmd2.output().expect("Failed to execute command");
+    assert!(output2.status.success());
+    assert!(String::from_utf8_lossy(&output2.stdout).contains("1,60,40,100,false"));
+
+    // Another input is refused
+    let mut other = tempfile::NamedTempFile::new().unwrap();
+    writeln!(other, "type, client, tx, amount").unwrap();
+    writeln!(other, "deposit, 2, 2, 10.0").unwrap();
+    let mut cmd3 = Command::new(cargo_bin!("hc190aop"));
+    cmd3.arg(other.path())
+        .arg("--db-path")
+        .arg(&db_path)
+        .arg("--resume");
+    let output3 = cmd3.output().expect("Failed to execute command");
+    assert!(!output3.status.success());
+}
+
+#[cfg(feature = "storage-rocksdb")]
+#[test]
+fn test_rocksdb_migrate_command() {
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("test_db");
+
+    let mut csv = tempfile::NamedTempFile::new().unwrap();
+    writeln!(csv, "type, client, tx, amount").unwrap();
+    writeln!(csv, "deposit, 1, 1, 100.0").unwrap();
+
+    let mut cmd1 = Command::new(cargo_bin!("hc190aop"));
+    cmd1.arg(csv.path()).arg("--db-path").arg(&db_path);
+    assert!(cmd1.output().unwrap().status.success());
+
+    // A database written by this release is already up to date
+    let mut cmd2 = Command::new(cargo_bin!("hc190aop"));
+    cmd2.arg("migrate").arg("--db-path").arg(&db_path);
+    let output2 = cmd2.output().expect("Failed to execute command");
+    assert!(output2.status.success());
+    assert!(String::from_utf8_lossy(&output2.stderr).contains("already at schema version"));
+
+    // And still opens after the migration
+    let mut cmd3 = Command::new(cargo_bin!("hc190aop"));
+    cmd3.arg(csv.path()).arg("--db-path").arg(&db_path);
+    let output3 = cmd3.output().expect("Failed to execute command");
+    assert!(output3.status.success());
+}
+
+#[cfg(feature = "storage-rocksdb")]
+#[test]
+fn test_rocksdb_migrate_command_upgrades_baseline_database() {
+    use rocksdb::{ColumnFamilyDescriptor, DB, Options};
+
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("test_db");
+
+    // A database as written by the first release, with a deposit under dispute
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf_descriptors(
+            &opts,
+            &db_path,
+            vec![
+                ColumnFamilyDescriptor::new("accounts", Options::default()),
+                ColumnFamilyDescriptor::new("transactions", Options::default()),
+            ],
+        )
+        .unwrap();
+        let accounts = db.cf_handle("accounts").unwrap();
+        db.put_cf(
+            accounts,
+            1u16.to_be_bytes(),
+            r#"{"client":1,"available":"0","held":"100.0","total":"100.0","locked":false}"#,
+        )
+        .unwrap();
+        let transactions = db.cf_handle("transactions").unwrap();
+        db.put_cf(
+            transactions,
+            1u32.to_be_bytes(),
+            r#"{"type":"deposit","client":1,"tx":1,"amount":"100.0","dispute_status":"Disputed"}"#,
+        )
+        .unwrap();
+    }
+
+    let mut csv = tempfile::NamedTempFile::new().unwrap();
+    writeln!(csv, "type, client, tx, amount").unwrap();
+    writeln!(csv, "resolve, 1, 1, ").unwrap();
+
+    // The database must be migrated before it is used
+    let mut cmd1 = Command::new(cargo_bin!("hc190aop"));
+    cmd1.arg(csv.path()).arg("--db-path").arg(&db_path);
+    let output1 = cmd1.output().expect("Failed to execute command");
+    assert!(!output1.status.success());
+    assert!(String::from_utf8_lossy(&output1.stderr).contains("migrate"));
+
+    let mut cmd2 = Command::new(cargo_bin!("hc190aop"));
+    cmd2.arg("migrate").arg("--db-path").arg(&db_path);
+    let output2 = cmd2.output().expect("Failed to execute command");
+    assert!(output2.status.success());
+    assert!(
+        String::from_utf8_lossy(&output2.stderr)
+            .contains("upgraded from schema version 1 to 3 (2 value(s) rewritten)")
+    );
+
+    // The open dispute survived the migration and can be resolved
+    let mut cmd3 = Command::new(cargo_bin!("hc190aop"));
+    cmd3.arg(csv.path()).arg("--db-path").arg(&db_path);
+    let output3 = cmd3.output().expect("Failed to execute command");
+    assert!(output3.status.success());
+    assert!(String::from_utf8_lossy(&output3.stdout).contains("1,100.0,0.0,100.0,false"));
+}
@@ -0,0 +1,23 @@
+use assert_cmd::cargo_bin;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::io::Write;
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+#[test]
+fn test_transfer_between_clients() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "type, client, tx, amount, destination").unwrap();
+    writeln!(file, "deposit, 1, 1, 100.0, ").unwrap();
+    writeln!(file, "transfer, 1, 2, 40.0, 2").unwrap();
+    writeln!(file, "transfer, 2, 3, 50.0, 1").unwrap(); // Insufficient funds, no effect on either
+
+    let mut cmd = Command::new(cargo_bin!("hc190aop"));
+    cmd.arg(file.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1,60,0,60,false"))
+        .stdout(predicate::str::contains("2,40,0,40,false"));
+}